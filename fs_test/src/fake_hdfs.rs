@@ -0,0 +1,684 @@
+use hdfs::err::Error as HdfsError;
+use hdfs::fs::{FileSystem, MemoryFileSystem};
+use hdfs::hdfs::{ContentSummary, FileStatus};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::vec;
+use walk;
+
+/// Operations an error can be injected into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Stat,
+    List,
+    Open,
+    Create,
+    Delete,
+    Rename,
+    Mkdirs,
+    SetPermission,
+    SetOwner,
+    SetReplication,
+    SetTimes,
+    ContentSummary,
+    SetQuota,
+}
+
+/// Limits set with `hdfs dfsadmin -setQuota` and `-setSpaceQuota`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quota {
+    /// Number of files and directories, the directory itself included
+    pub namespace: Option<u64>,
+    /// Bytes, replicas included
+    pub space: Option<u64>,
+}
+
+type ErrorFactory = Box<dyn Fn() -> HdfsError + Send>;
+
+/// In-memory HDFS for tests, usable both through `hdfs::fs::FileSystem` and by
+/// `walk::walk::Walk`.
+///
+/// ```
+/// # extern crate fs_test;
+/// # extern crate hdfs;
+/// use fs_test::FakeHdfs;
+/// use hdfs::fs::FileSystem;
+/// use std::path::Path;
+///
+/// let fs = FakeHdfs::new().dir("/data").file("/data/part-0", b"content");
+/// assert_eq!(7, fs.stat(Path::new("/data/part-0")).unwrap().len);
+/// ```
+pub struct FakeHdfs {
+    inner: MemoryFileSystem,
+    quotas: Mutex<HashMap<PathBuf, Quota>>,
+    errors: Mutex<HashMap<(Operation, PathBuf), ErrorFactory>>,
+}
+
+impl Default for FakeHdfs {
+    fn default() -> FakeHdfs {
+        FakeHdfs::new()
+    }
+}
+
+impl FakeHdfs {
+    /// An empty namespace owned by `hdfs:supergroup`, the working directory is `/user/hdfs`.
+    pub fn new() -> FakeHdfs {
+        FakeHdfs::with_user("hdfs", "supergroup")
+    }
+
+    pub fn with_user(user: &str, group: &str) -> FakeHdfs {
+        FakeHdfs {
+            inner: MemoryFileSystem::new(user, group),
+            quotas: Mutex::new(HashMap::new()),
+            errors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn dir(self, path: &str) -> FakeHdfs {
+        self.inner.mkdirs(Path::new(path)).unwrap();
+        self
+    }
+
+    pub fn file(self, path: &str, content: &[u8]) -> FakeHdfs {
+        self.inner.add_file(path, content).unwrap();
+        self
+    }
+
+    /// Adds a file with the given attributes, `len` zeroed bytes of content.
+    pub fn file_with(
+        self,
+        path: &str,
+        len: usize,
+        owner: &str,
+        permission: u16,
+        replication: u16,
+        block_size: u64,
+    ) -> FakeHdfs {
+        let path_ref = Path::new(path);
+        self.inner.add_file(path, &vec![0u8; len]).unwrap();
+        self.inner.set_owner(path_ref, Some(owner), None).unwrap();
+        self.inner.set_permission(path_ref, permission).unwrap();
+        self.inner.set_replication(path_ref, replication).unwrap();
+        self.inner.set_block_size(path_ref, block_size).unwrap();
+        self
+    }
+
+    pub fn quota(self, path: &str, quota: Quota) -> FakeHdfs {
+        self.set_quota(path, quota);
+        self
+    }
+
+    pub fn set_quota<P: AsRef<Path>>(&self, path: P, quota: Quota) {
+        let path = self.absolute(path.as_ref());
+        let mut quotas = self.quotas.lock().unwrap();
+        if quota == Quota::default() {
+            quotas.remove(&path);
+        } else {
+            quotas.insert(path, quota);
+        }
+    }
+
+    pub fn get_quota<P: AsRef<Path>>(&self, path: P) -> Option<Quota> {
+        let path = self.absolute(path.as_ref());
+        self.quotas.lock().unwrap().get(&path).cloned()
+    }
+
+    /// The quota of a directory about to be changed, HDFS refusing them on files.
+    fn quota_of_dir(&self, path: &Path) -> Result<Quota, HdfsError> {
+        self.check(Operation::SetQuota, path)?;
+        if !self.inner.stat(path)?.is_dir() {
+            return Err(HdfsError::FileNotFound(format!(
+                "Cannot set quota on a file: {}",
+                path.display()
+            )));
+        }
+        Ok(self.get_quota(path).unwrap_or_default())
+    }
+
+    pub fn set_block_size<P: AsRef<Path>>(
+        &self,
+        path: P,
+        block_size: u64,
+    ) -> Result<(), HdfsError> {
+        self.inner.set_block_size(path, block_size)
+    }
+
+    /// Every `operation` on exactly `path` fails with the error built by `error`
+    /// until `clear_errors` is called.
+    pub fn inject_error<P, F>(&self, operation: Operation, path: P, error: F)
+    where
+        P: AsRef<Path>,
+        F: Fn() -> HdfsError + Send + 'static,
+    {
+        let path = self.absolute(path.as_ref());
+        self.errors
+            .lock()
+            .unwrap()
+            .insert((operation, path), Box::new(error));
+    }
+
+    pub fn clear_errors(&self) {
+        self.errors.lock().unwrap().clear();
+    }
+
+    /// Files and directories under `path`, itself included, and the space they use.
+    pub fn usage<P: AsRef<Path>>(&self, path: P) -> Result<(u64, u64), HdfsError> {
+        let status = self.inner.stat(path.as_ref())?;
+        let mut count = 1;
+        let mut space = status.len * u64::from(status.replication);
+        if status.is_dir() {
+            for child in self.inner.list(path.as_ref())? {
+                let (child_count, child_space) = self.usage(&child.path)?;
+                count += child_count;
+                space += child_space;
+            }
+        }
+        Ok((count, space))
+    }
+
+    fn absolute(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_owned()
+        } else {
+            self.inner.current_dir().unwrap().join(path)
+        }
+    }
+
+    fn check(&self, operation: Operation, path: &Path) -> Result<(), HdfsError> {
+        let path = self.absolute(path);
+        match self.errors.lock().unwrap().get(&(operation, path)) {
+            Some(error) => Err(error()),
+            None => Ok(()),
+        }
+    }
+
+    /// Quotas set on `path` or its ancestors, but not on `except` or its ancestors.
+    fn quotas_above(&self, path: &Path, except: Option<&Path>) -> Vec<(PathBuf, Quota)> {
+        let path = self.absolute(path);
+        let except = except.map(|p| self.absolute(p));
+        self.quotas
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(dir, _)| path.starts_with(dir))
+            .filter(|(dir, _)| except.as_ref().is_none_or(|e| !e.starts_with(dir)))
+            .map(|(dir, quota)| (dir.to_owned(), *quota))
+            .collect()
+    }
+
+    /// Checks adding `count` entries and `space` bytes under `path` stays within quotas.
+    fn check_quotas(
+        &self,
+        path: &Path,
+        except: Option<&Path>,
+        count: u64,
+        space: u64,
+    ) -> Result<(), HdfsError> {
+        for (dir, quota) in self.quotas_above(path, except) {
+            let (used_count, used_space) = self.usage(&dir)?;
+            if let Some(namespace) = quota.namespace {
+                if used_count + count > namespace {
+                    return Err(HdfsError::RemoteException(
+                        String::from("NSQuotaExceededException"),
+                        format!(
+                            "The NameSpace quota of {} is exceeded: quota={} file count={}",
+                            dir.display(),
+                            namespace,
+                            used_count + count
+                        ),
+                    ));
+                }
+            }
+            if let Some(quota) = quota.space {
+                if used_space + space > quota {
+                    return Err(HdfsError::RemoteException(
+                        String::from("DSQuotaExceededException"),
+                        format!(
+                            "The DiskSpace quota of {} is exceeded: quota={} diskspace consumed={}",
+                            dir.display(),
+                            quota,
+                            used_space + space
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of entries `mkdirs` or `create` would add for `path`.
+    fn missing_entries(&self, path: &Path) -> u64 {
+        let path = self.absolute(path);
+        path.ancestors()
+            .take_while(|p| !self.inner.exists(p).unwrap_or(true))
+            .count() as u64
+    }
+}
+
+/// Enforces space quotas as data is written.
+struct QuotaWriter<'a> {
+    fs: &'a FakeHdfs,
+    path: PathBuf,
+    replication: u64,
+    inner: Box<dyn Write + 'a>,
+}
+
+impl<'a> Write for QuotaWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fs
+            .check_quotas(&self.path, None, 0, buf.len() as u64 * self.replication)
+            .map_err(|e| io::Error::other(format!("{:?}", e)))?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl FileSystem for FakeHdfs {
+    fn current_dir(&self) -> Result<PathBuf, HdfsError> {
+        self.inner.current_dir()
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, HdfsError> {
+        self.check(Operation::Stat, path)?;
+        self.inner.stat(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, HdfsError> {
+        self.check(Operation::List, path)?;
+        self.inner.list(path)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, HdfsError> {
+        self.check(Operation::Open, path)?;
+        self.inner.open(path)
+    }
+
+    fn create<'a>(
+        &'a self,
+        path: &Path,
+        overwrite: bool,
+    ) -> Result<Box<dyn Write + 'a>, HdfsError> {
+        self.check(Operation::Create, path)?;
+        let count = self.missing_entries(path);
+        self.check_quotas(path, None, count, 0)?;
+        let inner = self.inner.create(path, overwrite)?;
+        let replication = u64::from(self.inner.stat(path)?.replication);
+        Ok(Box::new(QuotaWriter {
+            fs: self,
+            path: path.to_owned(),
+            replication,
+            inner,
+        }))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, HdfsError> {
+        self.check(Operation::Delete, path)?;
+        let deleted = self.inner.delete(path, recursive)?;
+        if deleted {
+            let path = self.absolute(path);
+            self.quotas
+                .lock()
+                .unwrap()
+                .retain(|dir, _| !dir.starts_with(&path));
+        }
+        Ok(deleted)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), HdfsError> {
+        self.check(Operation::Rename, from)?;
+        let (count, space) = self.usage(from)?;
+        let parent = to.parent().unwrap_or(to);
+        self.check_quotas(parent, from.parent(), count, space)?;
+        self.inner.rename(from, to)?;
+
+        let (from, to) = (self.absolute(from), self.absolute(to));
+        let mut quotas = self.quotas.lock().unwrap();
+        let moved: Vec<_> = quotas
+            .keys()
+            .filter(|dir| dir.starts_with(&from))
+            .cloned()
+            .collect();
+        for dir in moved {
+            let quota = quotas.remove(&dir).unwrap();
+            quotas.insert(to.join(dir.strip_prefix(&from).unwrap()), quota);
+        }
+        Ok(())
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, HdfsError> {
+        self.check(Operation::Mkdirs, path)?;
+        let count = self.missing_entries(path);
+        self.check_quotas(path, None, count, 0)?;
+        self.inner.mkdirs(path)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), HdfsError> {
+        self.check(Operation::SetPermission, path)?;
+        self.inner.set_permission(path, permission)
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), HdfsError> {
+        self.check(Operation::SetOwner, path)?;
+        self.inner.set_owner(path, owner, group)
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), HdfsError> {
+        self.check(Operation::SetReplication, path)?;
+        let status = self.inner.stat(path)?;
+        if status.is_file() && replication > status.replication {
+            let extra = status.len * u64::from(replication - status.replication);
+            self.check_quotas(path, None, 0, extra)?;
+        }
+        self.inner.set_replication(path, replication)
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), HdfsError> {
+        self.check(Operation::SetTimes, path)?;
+        self.inner.set_times(path, modification_time, access_time)
+    }
+
+    fn content_summary(&self, path: &Path) -> Result<ContentSummary, HdfsError> {
+        self.check(Operation::ContentSummary, path)?;
+        let quota = self.get_quota(path).unwrap_or_default();
+        Ok(ContentSummary {
+            has_quotas: true,
+            quota: quota.namespace,
+            space_quota: quota.space,
+            ..self.inner.content_summary(path)?
+        })
+    }
+
+    fn set_namespace_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), HdfsError> {
+        let current = self.quota_of_dir(path)?;
+        self.set_quota(
+            path,
+            Quota {
+                namespace: quota,
+                ..current
+            },
+        );
+        Ok(())
+    }
+
+    fn set_space_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), HdfsError> {
+        let current = self.quota_of_dir(path)?;
+        self.set_quota(
+            path,
+            Quota {
+                space: quota,
+                ..current
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Errors seen by `walk`, which needs its own errors to convert into them.
+#[derive(Debug)]
+pub enum Error {
+    Walk(walk::err::Error),
+    Hdfs(HdfsError),
+}
+
+impl From<walk::err::Error> for Error {
+    fn from(e: walk::err::Error) -> Error {
+        Error::Walk(e)
+    }
+}
+
+impl From<HdfsError> for Error {
+    fn from(e: HdfsError) -> Error {
+        Error::Hdfs(e)
+    }
+}
+
+pub struct Entry(FileStatus);
+
+impl Entry {
+    pub fn status(&self) -> &FileStatus {
+        &self.0
+    }
+}
+
+fn time(millis: u64) -> Option<SystemTime> {
+    match millis {
+        0 => None,
+        millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+    }
+}
+
+impl walk::walk::DirEntryTrait for Entry {
+    fn path(&self) -> PathBuf {
+        self.0.path()
+    }
+}
+
+impl walk::walk::MetadataTrait for Entry {
+    fn is_dir(&self) -> bool {
+        self.0.is_dir()
+    }
+
+    fn len(&self) -> u64 {
+        self.0.len
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        time(self.0.modification_time)
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        time(self.0.access_time)
+    }
+
+    fn permissions(&self) -> Option<u32> {
+        Some(u32::from(self.0.permission))
+    }
+
+    fn owner(&self) -> Option<String> {
+        Some(self.0.owner.to_owned())
+    }
+
+    fn group(&self) -> Option<String> {
+        Some(self.0.group.to_owned())
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.0.symlink.is_some()
+    }
+
+    fn symlink_target(&self) -> Option<PathBuf> {
+        self.0.symlink.clone()
+    }
+
+    fn replication(&self) -> Option<u16> {
+        Some(self.0.replication)
+    }
+}
+
+impl walk::walk::FileSystem for FakeHdfs {
+    type Error = Error;
+    type DirEntry = Entry;
+    type ReadDir = vec::IntoIter<Result<Entry, Error>>;
+    type Metadata = Entry;
+
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        FileSystem::current_dir(self).map_err(Error::from)
+    }
+
+    fn exists(&self, path: &PathBuf) -> bool {
+        FileSystem::exists(self, path).unwrap_or(false)
+    }
+
+    fn read_dir(&self, path: &PathBuf) -> Result<Self::ReadDir, Error> {
+        let entries: Vec<_> = self
+            .list(path)?
+            .into_iter()
+            .map(|status| Ok(Entry(status)))
+            .collect();
+        Ok(entries.into_iter())
+    }
+
+    fn metadata(&self, path: &PathBuf) -> Result<Entry, Error> {
+        Ok(Entry(self.stat(path)?))
+    }
+
+    fn read_to_string(&self, path: &PathBuf) -> Result<String, Error> {
+        let mut content = String::new();
+        self.open(path)?
+            .read_to_string(&mut content)
+            .map_err(HdfsError::from)?;
+        Ok(content)
+    }
+
+    fn is_not_found(&self, error: &Error) -> bool {
+        matches!(*error, Error::Hdfs(HdfsError::FileNotFound(_)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FakeHdfs, Operation, Quota};
+    use hdfs::err::Error;
+    use hdfs::fs::FileSystem;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn remote_class<T>(result: Result<T, Error>) -> String {
+        match result {
+            Err(Error::RemoteException(class, _)) => class,
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_attributes() {
+        let fs = FakeHdfs::new().file_with("/data/a", 10, "etl", 0o600, 2, 1024);
+
+        let status = fs.stat(Path::new("/data/a")).unwrap();
+        assert_eq!(10, status.len);
+        assert_eq!("etl", status.owner);
+        assert_eq!("supergroup", status.group);
+        assert_eq!(0o600, status.permission);
+        assert_eq!(2, status.replication);
+        assert_eq!(1024, status.block_size);
+        assert_eq!((3, 20), fs.usage("/").unwrap());
+    }
+
+    #[test]
+    fn test_namespace_quota() {
+        let fs = FakeHdfs::new().dir("/q").quota(
+            "/q",
+            Quota {
+                namespace: Some(3),
+                space: None,
+            },
+        );
+
+        fs.create(Path::new("/q/a"), false).unwrap();
+        assert_eq!(
+            "NSQuotaExceededException",
+            remote_class(fs.mkdirs(Path::new("/q/b/c")))
+        );
+        fs.mkdirs(Path::new("/q/b")).unwrap();
+        assert_eq!(
+            "NSQuotaExceededException",
+            remote_class(fs.create(Path::new("/q/c"), false))
+        );
+
+        let fs = fs.dir("/other/d");
+        assert_eq!(
+            "NSQuotaExceededException",
+            remote_class(fs.rename(Path::new("/other/d"), Path::new("/q/d")))
+        );
+        fs.delete(Path::new("/q/a"), false).unwrap();
+        fs.rename(Path::new("/other/d"), Path::new("/q/d")).unwrap();
+    }
+
+    #[test]
+    fn test_space_quota() {
+        let fs = FakeHdfs::new().dir("/q").quota(
+            "/q",
+            Quota {
+                namespace: None,
+                space: Some(100),
+            },
+        );
+
+        let mut writer = fs.create(Path::new("/q/a"), false).unwrap();
+        writer.write_all(&[0u8; 30]).unwrap();
+        let error = writer.write_all(&[0u8; 10]).unwrap_err();
+        assert!(error.to_string().contains("DSQuotaExceededException"));
+        drop(writer);
+
+        assert_eq!(30, fs.stat(Path::new("/q/a")).unwrap().len);
+        assert_eq!(
+            "DSQuotaExceededException",
+            remote_class(fs.set_replication(Path::new("/q/a"), 4))
+        );
+        fs.set_replication(Path::new("/q/a"), 1).unwrap();
+        assert_eq!(Some(100), fs.get_quota("/q").unwrap().space);
+
+        let summary = fs.content_summary(Path::new("/q")).unwrap();
+        assert_eq!(
+            (30, 1, 1),
+            (summary.length, summary.file_count, summary.directory_count)
+        );
+        assert_eq!(
+            (30, None, Some(100)),
+            (summary.space_consumed, summary.quota, summary.space_quota)
+        );
+
+        fs.set_namespace_quota(Path::new("/q"), Some(5)).unwrap();
+        fs.set_space_quota(Path::new("/q"), None).unwrap();
+        assert_eq!(
+            Some(Quota {
+                namespace: Some(5),
+                space: None,
+            }),
+            fs.get_quota("/q")
+        );
+        fs.set_namespace_quota(Path::new("/q"), None).unwrap();
+        assert_eq!(None, fs.get_quota("/q"));
+        match fs.set_space_quota(Path::new("/q/a"), Some(1)) {
+            Err(Error::FileNotFound(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_injected_errors() {
+        let fs = FakeHdfs::new().dir("/data").file("/data/a", b"");
+        fs.inject_error(Operation::List, "/data", || {
+            Error::RemoteException(
+                String::from("AccessControlException"),
+                String::from("denied"),
+            )
+        });
+
+        assert_eq!(
+            "AccessControlException",
+            remote_class(fs.list(Path::new("/data")))
+        );
+        assert!(fs.stat(Path::new("/data")).is_ok());
+
+        fs.clear_errors();
+        assert_eq!(1, fs.list(Path::new("/data")).unwrap().len());
+    }
+}
@@ -0,0 +1,414 @@
+use hdfs::err::Error as HdfsError;
+use hdfs::fs::FileSystem;
+use hdfs::hdfs::{ContentSummary, FileStatus};
+use rand::prelude::*;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use walk;
+
+/// What happens to a single call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    None,
+    /// Metadata calls fail like on a standby namenode
+    Standby,
+    /// Reads and writes fail with a connection reset
+    Drop,
+    /// Reads and writes transfer only part of the buffer
+    Partial,
+    /// The call is delayed
+    Latency(Duration),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub calls: usize,
+    pub standby: usize,
+    pub drops: usize,
+    pub partial: usize,
+}
+
+struct State {
+    rng: StdRng,
+    script: VecDeque<Fault>,
+    stats: Stats,
+}
+
+/// Wraps a file system and makes it fail on purpose.
+///
+/// Faults are drawn from a random generator seeded by the caller, so a failing run can
+/// be replayed, unless a script is given: its faults are then applied in order, one per
+/// call, metadata calls and each `read`/`write` alike.
+pub struct FaultyFs<F> {
+    inner: F,
+    latency: Option<Duration>,
+    standby_rate: f64,
+    drop_rate: f64,
+    partial_io: bool,
+    state: Mutex<State>,
+}
+
+fn standby_error() -> HdfsError {
+    HdfsError::RemoteException(
+        String::from("StandbyException"),
+        String::from("Operation category READ is not supported in state standby"),
+    )
+}
+
+impl<F> FaultyFs<F> {
+    pub fn new(inner: F, seed: u64) -> FaultyFs<F> {
+        FaultyFs {
+            inner,
+            latency: None,
+            standby_rate: 0.0,
+            drop_rate: 0.0,
+            partial_io: false,
+            state: Mutex::new(State {
+                rng: StdRng::seed_from_u64(seed),
+                script: VecDeque::new(),
+                stats: Stats::default(),
+            }),
+        }
+    }
+
+    pub fn latency(mut self, latency: Duration) -> FaultyFs<F> {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Probability for a metadata call to hit a standby namenode.
+    pub fn standby_rate(mut self, rate: f64) -> FaultyFs<F> {
+        self.standby_rate = rate;
+        self
+    }
+
+    /// Probability for a read or a write to lose its connection.
+    pub fn drop_rate(mut self, rate: f64) -> FaultyFs<F> {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Reads and writes transfer a random part of the buffer.
+    pub fn partial_io(mut self, partial_io: bool) -> FaultyFs<F> {
+        self.partial_io = partial_io;
+        self
+    }
+
+    pub fn script(self, faults: Vec<Fault>) -> FaultyFs<F> {
+        self.state.lock().unwrap().script = faults.into_iter().collect();
+        self
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.state.lock().unwrap().stats
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn next_fault(&self, io: bool) -> Fault {
+        let fault = {
+            let mut state = self.state.lock().unwrap();
+            state.stats.calls += 1;
+            let fault = match state.script.pop_front() {
+                Some(fault) => fault,
+                None if io && state.rng.gen_bool(self.drop_rate) => Fault::Drop,
+                None if io && self.partial_io => Fault::Partial,
+                None if !io && state.rng.gen_bool(self.standby_rate) => Fault::Standby,
+                None => Fault::None,
+            };
+            match fault {
+                Fault::Standby => state.stats.standby += 1,
+                Fault::Drop => state.stats.drops += 1,
+                Fault::Partial => state.stats.partial += 1,
+                _ => {}
+            }
+            fault
+        };
+        if let Some(latency) = self.latency {
+            thread::sleep(latency);
+        }
+        if let Fault::Latency(latency) = fault {
+            thread::sleep(latency);
+        }
+        fault
+    }
+
+    fn metadata_call(&self) -> Result<(), HdfsError> {
+        match self.next_fault(false) {
+            Fault::Standby => Err(standby_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Length a partial transfer of `len` bytes stops at.
+    fn partial_len(&self, len: usize) -> usize {
+        if len <= 1 {
+            return len;
+        }
+        self.state.lock().unwrap().rng.gen_range(1, len)
+    }
+}
+
+fn connection_reset() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionReset,
+        "connection dropped by fault injection",
+    )
+}
+
+struct FaultyReader<'a, F: 'a> {
+    fs: &'a FaultyFs<F>,
+    inner: Box<dyn Read + 'a>,
+}
+
+impl<'a, F> Read for FaultyReader<'a, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.fs.next_fault(true) {
+            Fault::Drop => Err(connection_reset()),
+            Fault::Partial => {
+                let len = self.fs.partial_len(buf.len());
+                self.inner.read(&mut buf[..len])
+            }
+            _ => self.inner.read(buf),
+        }
+    }
+}
+
+struct FaultyWriter<'a, F: 'a> {
+    fs: &'a FaultyFs<F>,
+    inner: Box<dyn Write + 'a>,
+}
+
+impl<'a, F> Write for FaultyWriter<'a, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.fs.next_fault(true) {
+            Fault::Drop => Err(connection_reset()),
+            Fault::Partial => {
+                let len = self.fs.partial_len(buf.len());
+                self.inner.write(&buf[..len])
+            }
+            _ => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<F: FileSystem> FileSystem for FaultyFs<F> {
+    fn current_dir(&self) -> Result<PathBuf, HdfsError> {
+        self.metadata_call()?;
+        self.inner.current_dir()
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, HdfsError> {
+        self.metadata_call()?;
+        self.inner.stat(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, HdfsError> {
+        self.metadata_call()?;
+        self.inner.list(path)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, HdfsError> {
+        self.metadata_call()?;
+        let inner = self.inner.open(path)?;
+        Ok(Box::new(FaultyReader { fs: self, inner }))
+    }
+
+    fn create<'a>(
+        &'a self,
+        path: &Path,
+        overwrite: bool,
+    ) -> Result<Box<dyn Write + 'a>, HdfsError> {
+        self.metadata_call()?;
+        let inner = self.inner.create(path, overwrite)?;
+        Ok(Box::new(FaultyWriter { fs: self, inner }))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, HdfsError> {
+        self.metadata_call()?;
+        self.inner.delete(path, recursive)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.rename(from, to)
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, HdfsError> {
+        self.metadata_call()?;
+        self.inner.mkdirs(path)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.set_permission(path, permission)
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.set_owner(path, owner, group)
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.set_replication(path, replication)
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.set_times(path, modification_time, access_time)
+    }
+
+    fn content_summary(&self, path: &Path) -> Result<ContentSummary, HdfsError> {
+        self.metadata_call()?;
+        self.inner.content_summary(path)
+    }
+
+    fn set_namespace_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.set_namespace_quota(path, quota)
+    }
+
+    fn set_space_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), HdfsError> {
+        self.metadata_call()?;
+        self.inner.set_space_quota(path, quota)
+    }
+}
+
+impl<F> walk::walk::FileSystem for FaultyFs<F>
+where
+    F: walk::walk::FileSystem,
+    F::Error: From<HdfsError>,
+{
+    type Error = F::Error;
+    type DirEntry = F::DirEntry;
+    type ReadDir = F::ReadDir;
+    type Metadata = F::Metadata;
+
+    fn current_dir(&self) -> Result<PathBuf, Self::Error> {
+        self.metadata_call()?;
+        self.inner.current_dir()
+    }
+
+    /// `exists` cannot fail, a standby namenode makes the path look missing.
+    fn exists(&self, path: &PathBuf) -> bool {
+        self.metadata_call().is_ok() && self.inner.exists(path)
+    }
+
+    fn read_dir(&self, path: &PathBuf) -> Result<Self::ReadDir, Self::Error> {
+        self.metadata_call()?;
+        self.inner.read_dir(path)
+    }
+
+    fn metadata(&self, path: &PathBuf) -> Result<Self::Metadata, Self::Error> {
+        self.metadata_call()?;
+        self.inner.metadata(path)
+    }
+
+    fn read_to_string(&self, path: &PathBuf) -> Result<String, Self::Error> {
+        self.metadata_call()?;
+        self.inner.read_to_string(path)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        self.inner.is_not_found(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Fault, FaultyFs};
+    use fake_hdfs::FakeHdfs;
+    use hdfs::err::Error;
+    use hdfs::fs::FileSystem;
+    use std::io::{self, Read, Write};
+    use std::path::Path;
+
+    fn content() -> Vec<u8> {
+        (0..10_000).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_script() {
+        let fs = FaultyFs::new(FakeHdfs::new().dir("/data"), 0).script(vec![
+            Fault::Standby,
+            Fault::None,
+            Fault::Standby,
+        ]);
+        let path = Path::new("/data");
+
+        match fs.stat(path) {
+            Err(Error::RemoteException(class, _)) => assert_eq!("StandbyException", class),
+            other => panic!("unexpected {:?}", other.map(|s| s.path)),
+        }
+        assert!(fs.stat(path).is_ok());
+        assert!(fs.list(path).is_err());
+        assert!(fs.list(path).is_ok());
+        assert_eq!(2, fs.stats().standby);
+        assert_eq!(4, fs.stats().calls);
+    }
+
+    #[test]
+    fn test_partial_reads_and_short_writes() {
+        let data = content();
+        let fs = FaultyFs::new(FakeHdfs::new(), 42).partial_io(true);
+        let path = Path::new("/file");
+
+        fs.create(path, false).unwrap().write_all(&data).unwrap();
+        let mut read = vec![];
+        fs.open(path).unwrap().read_to_end(&mut read).unwrap();
+
+        assert_eq!(data, read);
+        assert!(fs.stats().partial > 2);
+    }
+
+    #[test]
+    fn test_connection_drop() {
+        let fs = FaultyFs::new(FakeHdfs::new().file("/file", &content()), 0).script(vec![
+            Fault::None,
+            Fault::None,
+            Fault::Drop,
+        ]);
+        let mut reader = fs.open(Path::new("/file")).unwrap();
+
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf).unwrap();
+        let error = reader.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionReset, error.kind());
+        assert_eq!(1, fs.stats().drops);
+    }
+
+    #[test]
+    fn test_same_seed_same_schedule() {
+        let schedule = |seed| {
+            let fs = FaultyFs::new(FakeHdfs::new(), seed).standby_rate(0.5);
+            (0..50)
+                .map(|_| fs.stat(Path::new("/")).is_ok())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(schedule(7), schedule(7));
+        assert_ne!(schedule(7), schedule(8));
+        assert!(schedule(7).contains(&false));
+    }
+}
@@ -1,10 +1,18 @@
+extern crate hdfs;
 extern crate rand;
+extern crate walk;
+
+mod fake_hdfs;
+mod faulty;
 
 use rand::prelude::*;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+pub use fake_hdfs::{Entry, Error, FakeHdfs, Operation, Quota};
+pub use faulty::{Fault, FaultyFs, Stats};
+
 pub struct TempDir(PathBuf);
 
 impl TempDir {
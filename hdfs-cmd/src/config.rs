@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct Config {
     pub hadoop: Option<Hadoop>,
     pub gateway: Option<Gateway>,
+    pub httpfs: Option<HashMap<String, HttpFs>>,
+    pub retry: Option<Retry>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
@@ -14,6 +19,64 @@ pub struct Gateway {
     pub default: Option<String>,
 }
 
+/// HttpFS gateway used instead of the namenode for a given gateway name
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct HttpFs {
+    pub url: String,
+    pub user: Option<String>,
+    pub delegation: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+}
+
+/// Overrides the `dfs.client.retry.*` keys of the hadoop config
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Retry {
+    pub max_attempts: Option<u32>,
+    pub base_delay_ms: Option<u64>,
+    pub max_delay_ms: Option<u64>,
+    pub exceptions: Option<Vec<String>>,
+}
+
+impl Config {
+    pub fn httpfs(&self, gateway: Option<&str>) -> Option<&HttpFs> {
+        let gateway = gateway?;
+        self.httpfs.as_ref().and_then(|h| h.get(gateway))
+    }
+}
+
+impl Retry {
+    pub fn apply(&self, policy: hdfs::fs::RetryPolicy) -> hdfs::fs::RetryPolicy {
+        hdfs::fs::RetryPolicy {
+            max_attempts: self.max_attempts.unwrap_or(policy.max_attempts).max(1),
+            base_delay: self
+                .base_delay_ms
+                .map(Duration::from_millis)
+                .unwrap_or(policy.base_delay),
+            max_delay: self
+                .max_delay_ms
+                .map(Duration::from_millis)
+                .unwrap_or(policy.max_delay),
+            retryable: self.exceptions.clone().unwrap_or(policy.retryable),
+        }
+    }
+}
+
+impl HttpFs {
+    pub fn to_options(&self) -> hdfs::webhdfs::WebHdfsOptions {
+        hdfs::webhdfs::WebHdfsOptions {
+            url: self.url.to_owned(),
+            user: self.user.to_owned(),
+            delegation: self.delegation.to_owned(),
+            headers: self
+                .headers
+                .iter()
+                .flat_map(|h| h.iter())
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -32,4 +95,50 @@ mod test {
         let deserialized: Config = toml::from_str(t).unwrap();
         println!("{:?}", deserialized);
     }
+
+    #[test]
+    fn test_httpfs() {
+        let t = "
+        [gateway]
+        default = \"prod\"
+
+        [httpfs.prod]
+        url = \"http://gateway:14000\"
+        user = \"etl\"
+
+        [httpfs.prod.headers]
+        X-Proxy-Auth = \"secret\"
+        ";
+
+        let deserialized: Config = toml::from_str(t).unwrap();
+        assert!(deserialized.httpfs(None).is_none());
+        assert!(deserialized.httpfs(Some("preprod")).is_none());
+
+        let options = deserialized.httpfs(Some("prod")).unwrap().to_options();
+        assert_eq!("http://gateway:14000", options.url);
+        assert_eq!(Some(String::from("etl")), options.user);
+        assert_eq!(None, options.delegation);
+        assert_eq!(
+            vec![(String::from("X-Proxy-Auth"), String::from("secret"))],
+            options.headers
+        );
+    }
+
+    #[test]
+    fn test_retry() {
+        let t = "
+        [retry]
+        max_attempts = 3
+        base_delay_ms = 100
+        ";
+
+        let deserialized: Config = toml::from_str(t).unwrap();
+        let policy = deserialized
+            .retry
+            .unwrap()
+            .apply(hdfs::fs::RetryPolicy::default());
+        assert_eq!(3, policy.max_attempts);
+        assert_eq!(Duration::from_millis(100), policy.base_delay);
+        assert_eq!(hdfs::fs::RetryPolicy::default().max_delay, policy.max_delay);
+    }
 }
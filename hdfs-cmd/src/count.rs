@@ -0,0 +1,237 @@
+use du::human_size;
+use err::Error;
+use hdfs::err::Error as HdfsError;
+use hdfs::hdfs::ContentSummary;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Directories, files and bytes under paths, like `hdfs dfs -count`, with the quotas
+/// and what remains of them in front.
+#[derive(Default)]
+pub struct Count {
+    quotas: bool,
+    human: bool,
+    header: bool,
+}
+
+impl Count {
+    pub fn new() -> Count {
+        Count::default()
+    }
+
+    /// Adds the QUOTA, REM_QUOTA, SPACE_QUOTA and REM_SPACE_QUOTA columns.
+    pub fn quotas(&mut self, quotas: bool) {
+        self.quotas = quotas;
+    }
+
+    pub fn human(&mut self, human: bool) {
+        self.human = human;
+    }
+
+    /// Prints the names of the columns first.
+    pub fn header(&mut self, header: bool) {
+        self.header = header;
+    }
+
+    /// Prints a line per path, returns the number of paths that failed. Quotas fail as
+    /// unsupported with backends that do not report them.
+    pub fn run(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        paths: &[PathBuf],
+        out: &mut dyn Write,
+    ) -> Result<usize, Error> {
+        if self.header {
+            let mut header = String::new();
+            if self.quotas {
+                header.push_str(&format!(
+                    "{:>12} {:>15} {:>15} {:>15} ",
+                    "QUOTA", "REM_QUOTA", "SPACE_QUOTA", "REM_SPACE_QUOTA"
+                ));
+            }
+            header.push_str(&format!(
+                "{:>12} {:>12} {:>18} PATHNAME",
+                "DIR_COUNT", "FILE_COUNT", "CONTENT_SIZE"
+            ));
+            writeln!(out, "{}", header)?;
+        }
+        let mut failed = 0;
+        for path in paths {
+            match hdfs_fs.content_summary(path) {
+                Ok(ref summary) if self.quotas && !summary.has_quotas => {
+                    return Err(
+                        HdfsError::Unsupported(format!("quotas of {}", path.display())).into(),
+                    )
+                }
+                Ok(summary) => writeln!(out, "{}", self.line(&summary, path))?,
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("count: {}: {}", path.display(), e);
+                }
+            }
+        }
+        Ok(failed)
+    }
+
+    /// The columns of `hdfs dfs -count`, unset quotas showing as `none` and `inf`.
+    pub fn line(&self, summary: &ContentSummary, path: &Path) -> String {
+        let mut line = String::new();
+        if self.quotas {
+            let used = summary.directory_count + summary.file_count;
+            let (quota, remaining) = self.quota(summary.quota, used);
+            line.push_str(&format!("{:>12} {:>15} ", quota, remaining));
+            let (quota, remaining) = self.quota(summary.space_quota, summary.space_consumed);
+            line.push_str(&format!("{:>15} {:>15} ", quota, remaining));
+        }
+        line.push_str(&format!(
+            "{:>12} {:>12} {:>18} {}",
+            self.number(summary.directory_count),
+            self.number(summary.file_count),
+            self.number(summary.length),
+            path.display()
+        ));
+        line
+    }
+
+    /// The quota and what remains of it, negative once exceeded.
+    fn quota(&self, quota: Option<u64>, used: u64) -> (String, String) {
+        match quota {
+            Some(quota) if quota >= used => (self.number(quota), self.number(quota - used)),
+            Some(quota) => (
+                self.number(quota),
+                format!("-{}", self.number(used - quota)),
+            ),
+            None => (String::from("none"), String::from("inf")),
+        }
+    }
+
+    fn number(&self, number: u64) -> String {
+        if self.human {
+            human_size(number)
+        } else {
+            number.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Count;
+    use err;
+    use fs_test::{FakeHdfs, Quota};
+    use hdfs::err::Error;
+    use hdfs::fs::MemoryFileSystem;
+    use std::path::PathBuf;
+
+    fn tree() -> FakeHdfs {
+        FakeHdfs::new()
+            .file_with("/data/a/part-0", 1000, "etl", 0o644, 3, 1024)
+            .file_with("/data/a/part-1", 2000, "etl", 0o644, 3, 1024)
+            .file_with("/data/b/part-0", 30, "etl", 0o644, 2, 1024)
+            .quota(
+                "/data/a",
+                Quota {
+                    namespace: Some(10),
+                    space: Some(4096),
+                },
+            )
+            .quota(
+                "/data/b",
+                Quota {
+                    namespace: None,
+                    space: Some(1 << 30),
+                },
+            )
+    }
+
+    fn count(fs: &FakeHdfs, paths: &[&str], count: &Count) -> Vec<String> {
+        let paths: Vec<_> = paths.iter().map(PathBuf::from).collect();
+        let mut out = vec![];
+        let failed = count.run(fs, &paths, &mut out).unwrap();
+        assert_eq!(0, failed);
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+
+    #[test]
+    fn test_count() {
+        let fs = tree();
+        let count_ = Count::new();
+        assert_eq!(
+            vec!["3 3 3030 /data", "1 2 3000 /data/a"],
+            count(&fs, &["/data", "/data/a"], &count_)
+        );
+
+        let mut out = vec![];
+        count_
+            .run(&fs, &[PathBuf::from("/data/b")], &mut out)
+            .unwrap();
+        assert_eq!(
+            format!("{:>12} {:>12} {:>18} /data/b\n", 1, 1, 30),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_count_quotas() {
+        let fs = tree();
+        let mut count_ = Count::new();
+        count_.quotas(true);
+        count_.header(true);
+        assert_eq!(
+            vec![
+                "QUOTA REM_QUOTA SPACE_QUOTA REM_SPACE_QUOTA DIR_COUNT FILE_COUNT CONTENT_SIZE \
+                 PATHNAME",
+                "10 7 4096 -4904 1 2 3000 /data/a",
+                "none inf 1073741824 1073741764 1 1 30 /data/b",
+                "none inf none inf 3 3 3030 /data",
+            ],
+            count(&fs, &["/data/a", "/data/b", "/data"], &count_)
+        );
+
+        count_.header(false);
+        count_.human(true);
+        assert_eq!(
+            vec!["10 7 4.0 K -4.8 K 1 2 2.9 K /data/a"],
+            count(&fs, &["/data/a"], &count_)
+        );
+    }
+
+    #[test]
+    fn test_quotas_unknown() {
+        let fs = MemoryFileSystem::default();
+        fs.add_file("/data/part-0", b"abc").unwrap();
+        let paths = vec![PathBuf::from("/data")];
+        let mut count_ = Count::new();
+        let mut out = vec![];
+        assert_eq!(0, count_.run(&fs, &paths, &mut out).unwrap());
+
+        count_.quotas(true);
+        match count_.run(&fs, &paths, &mut vec![]) {
+            Err(err::Error::HdfsError(Error::Unsupported(_))) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_count_failures() {
+        let fs = tree();
+        let paths = vec![PathBuf::from("/missing"), PathBuf::from("/data/b")];
+        let mut out = vec![];
+        assert_eq!(1, Count::new().run(&fs, &paths, &mut out).unwrap());
+        assert_eq!(1, String::from_utf8(out).unwrap().lines().count());
+
+        fs.inject_error(fs_test::Operation::ContentSummary, "/data/b", || {
+            Error::RemoteException(
+                String::from("AccessControlException"),
+                String::from("denied"),
+            )
+        });
+        let mut out = vec![];
+        assert_eq!(2, Count::new().run(&fs, &paths, &mut out).unwrap());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,511 @@
+use err::Error;
+use find::CHECKPOINT_EVERY;
+use hdfs::err::Error as HdfsError;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use walk::checkpoint::{self, Checkpoint};
+use walk::err::Error as WalkError;
+use walk::ignore::IgnoreRules;
+use walk::pattern;
+use walk::walk::{SortBy, WalkBuilder, WalkEvent};
+use walk_hdfs::HdfsFileSystem;
+
+const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+const CHECKPOINT_HEADER: &str = "du-checkpoint 1";
+
+/// Sizes of what is under a path, like `hdfs dfs -du`: each entry of a directory by
+/// default, the path itself once summarized.
+pub struct Du {
+    max_depth: usize,
+    human: bool,
+    sort: bool,
+    excludes: IgnoreRules,
+    checkpoint: Option<PathBuf>,
+}
+
+/// Bytes of the files under `path`, and what their replicas take on the datanodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Usage {
+    pub path: PathBuf,
+    pub length: u64,
+    pub space_consumed: u64,
+}
+
+impl Usage {
+    fn new(path: PathBuf) -> Usage {
+        Usage {
+            path,
+            length: 0,
+            space_consumed: 0,
+        }
+    }
+}
+
+/// Sizes like Hadoop prints them: `1.5 G`, plain bytes below a kilobyte.
+pub fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+impl Default for Du {
+    fn default() -> Du {
+        Du {
+            max_depth: 1,
+            human: false,
+            sort: false,
+            excludes: IgnoreRules::new(),
+            checkpoint: None,
+        }
+    }
+}
+
+/// Usages of the entries reported and of the directories being walked, added to
+/// their parent once walked.
+#[derive(Debug, Default)]
+struct Sums {
+    usages: Vec<Usage>,
+    dirs: Vec<Usage>,
+}
+
+impl Sums {
+    fn add(&mut self, event: WalkEvent, max_depth: usize) {
+        let (usage, depth, is_dir) = match event {
+            WalkEvent::EnterDir(item) => {
+                self.dirs
+                    .push(Usage::new(item.path().components().collect()));
+                return;
+            }
+            WalkEvent::File(item) => {
+                let mut usage = Usage::new(item.path().components().collect());
+                if let Some(attributes) = item.attributes() {
+                    let replication = attributes.replication.unwrap_or(1).max(1);
+                    usage.length = attributes.len;
+                    usage.space_consumed = attributes.len * u64::from(replication);
+                }
+                (usage, item.depth(), false)
+            }
+            WalkEvent::ExitDir(item) => match self.dirs.pop() {
+                Some(usage) => (usage, item.depth(), true),
+                None => return,
+            },
+        };
+        if let Some(parent) = self.dirs.last_mut() {
+            parent.length += usage.length;
+            parent.space_consumed += usage.space_consumed;
+        }
+        if depth <= max_depth && (depth > 0 || max_depth == 0 || !is_dir) {
+            self.usages.push(usage);
+        }
+    }
+
+    /// The sums, then the checkpoint of the walk they were taken at.
+    fn write(&self, out: &mut dyn Write, walk: &Checkpoint) -> Result<(), WalkError> {
+        writeln!(out, "{}", CHECKPOINT_HEADER)?;
+        writeln!(out, "sums\t{}\t{}", self.usages.len(), self.dirs.len())?;
+        for usage in self.usages.iter().chain(&self.dirs) {
+            writeln!(
+                out,
+                "{}\t{}\t{}",
+                usage.length,
+                usage.space_consumed,
+                checkpoint::escape(&usage.path)?
+            )?;
+        }
+        walk.write(out)
+    }
+
+    fn read(input: &mut dyn BufRead) -> Result<(Sums, Checkpoint), WalkError> {
+        let mut next_line = || -> Result<String, WalkError> {
+            let mut line = String::new();
+            input.read_line(&mut line)?;
+            Ok(line.trim_end_matches('\n').to_owned())
+        };
+        let invalid =
+            |line: &str| WalkError::InvalidCheckpoint(format!("unexpected line: {}", line));
+        let line = next_line()?;
+        if line != CHECKPOINT_HEADER {
+            return Err(WalkError::InvalidCheckpoint(String::from(
+                "not a checkpoint",
+            )));
+        }
+        let line = next_line()?;
+        let counts: Vec<usize> = match line.split('\t').collect::<Vec<_>>()[..] {
+            ["sums", usages, dirs] => vec![
+                usages.parse().map_err(|_| invalid(&line))?,
+                dirs.parse().map_err(|_| invalid(&line))?,
+            ],
+            _ => return Err(invalid(&line)),
+        };
+        let mut sums = Sums::default();
+        for i in 0..counts[0] + counts[1] {
+            let line = next_line()?;
+            let usage = match line.splitn(3, '\t').collect::<Vec<_>>()[..] {
+                [length, space_consumed, path] => Usage {
+                    path: checkpoint::unescape(path)?,
+                    length: length.parse().map_err(|_| invalid(&line))?,
+                    space_consumed: space_consumed.parse().map_err(|_| invalid(&line))?,
+                },
+                _ => return Err(invalid(&line)),
+            };
+            if i < counts[0] {
+                sums.usages.push(usage);
+            } else {
+                sums.dirs.push(usage);
+            }
+        }
+        Ok((sums, Checkpoint::read(input)?))
+    }
+}
+
+impl Du {
+    pub fn new() -> Du {
+        Du::default()
+    }
+
+    /// Reports the entries down to `depth`, the path itself alone at 0.
+    pub fn max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    pub fn human(&mut self, human: bool) {
+        self.human = human;
+    }
+
+    /// Largest first instead of by path.
+    pub fn sort(&mut self, sort: bool) {
+        self.sort = sort;
+    }
+
+    /// Adds a gitignore-style pattern, relative to the path. What it excludes is not
+    /// counted.
+    pub fn exclude(&mut self, pattern: &str) -> Result<(), Error> {
+        Ok(self.excludes.add(pattern)?)
+    }
+
+    /// Saves the progress of the walk to this local file, resumed from when it exists
+    /// and removed once done. The sums of the namenode are not walks, nothing is saved
+    /// for them.
+    pub fn checkpoint(&mut self, file: &Path) {
+        self.checkpoint = Some(file.to_path_buf());
+    }
+
+    /// Prints the usage under `root`, returns the number of entries that failed.
+    pub fn run(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        root: &Path,
+        out: &mut dyn Write,
+    ) -> Result<usize, Error> {
+        let (mut usages, failed) = self.usages(hdfs_fs, root)?;
+        if self.sort {
+            usages.sort_by(|a, b| b.length.cmp(&a.length).then(a.path.cmp(&b.path)));
+        }
+        let size = |bytes| {
+            if self.human {
+                human_size(bytes)
+            } else {
+                bytes.to_string()
+            }
+        };
+        let rows: Vec<_> = usages
+            .iter()
+            .map(|usage| (size(usage.length), size(usage.space_consumed), &usage.path))
+            .collect();
+        let length_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+        let space_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+        for (length, space_consumed, path) in rows {
+            writeln!(
+                out,
+                "{:<length_width$}  {:<space_width$}  {}",
+                length,
+                space_consumed,
+                path.display(),
+                length_width = length_width,
+                space_width = space_width
+            )?;
+        }
+        Ok(failed)
+    }
+
+    /// Usage of the entries reported, by path, and the number of entries that failed.
+    /// The namenode sums up each entry on its own, unless excludes or depth need the
+    /// whole tree to be walked.
+    pub fn usages(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        root: &Path,
+    ) -> Result<(Vec<Usage>, usize), Error> {
+        let root = if root.is_absolute() {
+            root.to_path_buf()
+        } else {
+            hdfs_fs.current_dir()?.join(root)
+        };
+        let root: PathBuf = root.components().collect();
+        if self.excludes.is_empty() && self.max_depth <= 1 {
+            match self.summarize(hdfs_fs, &root) {
+                Err(Error::HdfsError(HdfsError::Unsupported(_))) => {}
+                result => return result,
+            }
+        }
+        self.walk(hdfs_fs, &root)
+    }
+
+    fn summarize(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        root: &Path,
+    ) -> Result<(Vec<Usage>, usize), Error> {
+        let paths = if self.max_depth == 0 || !hdfs_fs.stat(root)?.is_dir() {
+            vec![root.to_path_buf()]
+        } else {
+            let mut paths: Vec<_> = hdfs_fs
+                .list(root)?
+                .into_iter()
+                .filter_map(|status| status.path.file_name().map(|name| root.join(name)))
+                .collect();
+            paths.sort();
+            paths
+        };
+        let mut usages = vec![];
+        let mut failed = 0;
+        for path in paths {
+            match hdfs_fs.content_summary(&path) {
+                Ok(summary) => usages.push(Usage {
+                    path,
+                    length: summary.length,
+                    space_consumed: summary.space_consumed,
+                }),
+                Err(e @ HdfsError::Unsupported(_)) => return Err(e.into()),
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("du: {}: {}", path.display(), e);
+                }
+            }
+        }
+        Ok((usages, failed))
+    }
+
+    /// Sums up the files as the walk goes, each directory being added to its parent
+    /// once walked.
+    fn walk(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        root: &Path,
+    ) -> Result<(Vec<Usage>, usize), Error> {
+        let fs = HdfsFileSystem::new(hdfs_fs);
+        let mut builder = WalkBuilder::new(&fs)
+            .with_path(PathBuf::from(pattern::escape(&root.to_string_lossy())).join("**"))
+            .sort_by(SortBy::Name)
+            .exclude(self.excludes.clone())
+            .attributes(true);
+        let mut sums = Sums::default();
+        if let Some(file) = self.checkpoint.as_ref() {
+            if let Some((saved, checkpoint)) = checkpoint::load(file, Sums::read)? {
+                sums = saved;
+                builder = builder.resume(checkpoint);
+            }
+        }
+
+        let mut walk = builder.build()?.events();
+        let mut failed = 0;
+        loop {
+            let events: Vec<_> = walk.by_ref().take(CHECKPOINT_EVERY).collect();
+            let done = events.is_empty();
+            for event in events {
+                match event {
+                    Ok(event) => sums.add(event, self.max_depth),
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("du: {}: {}", e.path().display(), e.error());
+                    }
+                }
+            }
+            if let Some(file) = self.checkpoint.as_ref() {
+                let walk = walk.checkpoint();
+                checkpoint::save(file, |out| sums.write(out, &walk))?;
+            }
+            if done {
+                break;
+            }
+        }
+        if let Some(file) = self.checkpoint.as_ref() {
+            std::fs::remove_file(file)?;
+        }
+        let mut usages = sums.usages;
+        usages.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok((usages, failed))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{human_size, Du, Sums};
+    use fs_test::{FakeHdfs, Operation, TempDir};
+    use hdfs::err::Error;
+    use std::path::{Path, PathBuf};
+    use walk::checkpoint;
+    use walk::walk::{SortBy, WalkBuilder};
+    use walk_hdfs::HdfsFileSystem;
+
+    fn tree() -> FakeHdfs {
+        FakeHdfs::new()
+            .file_with("/data/a/x/part-0", 1000, "etl", 0o644, 3, 1024)
+            .file_with("/data/a/part-0", 200, "etl", 0o644, 3, 1024)
+            .file_with("/data/a/_SUCCESS", 0, "etl", 0o644, 3, 1024)
+            .file_with("/data/b/part-0", 30, "etl", 0o644, 2, 1024)
+            .file_with("/data/readme", 4, "etl", 0o644, 1, 1024)
+    }
+
+    fn du(fs: &FakeHdfs, root: &str, du: &Du) -> String {
+        let mut out = vec![];
+        let failed = du.run(fs, Path::new(root), &mut out).unwrap();
+        assert_eq!(0, failed);
+        String::from_utf8(out).unwrap()
+    }
+
+    fn usages(fs: &FakeHdfs, root: &str, du: &Du) -> Vec<(PathBuf, u64, u64)> {
+        let (usages, failed) = du.usages(fs, Path::new(root)).unwrap();
+        assert_eq!(0, failed);
+        usages
+            .into_iter()
+            .map(|usage| (usage.path, usage.length, usage.space_consumed))
+            .collect()
+    }
+
+    fn usage(path: &str, length: u64, space_consumed: u64) -> (PathBuf, u64, u64) {
+        (PathBuf::from(path), length, space_consumed)
+    }
+
+    #[test]
+    fn test_du() {
+        let fs = tree();
+        let mut du_ = Du::new();
+        assert_eq!(
+            "1200  3600  /data/a\n30    60    /data/b\n4     4     /data/readme\n",
+            du(&fs, "/data", &du_)
+        );
+        assert_eq!("4  4  /data/readme\n", du(&fs, "/data/readme", &du_));
+
+        du_.max_depth(0);
+        assert_eq!("1234  3664  /data\n", du(&fs, "/data", &du_));
+
+        let mut du_ = Du::new();
+        du_.sort(true);
+        du_.human(true);
+        assert_eq!(
+            "1.2 K  3.5 K  /data/a\n30     60     /data/b\n4      4      /data/readme\n",
+            du(&fs, "/data", &du_)
+        );
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!("0", human_size(0));
+        assert_eq!("1023", human_size(1023));
+        assert_eq!("1.0 K", human_size(1024));
+        assert_eq!("1.5 K", human_size(1536));
+        assert_eq!("1.5 G", human_size(3 << 29));
+        assert_eq!("16.0 E", human_size(u64::MAX));
+    }
+
+    #[test]
+    fn test_walked_usages() {
+        let fs = tree();
+        let mut du = Du::new();
+        du.max_depth(2);
+        assert_eq!(
+            vec![
+                usage("/data/a", 1200, 3600),
+                usage("/data/a/_SUCCESS", 0, 0),
+                usage("/data/a/part-0", 200, 600),
+                usage("/data/a/x", 1000, 3000),
+                usage("/data/b", 30, 60),
+                usage("/data/b/part-0", 30, 60),
+                usage("/data/readme", 4, 4),
+            ],
+            usages(&fs, "/data", &du)
+        );
+
+        let mut du = Du::new();
+        du.exclude("x/").unwrap();
+        du.exclude("readme").unwrap();
+        assert_eq!(
+            vec![usage("/data/a", 200, 600), usage("/data/b", 30, 60)],
+            usages(&fs, "/data", &du)
+        );
+        du.max_depth(0);
+        assert_eq!(vec![usage("/data", 230, 660)], usages(&fs, "/data", &du));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let fs = tree();
+        let dir = TempDir::default();
+        let file = dir.path().join("du.checkpoint");
+        let mut du = Du::new();
+        du.max_depth(2);
+        let all = usages(&fs, "/data", &du);
+        du.checkpoint(&file);
+        assert_eq!(all, usages(&fs, "/data", &du));
+        assert!(!file.exists());
+
+        // Stopped after each event
+        let hdfs_fs = HdfsFileSystem::new(&fs);
+        let builder = || {
+            WalkBuilder::new(&hdfs_fs)
+                .with_path(PathBuf::from("/data/**"))
+                .sort_by(SortBy::Name)
+                .attributes(true)
+        };
+        let events = builder().build().unwrap().events().count();
+        for stop in 0..=events {
+            let mut walk = builder().build().unwrap().events();
+            let mut sums = Sums::default();
+            for event in walk.by_ref().take(stop) {
+                sums.add(event.unwrap(), 2);
+            }
+            let walk = walk.checkpoint();
+            checkpoint::save(&file, |out| sums.write(out, &walk)).unwrap();
+            assert_eq!(all, usages(&fs, "/data", &du), "stopped after {}", stop);
+            assert!(!file.exists());
+        }
+
+        // Of another walk
+        let other = WalkBuilder::new(&hdfs_fs)
+            .with_path(PathBuf::from("/data/b/**"))
+            .build()
+            .unwrap()
+            .checkpoint();
+        checkpoint::save(&file, |out| Sums::default().write(out, &other)).unwrap();
+        assert!(du.usages(&fs, Path::new("/data")).is_err());
+    }
+
+    #[test]
+    fn test_without_native_summaries() {
+        let fs = tree();
+        let du = Du::new();
+        let native = usages(&fs, "/data", &du);
+        fs.inject_error(Operation::ContentSummary, "/data/b", || {
+            Error::Unsupported(String::from("content summary"))
+        });
+        assert_eq!(native, usages(&fs, "/data", &du));
+
+        fs.inject_error(Operation::ContentSummary, "/data/b", || {
+            Error::RemoteException(
+                String::from("AccessControlException"),
+                String::from("denied"),
+            )
+        });
+        let (usages, failed) = du.usages(&fs, Path::new("/data")).unwrap();
+        assert_eq!(1, failed);
+        assert_eq!(2, usages.len());
+    }
+}
@@ -1,3 +1,6 @@
+use std::fmt;
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Error {
     WalkError(walk::err::Error),
@@ -7,6 +10,18 @@ pub enum Error {
     NoHome,
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::WalkError(ref e) => write!(f, "{}", e),
+            Error::HdfsError(ref e) => write!(f, "{}", e),
+            Error::IoError(ref e) => write!(f, "{}", e),
+            Error::SerializationError(ref e) => write!(f, "{}", e),
+            Error::NoHome => write!(f, "no home directory"),
+        }
+    }
+}
+
 impl From<hdfs::err::Error> for Error {
     fn from(err: hdfs::err::Error) -> Error {
         Error::HdfsError(err)
@@ -0,0 +1,540 @@
+use err::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::time::Duration;
+use walk::checkpoint::Checkpoint;
+use walk::err::Error as WalkError;
+use walk::filter::{
+    And, GlobFilter, MetadataFilter, MtimeFilter, NameFilter, Not, Or, OwnerFilter, SizeFilter,
+    TypeFilter,
+};
+use walk::ignore::{IgnoreRules, IGNORE_FILE};
+use walk::pattern;
+use walk::walk::{SortBy, WalkBuilder, WalkEvent};
+use walk_hdfs::HdfsFileSystem;
+
+const DAY: u64 = 24 * 60 * 60;
+/// Entries returned between two checkpoints.
+pub const CHECKPOINT_EVERY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Print,
+    Print0,
+    Delete,
+    /// The command and its arguments, `{}` being replaced by the path
+    Exec(Vec<String>),
+}
+
+/// A parsed `find` command line. Actions apply to every entry matching the tests,
+/// excluded entries and those of `.hdfsignore` files being left out.
+pub struct Find {
+    test: Rc<dyn MetadataFilter + Send + Sync>,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    action: Action,
+    excludes: IgnoreRules,
+    checkpoint: Option<PathBuf>,
+}
+
+fn invalid(message: String) -> Error {
+    Error::WalkError(WalkError::InvalidFilter(message))
+}
+
+struct Parser<'a> {
+    tokens: &'a [&'a str],
+    position: usize,
+    find: Find,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn argument(&mut self, primary: &str) -> Result<&'a str, Error> {
+        self.next()
+            .ok_or_else(|| invalid(format!("missing argument to {}", primary)))
+    }
+
+    fn number(&mut self, primary: &str) -> Result<usize, Error> {
+        let argument = self.argument(primary)?;
+        argument
+            .parse()
+            .map_err(|_| invalid(format!("invalid argument {} to {}", argument, primary)))
+    }
+
+    fn or(&mut self) -> Result<Box<dyn MetadataFilter + Send + Sync>, Error> {
+        let mut tests = vec![self.and()?];
+        while let Some("-o") | Some("-or") = self.peek() {
+            self.next();
+            tests.push(self.and()?);
+        }
+        Ok(match tests.len() {
+            1 => tests.pop().unwrap(),
+            _ => Box::new(Or(tests)),
+        })
+    }
+
+    fn and(&mut self) -> Result<Box<dyn MetadataFilter + Send + Sync>, Error> {
+        let mut tests = vec![self.not()?];
+        loop {
+            match self.peek() {
+                None | Some("-o") | Some("-or") | Some(")") => break,
+                Some("-a") | Some("-and") => {
+                    self.next();
+                }
+                _ => {}
+            }
+            tests.push(self.not()?);
+        }
+        Ok(match tests.len() {
+            1 => tests.pop().unwrap(),
+            _ => Box::new(And(tests)),
+        })
+    }
+
+    fn not(&mut self) -> Result<Box<dyn MetadataFilter + Send + Sync>, Error> {
+        match self.peek() {
+            Some("!") | Some("-not") => {
+                self.next();
+                Ok(Box::new(Not(self.not()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Box<dyn MetadataFilter + Send + Sync>, Error> {
+        let primary = self
+            .next()
+            .ok_or_else(|| invalid(String::from("expression expected")))?;
+        let test: Box<dyn MetadataFilter + Send + Sync> = match primary {
+            "(" => {
+                let test = self.or()?;
+                if self.next() != Some(")") {
+                    return Err(invalid(String::from("missing )")));
+                }
+                test
+            }
+            "-name" => Box::new(NameFilter::new(self.argument(primary)?)?),
+            "-iname" => Box::new(NameFilter::case_insensitive(self.argument(primary)?)?),
+            "-path" => Box::new(GlobFilter::new(self.argument(primary)?)?),
+            "-type" => Box::new(TypeFilter::parse(self.argument(primary)?)?),
+            // Sizes are in bytes unless a unit is given, `c` being bytes too
+            "-size" => {
+                let size = self.argument(primary)?;
+                Box::new(SizeFilter::parse(size.trim_end_matches('c'))?)
+            }
+            "-mtime" => mtime(self.argument(primary)?)?,
+            "-user" => Box::new(OwnerFilter::user(self.argument(primary)?)),
+            "-group" => Box::new(OwnerFilter::group(self.argument(primary)?)),
+            "-maxdepth" => {
+                self.find.max_depth = Some(self.number(primary)?);
+                always()
+            }
+            "-mindepth" => {
+                self.find.min_depth = self.number(primary)?;
+                always()
+            }
+            "-print" => self.action(Action::Print)?,
+            "-print0" => self.action(Action::Print0)?,
+            "-delete" => self.action(Action::Delete)?,
+            "-exec" => {
+                let mut command = vec![];
+                loop {
+                    match self.next() {
+                        Some(";") => break,
+                        Some(argument) => command.push(argument.to_owned()),
+                        None => return Err(invalid(String::from("missing ; after -exec"))),
+                    }
+                }
+                if command.is_empty() {
+                    return Err(invalid(String::from("missing command to -exec")));
+                }
+                self.action(Action::Exec(command))?
+            }
+            _ => return Err(invalid(format!("unknown predicate {}", primary))),
+        };
+        Ok(test)
+    }
+
+    fn action(&mut self, action: Action) -> Result<Box<dyn MetadataFilter + Send + Sync>, Error> {
+        if self.find.action != Action::Print && self.find.action != action {
+            return Err(invalid(String::from("only one action is supported")));
+        }
+        self.find.action = action;
+        Ok(always())
+    }
+}
+
+fn always() -> Box<dyn MetadataFilter + Send + Sync> {
+    Box::new(And(vec![]))
+}
+
+/// Days like `find -mtime`: `+N` more than N days ago, `-N` less, `N` between N and N + 1.
+fn mtime(days: &str) -> Result<Box<dyn MetadataFilter + Send + Sync>, Error> {
+    if days.starts_with('+') || days.starts_with('-') {
+        return Ok(Box::new(MtimeFilter::parse(&format!("{}d", days))?));
+    }
+    let invalid_days = || invalid(format!("invalid argument {} to -mtime", days));
+    let days: u64 = days.parse().map_err(|_| invalid_days())?;
+    let seconds = |days: u64| days.checked_mul(DAY).ok_or_else(invalid_days);
+    let next_day = days.checked_add(1).ok_or_else(invalid_days)?;
+    Ok(Box::new(And(vec![
+        Box::new(MtimeFilter::older_than(Duration::from_secs(seconds(
+            days,
+        )?))?),
+        Box::new(MtimeFilter::newer_than(Duration::from_secs(seconds(
+            next_day,
+        )?))?),
+    ])))
+}
+
+impl Find {
+    pub fn parse(expression: &[&str]) -> Result<Find, Error> {
+        let mut parser = Parser {
+            tokens: expression,
+            position: 0,
+            find: Find {
+                test: Rc::new(And(vec![])),
+                min_depth: 0,
+                max_depth: None,
+                action: Action::Print,
+                excludes: IgnoreRules::new(),
+                checkpoint: None,
+            },
+        };
+        if !expression.is_empty() {
+            let test = parser.or()?;
+            if let Some(token) = parser.peek() {
+                return Err(invalid(format!("unexpected {}", token)));
+            }
+            parser.find.test = Rc::from(test);
+        }
+        Ok(parser.find)
+    }
+
+    /// Adds a gitignore-style pattern, relative to the root of the search.
+    pub fn exclude(&mut self, pattern: &str) -> Result<(), Error> {
+        Ok(self.excludes.add(pattern)?)
+    }
+
+    /// Saves the progress to this local file, resumed from when it exists and removed
+    /// once done. An entry is acted on at most once, whatever the restarts.
+    pub fn checkpoint(&mut self, file: &Path) {
+        self.checkpoint = Some(file.to_path_buf());
+    }
+
+    /// Runs the search under `root` and the action on what matches, returns the number
+    /// of entries that failed. Directories are deleted after what they hold.
+    pub fn run(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        root: &Path,
+        out: &mut dyn Write,
+    ) -> Result<usize, Error> {
+        let root = if root.is_absolute() {
+            root.to_path_buf()
+        } else {
+            hdfs_fs.current_dir()?.join(root)
+        };
+        let root: PathBuf = root.components().collect();
+        let fs = HdfsFileSystem::new(hdfs_fs);
+
+        let mut failed = 0;
+        let mut builder = WalkBuilder::new(&fs)
+            .with_path(PathBuf::from(pattern::escape(&root.to_string_lossy())).join("**"))
+            .min_depth(self.min_depth)
+            .sort_by(SortBy::Name)
+            .exclude(self.excludes.clone())
+            .ignore_file(IGNORE_FILE)
+            .add_metadata_filter(Box::new(Test(self.test.clone())));
+        if let Some(max_depth) = self.max_depth {
+            builder = builder.max_depth(max_depth);
+        }
+        if let Some(checkpoint) = self.checkpoint.as_ref() {
+            if let Some(checkpoint) = Checkpoint::load(checkpoint)? {
+                builder = builder.resume(checkpoint);
+            }
+        }
+        let mut walk = builder.build()?.events();
+        loop {
+            let events: Vec<_> = walk.by_ref().take(CHECKPOINT_EVERY).collect();
+            // Saved before acting, what follows is lost on a crash but not done twice
+            if let Some(checkpoint) = self.checkpoint.as_ref() {
+                walk.checkpoint().save(checkpoint)?;
+            }
+            if events.is_empty() {
+                break;
+            }
+            for event in events {
+                let item = match event {
+                    Ok(WalkEvent::File(item)) => item,
+                    Ok(WalkEvent::EnterDir(item)) if self.action != Action::Delete => item,
+                    Ok(WalkEvent::ExitDir(item)) if self.action == Action::Delete => item,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("find: {}: {}", e.path().display(), e.error());
+                        continue;
+                    }
+                };
+                // Glob roots come with a trailing separator
+                let path: PathBuf = item.path().components().collect();
+                if let Err(e) = self.apply(hdfs_fs, &path, out) {
+                    failed += 1;
+                    eprintln!("find: {}: {}", path.display(), e);
+                }
+            }
+        }
+        if let Some(checkpoint) = self.checkpoint.as_ref() {
+            std::fs::remove_file(checkpoint)?;
+        }
+        Ok(failed)
+    }
+
+    fn apply(
+        &self,
+        hdfs_fs: &dyn hdfs::fs::FileSystem,
+        path: &Path,
+        out: &mut dyn Write,
+    ) -> Result<(), Error> {
+        match self.action {
+            Action::Print => writeln!(out, "{}", path.display())?,
+            Action::Print0 => write!(out, "{}\0", path.display())?,
+            Action::Delete => {
+                hdfs_fs.delete(path, false)?;
+            }
+            Action::Exec(ref command) => {
+                let path = path.to_string_lossy();
+                let arguments: Vec<_> = command[1..]
+                    .iter()
+                    .map(|argument| argument.replace("{}", &path))
+                    .collect();
+                let output = Command::new(&command[0])
+                    .args(&arguments)
+                    .stderr(Stdio::inherit())
+                    .output()?;
+                out.write_all(&output.stdout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shares the parsed test with a walk.
+struct Test(Rc<dyn MetadataFilter + Send + Sync>);
+
+impl MetadataFilter for Test {
+    fn matches(&self, path: &str, metadata: &dyn walk::walk::MetadataTrait) -> bool {
+        self.0.matches(path, metadata)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Find};
+    use fs_test::FakeHdfs;
+    use hdfs::fs::FileSystem;
+    use std::path::{Path, PathBuf};
+
+    fn tree() -> FakeHdfs {
+        FakeHdfs::new()
+            .file_with("/data/a/part-0.parquet", 2000, "etl", 0o644, 3, 1024)
+            .file_with("/data/a/part-1.PARQUET", 10, "etl", 0o644, 3, 1024)
+            .file_with("/data/b/part-0.csv", 10, "alice", 0o644, 3, 1024)
+            .file("/data/readme", b"")
+    }
+
+    fn find(fs: &FakeHdfs, root: &str, expression: &str) -> String {
+        let expression: Vec<_> = expression.split_whitespace().collect();
+        let mut out = vec![];
+        let failed = Find::parse(&expression)
+            .unwrap()
+            .run(fs, Path::new(root), &mut out)
+            .unwrap();
+        assert_eq!(0, failed);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_tests() {
+        let fs = tree();
+
+        assert_eq!(
+            "/data\n/data/a\n/data/a/part-0.parquet\n/data/a/part-1.PARQUET\n/data/b\n\
+             /data/b/part-0.csv\n/data/readme\n",
+            find(&fs, "/data", "")
+        );
+        assert_eq!(
+            "/data/a/part-0.parquet\n",
+            find(&fs, "/data", "-name *.parquet")
+        );
+        assert_eq!(
+            "/data/a/part-0.parquet\n/data/a/part-1.PARQUET\n",
+            find(&fs, "/data", "-iname *.parquet -type f")
+        );
+        assert_eq!(
+            "/data/a\n/data/b\n",
+            find(&fs, "/data", "-type d -mindepth 1")
+        );
+        assert_eq!("/data/a/part-0.parquet\n", find(&fs, "/data", "-size +1k"));
+        assert_eq!("/data/b/part-0.csv\n", find(&fs, "/data", "-user alice"));
+        assert_eq!(
+            "/data/a/part-1.PARQUET\n/data/b/part-0.csv\n",
+            find(
+                &fs,
+                "/data",
+                "-type f ( -user alice -o -size -100 ) ! -name readme"
+            )
+        );
+        assert_eq!(
+            "/data\n/data/a\n/data/b\n/data/readme\n",
+            find(&fs, "/data", "-maxdepth 1")
+        );
+        assert_eq!(
+            "/data/a/part-0.parquet\n",
+            find(&fs, "/data", "-path /data/a/* -mtime -1 -size 2000")
+        );
+        assert_eq!("", find(&fs, "/data", "-mtime +1"));
+    }
+
+    #[test]
+    fn test_relative_root() {
+        let fs = FakeHdfs::new().file("/user/hdfs/logs/day", b"");
+
+        assert_eq!("/user/hdfs/logs/day\n", find(&fs, "logs", "-type f"));
+    }
+
+    #[test]
+    fn test_root_is_not_a_pattern() {
+        let fs = FakeHdfs::new()
+            .file("/data/[1]/{a,b}", b"")
+            .file("/data/1/a", b"");
+
+        assert_eq!("/data/[1]/{a,b}\n", find(&fs, "/data/[1]", "-type f"));
+    }
+
+    #[test]
+    fn test_actions() {
+        let fs = tree();
+
+        assert_eq!(
+            "/data/b\0/data/b/part-0.csv\0",
+            find(&fs, "/data/b", "-print0")
+        );
+        assert_eq!(
+            "found /data/readme\n",
+            find(&fs, "/data", "-name readme -exec echo found {} ;")
+        );
+
+        assert_eq!("", find(&fs, "/data/a", "-delete"));
+        assert!(!fs.exists(Path::new("/data/a")).unwrap());
+        assert!(fs.exists(Path::new("/data/b")).unwrap());
+    }
+
+    #[test]
+    fn test_excludes() {
+        let fs = tree()
+            .file("/data/a/_SUCCESS", b"")
+            .file("/data/b/.hdfsignore", b"*.csv\n")
+            .file("/data/b/_temporary/0/part-1.csv", b"");
+        let mut find = Find::parse(&["-delete"]).unwrap();
+        for pattern in &["_SUCCESS", "_temporary/", ".hdfsignore"] {
+            find.exclude(pattern).unwrap();
+        }
+
+        find.run(&fs, Path::new("/data/b"), &mut vec![]).unwrap();
+        assert!(fs.exists(Path::new("/data/b/part-0.csv")).unwrap());
+        assert!(fs
+            .exists(Path::new("/data/b/_temporary/0/part-1.csv"))
+            .unwrap());
+
+        let mut out = vec![];
+        let mut find = Find::parse(&["-type", "f"]).unwrap();
+        find.exclude("_SUCCESS").unwrap();
+        find.run(&fs, Path::new("/data"), &mut out).unwrap();
+        assert_eq!(
+            "/data/a/part-0.parquet\n/data/a/part-1.PARQUET\n/data/b/.hdfsignore\n\
+             /data/readme\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert!(find.exclude("[a").is_err());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        use fs_test::TempDir;
+        use walk::walk::{SortBy, WalkBuilder};
+        use walk_hdfs::HdfsFileSystem;
+
+        let fs = tree();
+        let dir = TempDir::default();
+        let file = dir.path().join("find.checkpoint");
+
+        // Stopped once /data and /data/a were printed
+        let hdfs_fs = HdfsFileSystem::new(&fs);
+        let mut walk = WalkBuilder::new(&hdfs_fs)
+            .with_path(PathBuf::from("/data/**"))
+            .sort_by(SortBy::Name)
+            .build()
+            .unwrap()
+            .events();
+        walk.by_ref().take(2).for_each(drop);
+        walk.checkpoint().save(&file).unwrap();
+
+        let mut find = Find::parse(&[]).unwrap();
+        find.checkpoint(&file);
+        let mut out = vec![];
+        assert_eq!(0, find.run(&fs, Path::new("/data"), &mut out).unwrap());
+        assert_eq!(
+            "/data/a/part-0.parquet\n/data/a/part-1.PARQUET\n/data/b\n/data/b/part-0.csv\n\
+             /data/readme\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert!(!file.exists());
+
+        // Without a checkpoint to resume from
+        let mut out = vec![];
+        find.run(&fs, Path::new("/data/b"), &mut out).unwrap();
+        assert_eq!(
+            "/data/b\n/data/b/part-0.csv\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert!(!file.exists());
+
+        // Of another search
+        walk.checkpoint().save(&file).unwrap();
+        assert!(find.run(&fs, Path::new("/data/b"), &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expression in &[
+            vec!["-name"],
+            vec!["-maxdepth", "x"],
+            vec!["(", "-name", "a"],
+            vec!["-unknown"],
+            vec!["-exec", "echo"],
+            vec!["-delete", "-print0"],
+            vec!["-name", "a", ")"],
+            vec!["-mtime", "300000000000000"],
+            vec!["-mtime", "18446744073709551615"],
+        ] {
+            assert!(Find::parse(expression).is_err(), "{:?}", expression);
+        }
+        let find = Find::parse(&["-exec", "rm", "{}", ";"]).unwrap();
+        assert_eq!(
+            &Action::Exec(vec![String::from("rm"), String::from("{}")]),
+            &find.action
+        );
+    }
+}
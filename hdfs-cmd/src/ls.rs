@@ -0,0 +1,563 @@
+use du::human_size;
+use err::Error;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walk::filter::PathFilter;
+use walk::ignore::IgnoreRules;
+use walk::pattern;
+use walk::walk::{Attributes, FileSystem, WalkBuilder};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Order of the entries of a listing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    Name,
+    /// Newest first
+    Modified,
+    /// Largest first
+    Size,
+}
+
+/// Listings like `hdfs dfs -ls`: the entries of the directories matched, the files
+/// themselves.
+pub struct Ls {
+    long: bool,
+    human: bool,
+    sort: Sort,
+    reverse: bool,
+    directories: bool,
+    recursive: bool,
+    excludes: IgnoreRules,
+    filters: Vec<Box<dyn PathFilter>>,
+}
+
+/// An entry with what its listing returned about it.
+struct Entry {
+    path: PathBuf,
+    attributes: Attributes,
+}
+
+impl Default for Ls {
+    fn default() -> Ls {
+        Ls {
+            long: false,
+            human: false,
+            sort: Sort::Name,
+            reverse: false,
+            directories: false,
+            recursive: false,
+            excludes: IgnoreRules::new(),
+            filters: vec![],
+        }
+    }
+}
+
+impl Ls {
+    pub fn new() -> Ls {
+        Ls::default()
+    }
+
+    /// Permissions, replication, owner, group, size and modification time before each
+    /// path.
+    pub fn long(&mut self, long: bool) {
+        self.long = long;
+    }
+
+    pub fn human(&mut self, human: bool) {
+        self.human = human;
+    }
+
+    pub fn sort(&mut self, sort: Sort) {
+        self.sort = sort;
+    }
+
+    pub fn reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
+    /// Lists the directories matched instead of their entries.
+    pub fn directories(&mut self, directories: bool) {
+        self.directories = directories;
+    }
+
+    pub fn recursive(&mut self, recursive: bool) {
+        self.recursive = recursive;
+    }
+
+    /// Adds a gitignore-style pattern, relative to the directory listed.
+    pub fn exclude(&mut self, pattern: &str) -> Result<(), Error> {
+        Ok(self.excludes.add(pattern)?)
+    }
+
+    /// Lists only the entries matching all the filters, directories are still walked
+    /// through.
+    pub fn filter(&mut self, filter: Box<dyn PathFilter>) {
+        self.filters.push(filter);
+    }
+
+    /// Lists what the paths match, returns the number of paths and entries that failed.
+    pub fn run<F>(&self, fs: &F, paths: &[PathBuf], out: &mut dyn Write) -> Result<usize, Error>
+    where
+        F: FileSystem,
+        F::Error: fmt::Display,
+    {
+        let mut failed = 0;
+        let mut files = vec![];
+        let mut dirs = vec![];
+        for path in paths {
+            let failed_before = failed;
+            let entries = self.walk(fs, path.clone(), 0, &mut failed)?;
+            // The walk reported why a path it could not read gave nothing
+            if entries.is_empty() && failed == failed_before {
+                failed += 1;
+                eprintln!("ls: {}: No such file or directory", path.display());
+            }
+            for entry in entries {
+                if entry.attributes.is_dir && !self.directories {
+                    dirs.push(entry);
+                } else {
+                    files.push(entry);
+                }
+            }
+        }
+
+        let headers = files.len() + dirs.len() > 1 || self.recursive;
+        if !files.is_empty() {
+            self.sort_entries(&mut files);
+            self.print(&files, out)?;
+        }
+        self.sort_entries(&mut dirs);
+        for dir in dirs {
+            let pattern = PathBuf::from(pattern::escape(&dir.path.to_string_lossy()))
+                .join(if self.recursive { "**" } else { "*" });
+            let mut children: HashMap<PathBuf, Vec<Entry>> = HashMap::new();
+            for entry in self.walk(fs, pattern, 1, &mut failed)? {
+                if let Some(parent) = entry.path.parent() {
+                    children.entry(key(parent)).or_default().push(entry);
+                }
+            }
+            if self.long {
+                if !self.recursive {
+                    let count = children.get(&key(&dir.path)).map_or(0, |entries| {
+                        entries.iter().filter(|e| self.is_match(&e.path)).count()
+                    });
+                    writeln!(out, "Found {} items", count)?;
+                }
+                self.print_long_tree(&dir.path, &mut children, out)?;
+            } else {
+                self.print_short_tree(&dir.path, &mut children, headers, out)?;
+            }
+        }
+        Ok(failed)
+    }
+
+    fn walk<F>(
+        &self,
+        fs: &F,
+        pattern: PathBuf,
+        min_depth: usize,
+        failed: &mut usize,
+    ) -> Result<Vec<Entry>, Error>
+    where
+        F: FileSystem,
+        F::Error: fmt::Display,
+    {
+        let walk = WalkBuilder::new(fs)
+            .with_path(pattern)
+            .min_depth(min_depth)
+            .exclude(self.excludes.clone())
+            .attributes(true)
+            .build()?;
+        let mut entries = vec![];
+        for item in walk {
+            match item {
+                Ok(item) => {
+                    if let Some(attributes) = item.attributes() {
+                        entries.push(Entry {
+                            path: item.path(),
+                            attributes: attributes.clone(),
+                        });
+                    }
+                }
+                Err(e) => {
+                    *failed += 1;
+                    eprintln!("ls: {}: {}", e.path().display(), e.error());
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Each entry is followed by the subtree of the directories, like `hdfs dfs -ls -R`.
+    fn print_long_tree(
+        &self,
+        dir: &Path,
+        children: &mut HashMap<PathBuf, Vec<Entry>>,
+        out: &mut dyn Write,
+    ) -> Result<(), Error> {
+        let mut entries = children.remove(&key(dir)).unwrap_or_default();
+        self.sort_entries(&mut entries);
+        let matched: Vec<_> = entries.iter().filter(|e| self.is_match(&e.path)).collect();
+        let widths = self.widths(&matched);
+        for entry in &entries {
+            if self.is_match(&entry.path) {
+                writeln!(out, "{}", self.long_line(entry, &widths))?;
+            }
+            if self.recursive && entry.attributes.is_dir {
+                self.print_long_tree(&entry.path, children, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of each directory under its path, like `ls -R`.
+    fn print_short_tree(
+        &self,
+        dir: &Path,
+        children: &mut HashMap<PathBuf, Vec<Entry>>,
+        header: bool,
+        out: &mut dyn Write,
+    ) -> Result<(), Error> {
+        let mut entries = children.remove(&key(dir)).unwrap_or_default();
+        self.sort_entries(&mut entries);
+        if header {
+            writeln!(out, "{}:", dir.display())?;
+        }
+        let matched: Vec<_> = entries
+            .iter()
+            .filter(|e| self.is_match(&e.path))
+            .map(|e| e.path.file_name().unwrap_or_default().to_string_lossy())
+            .collect();
+        writeln!(out, "{}", matched.join(" "))?;
+        if self.recursive {
+            for entry in entries.iter().filter(|e| e.attributes.is_dir) {
+                self.print_short_tree(&entry.path, children, header, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Entries matched by the paths themselves, printed with their whole path.
+    fn print(&self, entries: &[Entry], out: &mut dyn Write) -> Result<(), Error> {
+        if self.long {
+            let entries: Vec<_> = entries.iter().collect();
+            let widths = self.widths(&entries);
+            for entry in entries {
+                writeln!(out, "{}", self.long_line(entry, &widths))?;
+            }
+        } else {
+            let paths: Vec<_> = entries.iter().map(|e| e.path.to_string_lossy()).collect();
+            writeln!(out, "{}", paths.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Widths of the replication, owner, group and size columns, at least those of
+    /// `hdfs dfs -ls`.
+    fn widths(&self, entries: &[&Entry]) -> [usize; 4] {
+        let mut widths = [3, 0, 0, 10];
+        for entry in entries {
+            let columns = self.columns(entry);
+            for (width, column) in widths.iter_mut().zip(columns.iter().skip(1)) {
+                *width = (*width).max(column.len());
+            }
+        }
+        widths
+    }
+
+    fn columns(&self, entry: &Entry) -> [String; 5] {
+        let attributes = &entry.attributes;
+        let replication = match attributes.replication {
+            Some(replication) if !attributes.is_dir => replication.to_string(),
+            _ => String::from("-"),
+        };
+        let size = if self.human {
+            human_size(attributes.len)
+        } else {
+            attributes.len.to_string()
+        };
+        [
+            permission_string(attributes),
+            replication,
+            attributes
+                .owner
+                .clone()
+                .unwrap_or_else(|| String::from("-")),
+            attributes
+                .group
+                .clone()
+                .unwrap_or_else(|| String::from("-")),
+            size,
+        ]
+    }
+
+    fn long_line(&self, entry: &Entry, widths: &[usize; 4]) -> String {
+        let [permissions, replication, owner, group, size] = self.columns(entry);
+        format!(
+            "{} {:>w0$} {:<w1$} {:<w2$} {:>w3$} {} {}",
+            permissions,
+            replication,
+            owner,
+            group,
+            size,
+            format_time(entry.attributes.modified),
+            entry.path.display(),
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        )
+    }
+
+    fn sort_entries(&self, entries: &mut [Entry]) {
+        entries.sort_by(|a, b| {
+            let ordering = match self.sort {
+                Sort::Name => Ordering::Equal,
+                Sort::Modified => b.attributes.modified.cmp(&a.attributes.modified),
+                Sort::Size => b.attributes.len.cmp(&a.attributes.len),
+            };
+            ordering.then_with(|| a.path.cmp(&b.path))
+        });
+        if self.reverse {
+            entries.reverse();
+        }
+    }
+
+    fn is_match(&self, path: &Path) -> bool {
+        path.to_str()
+            .is_some_and(|path| self.filters.iter().all(|f| f.is_match(path)))
+    }
+}
+
+/// Paths as listings join them, without trailing separators.
+fn key(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// Type and permission bits, like `drwxr-xr-t`.
+fn permission_string(attributes: &Attributes) -> String {
+    let mode = attributes.permissions.unwrap_or(0);
+    let mut permissions = String::with_capacity(10);
+    permissions.push(if attributes.is_dir { 'd' } else { '-' });
+    for (i, c) in "rwxrwxrwx".chars().enumerate() {
+        permissions.push(if mode & (0o400 >> i) != 0 { c } else { '-' });
+    }
+    if mode & 0o1000 != 0 {
+        let sticky = if permissions.pop() == Some('x') {
+            't'
+        } else {
+            'T'
+        };
+        permissions.push(sticky);
+    }
+    permissions
+}
+
+/// Times like `2024-01-31 23:59`, in UTC.
+fn format_time(time: Option<SystemTime>) -> String {
+    let seconds = match time.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+        Some(since_epoch) => since_epoch.as_secs(),
+        None => return String::from("-"),
+    };
+    // Days to civil dates, from Howard Hinnant's algorithms, with years starting in March
+    let days = seconds / DAY + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let (year, month) = if month < 10 {
+        (year_of_era + era * 400, month + 3)
+    } else {
+        (year_of_era + era * 400 + 1, month - 9)
+    };
+    let time_of_day = seconds % DAY;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_time, Ls, Sort};
+    use fs_test::{FakeHdfs, Operation};
+    use hdfs::err::Error;
+    use hdfs::fs::FileSystem;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, UNIX_EPOCH};
+    use walk::filter::{MatchOn, RegexFilter};
+    use walk_hdfs::HdfsFileSystem;
+
+    const MINUTE: u64 = 60_000;
+    const TIME: u64 = 1_700_000_000_000;
+
+    fn tree() -> FakeHdfs {
+        let fs = FakeHdfs::new()
+            .dir("/data/a/x")
+            .file_with("/data/a/part-0", 100, "etl", 0o644, 3, 1024)
+            .file_with("/data/b.csv", 2000, "etl", 0o640, 2, 1024)
+            .file_with("/data/readme", 5, "hdfs", 0o644, 1, 1024);
+        let times = [
+            ("/data", 0),
+            ("/data/a", 1),
+            ("/data/a/x", 2),
+            ("/data/a/part-0", 3),
+            ("/data/b.csv", 5),
+            ("/data/readme", 4),
+        ];
+        for &(path, minutes) in &times {
+            fs.set_times(Path::new(path), Some(TIME + minutes * MINUTE), None)
+                .unwrap();
+        }
+        fs
+    }
+
+    fn ls(fs: &FakeHdfs, paths: &[&str], ls: &Ls) -> String {
+        let paths: Vec<_> = paths.iter().map(PathBuf::from).collect();
+        let mut out = vec![];
+        let failed = ls.run(&HdfsFileSystem::new(fs), &paths, &mut out).unwrap();
+        assert_eq!(0, failed);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_ls() {
+        let fs = tree();
+        let ls_ = Ls::new();
+        assert_eq!("a b.csv readme\n", ls(&fs, &["/data"], &ls_));
+        assert_eq!("/data/readme\n", ls(&fs, &["/data/readme"], &ls_));
+        assert_eq!(
+            "/data/b.csv /data/readme\n/data/a:\npart-0 x\n",
+            ls(&fs, &["/data/[ab]*", "/data/readme"], &ls_)
+        );
+
+        let mut ls_ = Ls::new();
+        ls_.recursive(true);
+        assert_eq!(
+            "/data/a:\npart-0 x\n/data/a/x:\n\n",
+            ls(&fs, &["/data/a"], &ls_)
+        );
+    }
+
+    #[test]
+    fn test_ls_long() {
+        let fs = tree();
+        let mut ls_ = Ls::new();
+        ls_.long(true);
+        assert_eq!(
+            "Found 3 items\n\
+             drwxr-xr-x   - hdfs supergroup          0 2023-11-14 22:14 /data/a\n\
+             -rw-r-----   2 etl  supergroup       2000 2023-11-14 22:18 /data/b.csv\n\
+             -rw-r--r--   1 hdfs supergroup          5 2023-11-14 22:17 /data/readme\n",
+            ls(&fs, &["/data"], &ls_)
+        );
+
+        ls_.human(true);
+        ls_.directories(true);
+        assert_eq!(
+            "drwxr-xr-x   - hdfs supergroup          0 2023-11-14 22:13 /data\n\
+             -rw-r-----   2 etl  supergroup      2.0 K 2023-11-14 22:18 /data/b.csv\n",
+            ls(&fs, &["/data", "/data/b.csv"], &ls_)
+        );
+    }
+
+    #[test]
+    fn test_ls_recursive() {
+        let fs = tree();
+        let mut ls_ = Ls::new();
+        ls_.long(true);
+        ls_.recursive(true);
+        let paths = |output: String| -> Vec<String> {
+            output
+                .lines()
+                .map(|line| line.rsplit(' ').next().unwrap().to_owned())
+                .collect()
+        };
+        assert_eq!(
+            vec![
+                "/data/a",
+                "/data/a/part-0",
+                "/data/a/x",
+                "/data/b.csv",
+                "/data/readme"
+            ],
+            paths(ls(&fs, &["/data"], &ls_))
+        );
+
+        ls_.sort(Sort::Modified);
+        assert_eq!(
+            vec![
+                "/data/b.csv",
+                "/data/readme",
+                "/data/a",
+                "/data/a/part-0",
+                "/data/a/x"
+            ],
+            paths(ls(&fs, &["/data"], &ls_))
+        );
+
+        ls_.sort(Sort::Size);
+        ls_.reverse(true);
+        ls_.filter(Box::new(
+            RegexFilter::new("part-.*|x", MatchOn::Name).unwrap(),
+        ));
+        assert_eq!(
+            vec!["/data/a/x", "/data/a/part-0"],
+            paths(ls(&fs, &["/data"], &ls_))
+        );
+
+        let mut ls_ = Ls::new();
+        ls_.long(true);
+        ls_.recursive(true);
+        ls_.exclude("a/").unwrap();
+        assert_eq!(
+            vec!["/data/b.csv", "/data/readme"],
+            paths(ls(&fs, &["/data"], &ls_))
+        );
+    }
+
+    #[test]
+    fn test_ls_missing() {
+        let fs = tree();
+        let paths = vec![PathBuf::from("/missing"), PathBuf::from("/data/readme")];
+        let mut out = vec![];
+        let failed = Ls::new()
+            .run(&HdfsFileSystem::new(&fs), &paths, &mut out)
+            .unwrap();
+        assert_eq!(1, failed);
+        assert_eq!("/data/readme\n", String::from_utf8(out).unwrap());
+
+        // Counted once, with its own error rather than as missing
+        fs.inject_error(Operation::Stat, "/data/readme", || {
+            Error::RemoteException(
+                String::from("AccessControlException"),
+                String::from("denied"),
+            )
+        });
+        let failed = Ls::new()
+            .run(&HdfsFileSystem::new(&fs), &paths[1..], &mut vec![])
+            .unwrap();
+        assert_eq!(1, failed);
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!("-", format_time(None));
+        assert_eq!("1970-01-01 00:00", format_time(Some(UNIX_EPOCH)));
+        let time = |seconds| Some(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!("2000-02-29 12:30", format_time(time(951_827_400)));
+        assert_eq!("2023-11-14 22:13", format_time(time(1_700_000_000)));
+    }
+}
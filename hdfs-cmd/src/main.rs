@@ -4,6 +4,8 @@ extern crate clap;
 extern crate serde_derive;
 extern crate dirs;
 extern crate env_logger;
+#[cfg(test)]
+extern crate fs_test;
 extern crate hdfs;
 extern crate serde;
 extern crate serde_json;
@@ -11,7 +13,12 @@ extern crate toml;
 extern crate walk;
 
 mod config;
+mod count;
+mod du;
 mod err;
+mod find;
+mod ls;
+mod quota;
 mod walk_hdfs;
 
 use clap::App;
@@ -19,81 +26,151 @@ use std::env;
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
-use std::path::PathBuf;
-use walk::walk::{DirEntryTrait, FileSystem, MetadataTrait};
-
-macro_rules! try_or_print {
-    ($e: expr) => {
-        match ($e) {
-            Ok(value) => value,
-            Err(e) => println!("{:?}", e),
-        }
-    };
-}
+use std::path::{Path, PathBuf};
+use walk::filter::{MatchOn, NameFilter, PathFilter, RegexFilter};
 
-fn ls(config_path: PathBuf, gateway: Option<&str>, path: PathBuf) {
-    //let hdfs_fs = hdfs::hdfs::get_hdfs(config_path, gateway, None).unwrap();
-    //let fs = walk_hdfs::HdfsFileSystem::new(&hdfs_fs);
-    let fs = walk::linuxfs::LinuxFS::default();
+/// Lists HDFS, or the local disk for `file://` paths and all paths with `--local`.
+/// Returns the number of paths and entries that failed.
+fn ls<F>(matches: &clap::ArgMatches, hdfs_fs: F, out: &mut dyn Write) -> Result<usize, err::Error>
+where
+    F: FnOnce() -> Result<Box<dyn hdfs::fs::FileSystem>, hdfs::err::Error>,
+{
+    let mut ls = ls::Ls::new();
+    ls.long(matches.is_present("long"));
+    ls.human(matches.is_present("human"));
+    ls.reverse(matches.is_present("reverse"));
+    ls.directories(matches.is_present("directory"));
+    ls.recursive(matches.is_present("recursive"));
+    if matches.is_present("time") {
+        ls.sort(ls::Sort::Modified);
+    } else if matches.is_present("size") {
+        ls.sort(ls::Sort::Size);
+    }
+    for filter in name_filters(matches)? {
+        ls.filter(filter);
+    }
+    for pattern in matches.values_of("exclude").into_iter().flatten() {
+        ls.exclude(pattern)?;
+    }
 
-    let walk: Vec<Result<_, _>> = walk::walk::WalkBuilder::new(&fs)
-        .with_path(path)
-        .build()
+    let local = matches.is_present("local");
+    let (local_paths, hdfs_paths): (Vec<_>, Vec<_>) = matches
+        .values_of("PATH")
         .unwrap()
-        .collect();
-
-    let mut walk = walk;
-    if walk.len() == 1 {
-        try_or_print!(walk.pop().unwrap().map(|item| {
-            print_item(&fs, &item);
-        }));
-    } else {
-        for item in walk {
-            try_or_print!(item.map(|i| print_item(&fs, &i)));
-        }
+        .partition(|path| local || path.starts_with(LOCAL_SCHEME));
+    let mut failed = 0;
+    if !local_paths.is_empty() {
+        let paths: Vec<_> = local_paths
+            .iter()
+            .map(|path| PathBuf::from(path.strip_prefix(LOCAL_SCHEME).unwrap_or(path)))
+            .collect();
+        failed += ls.run(&walk::linuxfs::LinuxFS::default(), &paths, out)?;
+    }
+    if !hdfs_paths.is_empty() {
+        let hdfs_fs = hdfs_fs()?;
+        let paths: Vec<_> = hdfs_paths.iter().map(PathBuf::from).collect();
+        failed += ls.run(
+            &walk_hdfs::HdfsFileSystem::new(hdfs_fs.as_ref()),
+            &paths,
+            out,
+        )?;
     }
+    Ok(failed)
 }
 
-fn print_item(fs: &walk::linuxfs::LinuxFS, item: &walk::walk::WalkItem, bool print_path) {
-    if item.is_dir() {
-        println!("{}:", item.path().display());
-        print_dir(&fs, &item.path());
-    } else if print_path {
-    }    else {
-        println!("{}", file_name(&item.path()));
+/// Filters of the names listed by `ls`.
+fn name_filters(matches: &clap::ArgMatches) -> Result<Vec<Box<dyn PathFilter>>, err::Error> {
+    let mut filters: Vec<Box<dyn PathFilter>> = vec![];
+    if let Some(regex) = matches.value_of("regex") {
+        filters.push(Box::new(RegexFilter::new(regex, MatchOn::Name)?));
     }
+    if let Some(pattern) = matches.value_of("iname") {
+        filters.push(Box::new(NameFilter::case_insensitive(pattern)?));
+    }
+    Ok(filters)
+}
+
+/// Kerberized clusters still go through libhdfs3, the native client only does simple auth.
+fn use_native_client(config_path: &PathBuf) -> bool {
+    hdfs::config::Config::new(config_path)
+        .map(|c| c.get_string(AUTHENTICATION).unwrap_or("simple") == "simple")
+        .unwrap_or(false)
 }
 
-fn print_dir(fs: &walk::linuxfs::LinuxFS, path: &PathBuf) {
-    for i in fs.read_dir(path).unwrap() {
-        let i = i.unwrap();
-        print!("{} ", file_name(&i.path()));
+/// Retry settings of the hadoop config, overridden by the ones of ~/.hdfsrc.
+fn retry_policy(
+    home_config: Option<&config::Config>,
+    config_path: &PathBuf,
+) -> hdfs::fs::RetryPolicy {
+    let policy = hdfs::config::Config::new(config_path)
+        .map(|c| hdfs::fs::RetryPolicy::from_config(&c))
+        .unwrap_or_default();
+    match home_config.and_then(|c| c.retry.as_ref()) {
+        Some(retry) => retry.apply(policy),
+        None => policy,
     }
-    println!()
 }
 
-fn file_name(path: &PathBuf) -> &str {
-    path.file_name().unwrap().to_str().unwrap()
+/// Connects to the fastest backend available for the gateway.
+fn get_fs(
+    home_config: Option<&config::Config>,
+    config_path: PathBuf,
+    gateway: Option<&str>,
+) -> Result<Box<dyn hdfs::fs::FileSystem>, hdfs::err::Error> {
+    let policy = retry_policy(home_config, &config_path);
+    if let Some(httpfs) = home_config.and_then(|c| c.httpfs(gateway)) {
+        let httpfs = hdfs::webhdfs::get_httpfs(httpfs.to_options());
+        Ok(Box::new(hdfs::fs::RetryFileSystem::new(httpfs, policy)))
+    } else if use_native_client(&config_path) {
+        let client = hdfs::rpc::get_namenode_client(config_path, gateway, None)?;
+        Ok(Box::new(hdfs::fs::RetryFileSystem::new(client, policy)))
+    } else {
+        let hdfs_fs = hdfs::hdfs::get_hdfs(config_path, gateway, None)?;
+        Ok(Box::new(hdfs::fs::RetryFileSystem::new(hdfs_fs, policy)))
+    }
 }
 
-fn text(config_path: PathBuf, gateway: Option<&str>, path: PathBuf) {
-    let hdfs_fs = hdfs::hdfs::get_hdfs(config_path, gateway, None).unwrap();
+fn text(
+    hdfs_fs: &dyn hdfs::fs::FileSystem,
+    path: &Path,
+    out: &mut dyn Write,
+) -> Result<(), hdfs::err::Error> {
+    let mut reader = hdfs_fs.open(path)?;
+    std::io::copy(&mut reader, out)?;
+    Ok(())
+}
 
-    if hdfs_fs.exists(&path).unwrap() {
-        let mut f = hdfs::hdfs::OpenOptions::new()
-            .read(true)
-            .open(&hdfs_fs, path)
-            .unwrap();
-        let mut buffer = vec![];
-        f.read_to_end(&mut buffer).unwrap();
+/// Copies `path` to `dest`, or inside it when it is a directory, and returns the copy.
+fn get(
+    hdfs_fs: &dyn hdfs::fs::FileSystem,
+    path: &Path,
+    dest: Option<&Path>,
+) -> Result<PathBuf, hdfs::err::Error> {
+    let mut dest = dest.unwrap_or_else(|| Path::new(".")).to_path_buf();
+    if dest.is_dir() {
+        dest.push(path.file_name().unwrap_or_default());
+    }
+    let mut reader = hdfs_fs.open(path)?;
+    let mut file = fs::File::create(&dest)?;
+    if let Err(e) = std::io::copy(&mut reader, &mut file) {
+        let _ = fs::remove_file(&dest);
+        return Err(e.into());
+    }
+    Ok(dest)
+}
 
-        std::io::stdout().write_all(buffer.as_slice()).unwrap();
-    } else {
-        println!("File {} not found", path.display())
+fn print_error(command: &str, path: &Path, error: hdfs::err::Error) {
+    match error {
+        hdfs::err::Error::FileNotFound(_) => {
+            eprintln!("{}: {}: No such file or directory", command, path.display())
+        }
+        e => eprintln!("{}: {}: {}", command, path.display(), e),
     }
 }
 
 const DEFAULT_PATH_STR: &str = ".hdfsrc";
+const AUTHENTICATION: &str = "hadoop.security.authentication";
+const LOCAL_SCHEME: &str = "file://";
 
 fn write_config(config: &config::Config) -> Result<(), err::Error> {
     let home = dirs::home_dir();
@@ -172,13 +249,153 @@ fn main() {
         });
 
     if let Some(matches) = matches.subcommand_matches("ls") {
-        let path = matches.value_of("PATH").unwrap();
-        let path = PathBuf::from(path);
-        ls(config, gateway, path);
+        let hdfs_fs = || get_fs(home_config.as_ref(), config, gateway);
+        match ls(matches, hdfs_fs, &mut std::io::stdout()) {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("ls: {}", e);
+                std::process::exit(1)
+            }
+        }
     } else if let Some(matches) = matches.subcommand_matches("cat") {
         let path = matches.value_of("PATH").unwrap();
         let path = PathBuf::from(path);
-        text(config, gateway, path);
+        let result = get_fs(home_config.as_ref(), config, gateway)
+            .and_then(|hdfs_fs| text(hdfs_fs.as_ref(), &path, &mut std::io::stdout()));
+        if let Err(e) = result {
+            print_error("cat", &path, e);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("get") {
+        let path = PathBuf::from(matches.value_of("PATH").unwrap());
+        let dest = matches.value_of("DEST").map(PathBuf::from);
+        let result = get_fs(home_config.as_ref(), config, gateway)
+            .and_then(|hdfs_fs| get(hdfs_fs.as_ref(), &path, dest.as_deref()).map(|_| ()));
+        if let Err(e) = result {
+            print_error("get", &path, e);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("find") {
+        let path = PathBuf::from(matches.value_of("PATH").unwrap());
+        let expression: Vec<_> = matches
+            .values_of("EXPRESSION")
+            .map(|values| values.collect())
+            .unwrap_or_default();
+        let result = find::Find::parse(&expression).and_then(|mut find| {
+            for pattern in matches.values_of("exclude").into_iter().flatten() {
+                find.exclude(pattern)?;
+            }
+            if let Some(file) = matches.value_of("checkpoint") {
+                find.checkpoint(Path::new(file));
+            }
+            let hdfs_fs = get_fs(home_config.as_ref(), config, gateway)?;
+            find.run(hdfs_fs.as_ref(), &path, &mut std::io::stdout())
+        });
+        match result {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("find: {}", e);
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("du") {
+        let path = PathBuf::from(matches.value_of("PATH").unwrap_or("."));
+        let mut du = du::Du::new();
+        du.human(matches.is_present("human"));
+        du.sort(matches.is_present("sort"));
+        if matches.is_present("summarize") {
+            du.max_depth(0);
+        } else if matches.is_present("max-depth") {
+            du.max_depth(value_t!(matches, "max-depth", usize).unwrap_or_else(|e| e.exit()));
+        }
+        if let Some(file) = matches.value_of("checkpoint") {
+            du.checkpoint(Path::new(file));
+        }
+        let result = matches
+            .values_of("exclude")
+            .into_iter()
+            .flatten()
+            .try_for_each(|pattern| du.exclude(pattern))
+            .and_then(|_| {
+                let hdfs_fs = get_fs(home_config.as_ref(), config, gateway)?;
+                du.run(hdfs_fs.as_ref(), &path, &mut std::io::stdout())
+            });
+        match result {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("du: {}", e);
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("count") {
+        let paths: Vec<_> = matches
+            .values_of("PATH")
+            .unwrap()
+            .map(PathBuf::from)
+            .collect();
+        let mut count = count::Count::new();
+        count.quotas(matches.is_present("quotas"));
+        count.human(matches.is_present("human"));
+        count.header(matches.is_present("header"));
+        let result = get_fs(home_config.as_ref(), config, gateway)
+            .map_err(err::Error::from)
+            .and_then(|hdfs_fs| count.run(hdfs_fs.as_ref(), &paths, &mut std::io::stdout()));
+        match result {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("count: {}", e);
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("quota") {
+        use quota::QuotaChange;
+        let (matches, namespace, space) = if let Some(matches) = matches.subcommand_matches("set") {
+            let namespace = if matches.is_present("namespace") {
+                QuotaChange::Set(value_t!(matches, "namespace", u64).unwrap_or_else(|e| e.exit()))
+            } else {
+                QuotaChange::Keep
+            };
+            let space = match matches.value_of("space").map(walk::filter::parse_size) {
+                Some(Ok(size)) => QuotaChange::Set(size),
+                Some(Err(e)) => {
+                    eprintln!("quota: {}", e);
+                    std::process::exit(1)
+                }
+                None => QuotaChange::Keep,
+            };
+            (matches, namespace, space)
+        } else if let Some(matches) = matches.subcommand_matches("clear") {
+            let both = !matches.is_present("namespace") && !matches.is_present("space");
+            let change = |name| {
+                if both || matches.is_present(name) {
+                    QuotaChange::Clear
+                } else {
+                    QuotaChange::Keep
+                }
+            };
+            (matches, change("namespace"), change("space"))
+        } else {
+            eprintln!("{}", matches.usage());
+            std::process::exit(1)
+        };
+        let paths: Vec<_> = matches
+            .values_of("PATH")
+            .unwrap()
+            .map(PathBuf::from)
+            .collect();
+        match get_fs(home_config.as_ref(), config, gateway) {
+            Ok(hdfs_fs) => {
+                if quota::set_quotas(hdfs_fs.as_ref(), &paths, namespace, space) > 0 {
+                    std::process::exit(1)
+                }
+            }
+            Err(e) => {
+                eprintln!("quota: {}", e);
+                std::process::exit(1)
+            }
+        }
     } else if let Some(matches) = matches.subcommand_matches("gateway") {
         if let Some(_matches) = matches.subcommand_matches("list") {
             for g in hdfs::hdfs::list_gateway(config).unwrap() {
@@ -188,7 +405,11 @@ fn main() {
             let gateway = matches.value_of("switch_gateway").unwrap();
 
             let gateways = hdfs::hdfs::list_gateway(config).unwrap();
-            if !gateways.contains(&gateway.to_owned()) {
+            let is_httpfs = home_config
+                .as_ref()
+                .and_then(|c| c.httpfs(Some(gateway)))
+                .is_some();
+            if !gateways.contains(&gateway.to_owned()) && !is_httpfs {
                 println!(
                     "No gateway with name \"{}\" found in hadoop config",
                     gateway
@@ -206,3 +427,191 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use clap::App;
+    use fs_test::{FakeHdfs, Fault, FaultyFs, TempDir};
+    use hdfs::err::Error;
+    use hdfs::fs::{FileSystem, MemoryFileSystem};
+    use std::fs;
+    use std::path::Path;
+
+    /// Runs `hdfs-cmd ls` with the arguments, on `hdfs_fs` unless listing the local disk.
+    fn ls(args: &[&str], hdfs_fs: Option<FakeHdfs>) -> (String, usize) {
+        let yaml = load_yaml!("cli.yml");
+        let args = ["hdfs-cmd", "ls"].iter().chain(args);
+        let matches = App::from_yaml(yaml).get_matches_from_safe(args).unwrap();
+        let matches = matches.subcommand_matches("ls").unwrap();
+        let hdfs_fs = || match hdfs_fs {
+            Some(hdfs_fs) => Ok(Box::new(hdfs_fs) as Box<dyn FileSystem>),
+            None => panic!("HDFS used to list local paths"),
+        };
+        let mut out = vec![];
+        let failed = ::ls(matches, hdfs_fs, &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), failed)
+    }
+
+    #[test]
+    fn test_text() {
+        let hdfs_fs = MemoryFileSystem::default();
+        hdfs_fs.add_file("/data/file", b"some text").unwrap();
+
+        let mut out = vec![];
+        ::text(&hdfs_fs, Path::new("/data/file"), &mut out).unwrap();
+        assert_eq!(b"some text", out.as_slice());
+
+        match ::text(&hdfs_fs, Path::new("/data/missing"), &mut out) {
+            Err(Error::FileNotFound(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_du_arguments() {
+        let yaml = load_yaml!("cli.yml");
+        let matches = App::from_yaml(yaml)
+            .get_matches_from_safe(vec!["hdfs-cmd", "du", "-sh", "-x", "_*", "/data"])
+            .unwrap();
+        let matches = matches.subcommand_matches("du").unwrap();
+        assert!(matches.is_present("summarize"));
+        assert!(matches.is_present("human"));
+        assert_eq!(Some("/data"), matches.value_of("PATH"));
+
+        let conflicting = vec!["hdfs-cmd", "du", "-s", "--max-depth", "2", "/data"];
+        assert!(App::from_yaml(yaml)
+            .get_matches_from_safe(conflicting)
+            .is_err());
+    }
+
+    #[test]
+    fn test_ls_arguments() {
+        let yaml = load_yaml!("cli.yml");
+        let matches = App::from_yaml(yaml)
+            .get_matches_from_safe(vec!["hdfs-cmd", "ls", "-lhtr", "/data", "/tmp/*"])
+            .unwrap();
+        let matches = matches.subcommand_matches("ls").unwrap();
+        for flag in &["long", "human", "time", "reverse"] {
+            assert!(matches.is_present(flag), "{}", flag);
+        }
+        assert_eq!(2, matches.values_of("PATH").unwrap().count());
+
+        let conflicting = vec!["hdfs-cmd", "ls", "-d", "-R", "/data"];
+        assert!(App::from_yaml(yaml)
+            .get_matches_from_safe(conflicting)
+            .is_err());
+    }
+
+    #[test]
+    fn test_ls_hdfs() {
+        let hdfs_fs = || {
+            FakeHdfs::new()
+                .dir("/data/logs")
+                .file_with("/data/part-0", 2048, "etl", 0o644, 3, 1024)
+                .file("/data/part-1", b"content")
+        };
+        assert_eq!(
+            (String::from("logs part-0 part-1\n"), 0),
+            ls(&["/data"], Some(hdfs_fs()))
+        );
+
+        let (out, failed) = ls(&["-lh", "/data/part-*"], Some(hdfs_fs()));
+        assert_eq!(0, failed);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("-rw-r--r--   3 etl "), "{}", lines[0]);
+        assert!(lines[0].ends_with(" /data/part-0"), "{}", lines[0]);
+        assert!(lines[0].contains(" 2.0 K "), "{}", lines[0]);
+        assert!(lines[1].ends_with(" /data/part-1"), "{}", lines[1]);
+
+        assert_eq!(
+            (String::from("logs\n"), 0),
+            ls(&["--regex", "l.*", "/data"], Some(hdfs_fs()))
+        );
+        assert_eq!(1, ls(&["/missing"], Some(hdfs_fs())).1);
+    }
+
+    #[test]
+    fn test_ls_local() {
+        let temp_dir = TempDir::default();
+        fs::create_dir(temp_dir.path().join("logs")).unwrap();
+        fs::write(temp_dir.path().join("part-0"), b"content").unwrap();
+        let dir = temp_dir.path().to_str().unwrap();
+
+        let expected = (String::from("logs part-0\n"), 0);
+        assert_eq!(expected, ls(&["--local", dir], None));
+        let uri = format!("file://{}", dir);
+        assert_eq!(expected, ls(&[&uri], None));
+
+        let hdfs_fs = FakeHdfs::new().file("/data/part-1", b"");
+        let local_file = format!("file://{}/part-0", dir);
+        let (out, failed) = ls(&[&local_file, "/data/part-1"], Some(hdfs_fs));
+        assert_eq!(0, failed);
+        assert_eq!(format!("{}/part-0\n/data/part-1\n", dir), out);
+    }
+
+    #[test]
+    fn test_quota_arguments() {
+        let yaml = load_yaml!("cli.yml");
+        let matches = App::from_yaml(yaml)
+            .get_matches_from_safe(vec!["hdfs-cmd", "quota", "set", "-s", "10g", "/a", "/b"])
+            .unwrap();
+        let matches = matches
+            .subcommand_matches("quota")
+            .and_then(|matches| matches.subcommand_matches("set"))
+            .unwrap();
+        assert_eq!(Some("10g"), matches.value_of("space"));
+        assert_eq!(2, matches.values_of("PATH").unwrap().count());
+
+        let without_quota = vec!["hdfs-cmd", "quota", "set", "/a"];
+        assert!(App::from_yaml(yaml)
+            .get_matches_from_safe(without_quota)
+            .is_err());
+    }
+
+    #[test]
+    fn test_get() {
+        let temp_dir = TempDir::default();
+        let temp_dir = temp_dir.path();
+        let hdfs_fs = MemoryFileSystem::default();
+        hdfs_fs.add_file("/data/file", b"content").unwrap();
+
+        let copy = ::get(&hdfs_fs, Path::new("/data/file"), Some(temp_dir)).unwrap();
+        assert_eq!(temp_dir.join("file"), copy);
+        assert_eq!("content", fs::read_to_string(&copy).unwrap());
+
+        let renamed = temp_dir.join("renamed");
+        ::get(&hdfs_fs, Path::new("/data/file"), Some(renamed.as_path())).unwrap();
+        assert_eq!("content", fs::read_to_string(&renamed).unwrap());
+
+        assert!(::get(&hdfs_fs, Path::new("/data"), Some(temp_dir)).is_err());
+    }
+
+    #[test]
+    fn test_get_with_partial_reads() {
+        let temp_dir = TempDir::default();
+        let content: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        let memory_fs = MemoryFileSystem::default();
+        memory_fs.add_file("/data/file", &content).unwrap();
+        let hdfs_fs = FaultyFs::new(memory_fs, 1).partial_io(true);
+
+        let copy = ::get(&hdfs_fs, Path::new("/data/file"), Some(temp_dir.path())).unwrap();
+        assert_eq!(content, fs::read(&copy).unwrap());
+        assert!(hdfs_fs.stats().partial > 0);
+    }
+
+    #[test]
+    fn test_get_with_connection_drop() {
+        let temp_dir = TempDir::default();
+        let memory_fs = MemoryFileSystem::default();
+        memory_fs.add_file("/data/file", &[0; 100_000]).unwrap();
+        let hdfs_fs =
+            FaultyFs::new(memory_fs, 1).script(vec![Fault::None, Fault::None, Fault::Drop]);
+
+        match ::get(&hdfs_fs, Path::new("/data/file"), Some(temp_dir.path())) {
+            Err(Error::Io(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(!temp_dir.path().join("file").exists());
+    }
+}
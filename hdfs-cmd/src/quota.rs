@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+
+/// What to do with one of the quotas of a directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuotaChange {
+    Keep,
+    Set(u64),
+    Clear,
+}
+
+impl QuotaChange {
+    fn apply<F>(self, set: F) -> Result<(), hdfs::err::Error>
+    where
+        F: FnOnce(Option<u64>) -> Result<(), hdfs::err::Error>,
+    {
+        match self {
+            QuotaChange::Keep => Ok(()),
+            QuotaChange::Set(quota) => set(Some(quota)),
+            QuotaChange::Clear => set(None),
+        }
+    }
+}
+
+/// Changes the namespace and space quotas of directories, like `hdfs dfsadmin
+/// -setQuota` and `-setSpaceQuota`. Returns the number of paths that failed.
+pub fn set_quotas(
+    hdfs_fs: &dyn hdfs::fs::FileSystem,
+    paths: &[PathBuf],
+    namespace: QuotaChange,
+    space: QuotaChange,
+) -> usize {
+    let mut failed = 0;
+    for path in paths {
+        let result = namespace
+            .apply(|quota| hdfs_fs.set_namespace_quota(path, quota))
+            .and_then(|_| space.apply(|quota| hdfs_fs.set_space_quota(path, quota)));
+        if let Err(e) = result {
+            failed += 1;
+            eprintln!("quota: {}: {}", path.display(), e);
+        }
+    }
+    failed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{set_quotas, QuotaChange};
+    use fs_test::{FakeHdfs, Quota};
+    use hdfs::fs::MemoryFileSystem;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_set_quotas() {
+        let fs = FakeHdfs::new().dir("/a").dir("/b").file("/a/part-0", b"");
+        let paths = vec![PathBuf::from("/a"), PathBuf::from("/b")];
+
+        let failed = set_quotas(&fs, &paths, QuotaChange::Set(100), QuotaChange::Set(1024));
+        assert_eq!(0, failed);
+        let quota = Quota {
+            namespace: Some(100),
+            space: Some(1024),
+        };
+        assert_eq!(Some(quota), fs.get_quota("/a"));
+        assert_eq!(Some(quota), fs.get_quota("/b"));
+
+        set_quotas(&fs, &paths[..1], QuotaChange::Clear, QuotaChange::Keep);
+        assert_eq!(
+            Some(Quota {
+                namespace: None,
+                space: Some(1024),
+            }),
+            fs.get_quota("/a")
+        );
+        set_quotas(&fs, &paths, QuotaChange::Clear, QuotaChange::Clear);
+        assert_eq!(None, fs.get_quota("/a"));
+        assert_eq!(None, fs.get_quota("/b"));
+
+        let paths = vec![PathBuf::from("/a/part-0"), PathBuf::from("/missing")];
+        let failed = set_quotas(&fs, &paths, QuotaChange::Set(1), QuotaChange::Keep);
+        assert_eq!(2, failed);
+    }
+
+    #[test]
+    fn test_set_quotas_unsupported() {
+        let fs = MemoryFileSystem::default();
+        fs.add_file("/a/part-0", b"").unwrap();
+        let paths = vec![PathBuf::from("/a")];
+        assert_eq!(
+            1,
+            set_quotas(&fs, &paths, QuotaChange::Keep, QuotaChange::Clear)
+        );
+    }
+}
@@ -1,48 +1,95 @@
 use err::Error;
+use hdfs::err::Error as HdfsError;
+use hdfs::fs::FileSystem as HdfsFs;
+use hdfs::hdfs::FileStatus;
+use std::io::Read;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::vec;
 use walk::walk::{DirEntryTrait, FileSystem, MetadataTrait};
 
-pub struct HdfsFileSystem<'a>(&'a hdfs::hdfs::HDFileSystem);
+/// Walks any backend of `hdfs::fs`.
+pub struct HdfsFileSystem<'a>(&'a dyn HdfsFs);
 
 impl<'a> HdfsFileSystem<'a> {
-    pub fn new(fs: &'a hdfs::hdfs::HDFileSystem) -> HdfsFileSystem {
+    pub fn new(fs: &'a dyn HdfsFs) -> HdfsFileSystem<'a> {
         HdfsFileSystem(fs)
     }
 }
 
-pub struct ReadDirWrapper(hdfs::hdfs::ReadDir);
-pub struct MetadataWrapper(hdfs::hdfs::DirEntry);
-pub struct DirEntryWrapper(hdfs::hdfs::DirEntry);
+pub struct ReadDirWrapper(vec::IntoIter<FileStatus>);
+/// Listings return the whole status of each entry, nothing is fetched afterwards.
+pub struct Entry(FileStatus);
 
 impl Iterator for ReadDirWrapper {
-    type Item = Result<DirEntryWrapper, Error>;
+    type Item = Result<Entry, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|entry| Ok(DirEntryWrapper(entry)))
+        self.0.next().map(|status| Ok(Entry(status)))
     }
 }
 
-impl MetadataTrait for MetadataWrapper {
+/// HDFS times are milliseconds, zero when not recorded like access times of directories.
+fn time(millis: u64) -> Option<SystemTime> {
+    match millis {
+        0 => None,
+        millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+    }
+}
+
+impl MetadataTrait for Entry {
     fn is_dir(&self) -> bool {
         self.0.is_dir()
     }
+
+    fn len(&self) -> u64 {
+        self.0.len
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        time(self.0.modification_time)
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        time(self.0.access_time)
+    }
+
+    fn permissions(&self) -> Option<u32> {
+        Some(u32::from(self.0.permission))
+    }
+
+    fn owner(&self) -> Option<String> {
+        Some(self.0.owner.to_owned())
+    }
+
+    fn group(&self) -> Option<String> {
+        Some(self.0.group.to_owned())
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.0.symlink.is_some()
+    }
+
+    fn symlink_target(&self) -> Option<PathBuf> {
+        self.0.symlink.clone()
+    }
+
+    fn replication(&self) -> Option<u16> {
+        Some(self.0.replication)
+    }
 }
 
-impl DirEntryTrait for DirEntryWrapper {
+impl DirEntryTrait for Entry {
     fn path(&self) -> PathBuf {
         self.0.path()
     }
-
-    fn is_dir(&self) -> bool {
-        self.0.is_dir()
-    }
 }
 
 impl<'a> FileSystem for HdfsFileSystem<'a> {
     type Error = Error;
-    type DirEntry = DirEntryWrapper;
+    type DirEntry = Entry;
     type ReadDir = ReadDirWrapper;
-    type Metadata = MetadataWrapper;
+    type Metadata = Entry;
 
     fn exists(&self, path: &PathBuf) -> bool {
         self.0.exists(path).unwrap_or(false)
@@ -52,10 +99,28 @@ impl<'a> FileSystem for HdfsFileSystem<'a> {
         Ok(self.0.current_dir()?)
     }
 
+    /// Entries are joined to `path`, some backends list full URIs.
     fn read_dir(&self, path: &PathBuf) -> Result<Self::ReadDir, Self::Error> {
-        Ok(ReadDirWrapper(self.0.list_directory(path)?))
+        let mut entries = self.0.list(path)?;
+        for entry in &mut entries {
+            if let Some(joined) = entry.path.file_name().map(|name| path.join(name)) {
+                entry.path = joined;
+            }
+        }
+        Ok(ReadDirWrapper(entries.into_iter()))
     }
+
     fn metadata(&self, path: &PathBuf) -> Result<Self::Metadata, Self::Error> {
-        Ok(MetadataWrapper(self.0.path_info(path)?))
+        Ok(Entry(self.0.stat(path)?))
+    }
+
+    fn read_to_string(&self, path: &PathBuf) -> Result<String, Self::Error> {
+        let mut content = String::new();
+        self.0.open(path)?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        matches!(*error, Error::HdfsError(HdfsError::FileNotFound(_)))
     }
 }
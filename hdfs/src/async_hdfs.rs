@@ -0,0 +1,478 @@
+//! Tokio front end running the blocking backends on a bounded pool of threads.
+//!
+//! Dropping a pending future never cancels the operation: a metadata call still
+//! completes on the pool and its result is discarded, data read for an abandoned
+//! `read` is kept for the next one. Like `tokio::fs::File`, a write returns once its
+//! data is handed to the pool and failures surface on the following call or `flush`.
+use err::Error;
+use fs::{FileSystem, MemoryFile, MemoryFileSystem};
+use hdfs::{self, FileStatus, HDFileSystem, OpenOptions};
+use std::future::Future;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use tokio::sync::oneshot;
+
+/// Largest chunk moved to or from the pool by a single read or write.
+const MAX_BUF_SIZE: usize = 2 * 1024 * 1024;
+
+/// A file system whose open files can be moved to the pool threads.
+pub trait Backend: FileSystem + Send + Sync + 'static {
+    type File: Read + Write + Seek + Send + 'static;
+
+    fn open_file(&self, path: &Path) -> Result<Self::File, Error>;
+    fn create_file(&self, path: &Path, overwrite: bool) -> Result<Self::File, Error>;
+}
+
+impl Backend for HDFileSystem {
+    type File = hdfs::File;
+
+    fn open_file(&self, path: &Path) -> Result<hdfs::File, Error> {
+        OpenOptions::new().read(true).open(self, path)
+    }
+
+    fn create_file(&self, path: &Path, overwrite: bool) -> Result<hdfs::File, Error> {
+        HDFileSystem::create_file(self, path, overwrite)
+    }
+}
+
+impl Backend for MemoryFileSystem {
+    type File = MemoryFile;
+
+    fn open_file(&self, path: &Path) -> Result<MemoryFile, Error> {
+        MemoryFileSystem::open_file(self, path)
+    }
+
+    fn create_file(&self, path: &Path, overwrite: bool) -> Result<MemoryFile, Error> {
+        MemoryFileSystem::create_file(self, path, overwrite)
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed number of threads running blocking jobs in submission order.
+pub struct BlockingPool {
+    sender: Mutex<Sender<Job>>,
+}
+
+impl BlockingPool {
+    pub fn new(threads: usize) -> BlockingPool {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("hdfs-blocking-{}", i))
+                .spawn(move || BlockingPool::work(&receiver))
+                .expect("cannot start blocking thread");
+        }
+        BlockingPool {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Threads stop once the pool is dropped and the queue drained.
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = receiver.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return,
+            }
+        }
+    }
+
+    pub fn spawn<T, F>(&self, f: F) -> oneshot::Receiver<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            // The caller may be gone already
+            let _ = sender.send(f());
+        });
+        // Workers only stop when the sender is dropped, the send cannot fail
+        let _ = self.sender.lock().unwrap().send(job);
+        receiver
+    }
+}
+
+fn lost() -> io::Error {
+    io::Error::other("blocking thread panicked")
+}
+
+/// A blocking call running on the pool.
+pub struct Task<T>(oneshot::Receiver<Result<T, Error>>);
+
+impl<T> Future for Task<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, Error>> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(Error::Io(lost()))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Async version of a `Backend`, cheap to clone.
+pub struct AsyncHdfs<F> {
+    fs: Arc<F>,
+    pool: Arc<BlockingPool>,
+}
+
+impl<F> Clone for AsyncHdfs<F> {
+    fn clone(&self) -> AsyncHdfs<F> {
+        AsyncHdfs {
+            fs: self.fs.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<F: Backend> AsyncHdfs<F> {
+    /// At most `threads` calls run at the same time, the others wait in line.
+    pub fn new(fs: F, threads: usize) -> AsyncHdfs<F> {
+        AsyncHdfs {
+            fs: Arc::new(fs),
+            pool: Arc::new(BlockingPool::new(threads)),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.fs
+    }
+
+    pub(crate) fn spawn<T, C>(&self, call: C) -> oneshot::Receiver<T>
+    where
+        T: Send + 'static,
+        C: FnOnce(&F) -> T + Send + 'static,
+    {
+        let fs = self.fs.clone();
+        self.pool.spawn(move || call(&fs))
+    }
+
+    fn run<T, C>(&self, call: C) -> Task<T>
+    where
+        T: Send + 'static,
+        C: FnOnce(&F) -> Result<T, Error> + Send + 'static,
+    {
+        Task(self.spawn(call))
+    }
+
+    pub fn current_dir(&self) -> Task<PathBuf> {
+        self.run(|fs| fs.current_dir())
+    }
+
+    pub fn stat<P: AsRef<Path>>(&self, path: P) -> Task<FileStatus> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.stat(&path))
+    }
+
+    pub fn list<P: AsRef<Path>>(&self, path: P) -> Task<Vec<FileStatus>> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.list(&path))
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> Task<bool> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.exists(&path))
+    }
+
+    pub fn delete<P: AsRef<Path>>(&self, path: P, recursive: bool) -> Task<bool> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.delete(&path, recursive))
+    }
+
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Task<()> {
+        let from = from.as_ref().to_owned();
+        let to = to.as_ref().to_owned();
+        self.run(move |fs| fs.rename(&from, &to))
+    }
+
+    pub fn mkdirs<P: AsRef<Path>>(&self, path: P) -> Task<bool> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.mkdirs(&path))
+    }
+
+    pub fn set_permission<P: AsRef<Path>>(&self, path: P, permission: u16) -> Task<()> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.set_permission(&path, permission))
+    }
+
+    pub fn set_owner<P: AsRef<Path>>(
+        &self,
+        path: P,
+        owner: Option<String>,
+        group: Option<String>,
+    ) -> Task<()> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.set_owner(&path, owner.as_deref(), group.as_deref()))
+    }
+
+    pub fn set_replication<P: AsRef<Path>>(&self, path: P, replication: u16) -> Task<()> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.set_replication(&path, replication))
+    }
+
+    pub fn set_times<P: AsRef<Path>>(
+        &self,
+        path: P,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Task<()> {
+        let path = path.as_ref().to_owned();
+        self.run(move |fs| fs.set_times(&path, modification_time, access_time))
+    }
+
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Task<AsyncFile<F>> {
+        let path = path.as_ref().to_owned();
+        let async_fs = self.clone();
+        self.run(move |fs| {
+            let file = fs.open_file(&path)?;
+            Ok(AsyncFile::new(async_fs, file))
+        })
+    }
+
+    pub fn create<P: AsRef<Path>>(&self, path: P, overwrite: bool) -> Task<AsyncFile<F>> {
+        let path = path.as_ref().to_owned();
+        let async_fs = self.clone();
+        self.run(move |fs| {
+            let file = fs.create_file(&path, overwrite)?;
+            Ok(AsyncFile::new(async_fs, file))
+        })
+    }
+}
+
+enum Operation {
+    Read(io::Result<Vec<u8>>),
+    Write(io::Result<()>),
+    Flush(io::Result<()>),
+    Seek(io::Result<u64>),
+}
+
+enum State<F: Backend> {
+    Idle(F::File),
+    /// The pool sends the file system back with the file, so that a file nobody waits
+    /// for any more is closed before its file system is dropped.
+    Busy(oneshot::Receiver<(F::File, Operation, Arc<F>)>),
+    /// The file went down with a panicking pool thread
+    Lost,
+}
+
+/// An open file whose reads, writes and seeks run on the pool.
+pub struct AsyncFile<F: Backend> {
+    // Fields are dropped in order, the file is closed first
+    state: State<F>,
+    /// Data read by the pool and not returned yet
+    buffer: Vec<u8>,
+    /// Keeps the file system connected while the file is open
+    async_fs: AsyncHdfs<F>,
+}
+
+// The file moves to and from the pool threads, it is never pinned
+impl<F: Backend> Unpin for AsyncFile<F> {}
+
+impl<F: Backend> AsyncFile<F> {
+    pub(crate) fn new(async_fs: AsyncHdfs<F>, file: F::File) -> AsyncFile<F> {
+        AsyncFile {
+            state: State::Idle(file),
+            buffer: vec![],
+            async_fs,
+        }
+    }
+
+    /// Runs `operation` on the idle file.
+    fn start<O>(&mut self, operation: O)
+    where
+        O: FnOnce(&mut F::File) -> Operation + Send + 'static,
+    {
+        match std::mem::replace(&mut self.state, State::Lost) {
+            State::Idle(mut file) => {
+                let fs = self.async_fs.fs.clone();
+                let receiver = self.async_fs.pool.spawn(move || {
+                    let result = operation(&mut file);
+                    (file, result, fs)
+                });
+                self.state = State::Busy(receiver);
+            }
+            _ => unreachable!("operation started on a busy file"),
+        }
+    }
+
+    /// Waits for the running operation, if any, and returns its outcome.
+    fn poll_idle(&mut self, cx: &mut Context) -> Poll<io::Result<Option<Operation>>> {
+        let result = match self.state {
+            State::Idle(_) => return Poll::Ready(Ok(None)),
+            State::Lost => return Poll::Ready(Err(lost())),
+            State::Busy(ref mut receiver) => match Pin::new(receiver).poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(result) => result,
+            },
+        };
+        match result {
+            Ok((file, mut operation, _)) => {
+                self.state = State::Idle(file);
+                if let Operation::Read(Ok(ref mut data)) = operation {
+                    // Possibly a read abandoned by its caller, the data is still ours
+                    self.buffer.append(data);
+                }
+                Poll::Ready(Ok(Some(operation)))
+            }
+            Err(_) => {
+                self.state = State::Lost;
+                Poll::Ready(Err(lost()))
+            }
+        }
+    }
+
+    /// The position of the pool is ahead of the caller by the buffered data, seeking or
+    /// writing first moves it back.
+    fn take_unread(&mut self) -> i64 {
+        let unread = self.buffer.len() as i64;
+        self.buffer.clear();
+        unread
+    }
+}
+
+/// Errors of a write or a flush nobody waited for.
+fn pending_error(operation: Option<Operation>) -> io::Result<()> {
+    match operation {
+        Some(Operation::Write(Err(e))) | Some(Operation::Flush(Err(e))) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+macro_rules! ready {
+    ($e:expr) => {
+        match $e {
+            Poll::Ready(value) => value,
+            Poll::Pending => return Poll::Pending,
+        }
+    };
+}
+
+impl<F: Backend> AsyncRead for AsyncFile<F> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        loop {
+            if !me.buffer.is_empty() {
+                let len = me.buffer.len().min(buf.remaining());
+                buf.put_slice(&me.buffer[..len]);
+                me.buffer.drain(..len);
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(me.poll_idle(cx))? {
+                Some(Operation::Read(Ok(_))) => {
+                    if me.buffer.is_empty() {
+                        // End of file
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+                Some(Operation::Read(Err(e))) => return Poll::Ready(Err(e)),
+                None => {
+                    if buf.remaining() == 0 {
+                        return Poll::Ready(Ok(()));
+                    }
+                    let len = buf.remaining().min(MAX_BUF_SIZE);
+                    me.start(move |file| {
+                        let mut data = vec![0; len];
+                        Operation::Read(file.read(&mut data).map(|n| {
+                            data.truncate(n);
+                            data
+                        }))
+                    });
+                }
+                operation => pending_error(operation)?,
+            }
+        }
+    }
+}
+
+impl<F: Backend> AsyncWrite for AsyncFile<F> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+        loop {
+            match ready!(me.poll_idle(cx))? {
+                None => {
+                    let unread = me.take_unread();
+                    let data = buf[..buf.len().min(MAX_BUF_SIZE)].to_vec();
+                    let len = data.len();
+                    me.start(move |file| {
+                        let result = if unread > 0 {
+                            file.seek(SeekFrom::Current(-unread)).map(|_| ())
+                        } else {
+                            Ok(())
+                        };
+                        Operation::Write(result.and_then(|_| file.write_all(&data)))
+                    });
+                    return Poll::Ready(Ok(len));
+                }
+                operation => pending_error(operation)?,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        loop {
+            match ready!(me.poll_idle(cx))? {
+                Some(Operation::Flush(result)) => return Poll::Ready(result),
+                None => me.start(|file| Operation::Flush(file.flush())),
+                operation => pending_error(operation)?,
+            }
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<F: Backend> AsyncSeek for AsyncFile<F> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let me = self.get_mut();
+        match me.state {
+            State::Idle(_) => {}
+            State::Busy(_) => {
+                return Err(io::Error::other(
+                    "other file operation is pending, call poll_complete before start_seek",
+                ))
+            }
+            State::Lost => return Err(lost()),
+        }
+        let unread = me.take_unread();
+        let position = match position {
+            SeekFrom::Current(offset) => SeekFrom::Current(offset - unread),
+            position => position,
+        };
+        me.start(move |file| Operation::Seek(file.seek(position)));
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<u64>> {
+        let me = self.get_mut();
+        loop {
+            match ready!(me.poll_idle(cx))? {
+                Some(Operation::Seek(result)) => return Poll::Ready(result),
+                None => {
+                    // No seek was started, only ask for the position
+                    let unread = me.take_unread();
+                    me.start(move |file| Operation::Seek(file.seek(SeekFrom::Current(-unread))));
+                }
+                operation => pending_error(operation)?,
+            }
+        }
+    }
+}
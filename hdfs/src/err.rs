@@ -1,4 +1,6 @@
 use native;
+use std::error;
+use std::fmt;
 use std::io;
 use std::path::PathBuf;
 use util::chars_to_str;
@@ -16,6 +18,10 @@ pub enum Error {
     PathConversionError(String),
     NoError(),
     InvalidPath(PathBuf),
+    Http(String),
+    Rpc(String),
+    RemoteException(String, String),
+    Unsupported(String),
 }
 
 impl From<io::Error> for Error {
@@ -30,6 +36,16 @@ impl Error {
         Error::Io(os_error)
     }
 
+    /// Maps a java exception raised by the namenode or a gateway, `class` being either
+    /// the simple or the fully qualified class name.
+    pub fn remote(class: &str, message: String) -> Error {
+        let simple_name = class.rsplit('.').next().unwrap_or(class);
+        match simple_name {
+            "FileNotFoundException" => Error::FileNotFound(message),
+            _ => Error::RemoteException(simple_name.to_owned(), message),
+        }
+    }
+
     pub fn get_last_hdfs_error() -> Error {
         let hdfs_error_raw = chars_to_str(unsafe { native::hdfsGetLastError() });
         if hdfs_error_raw == "Success" {
@@ -39,3 +55,27 @@ impl Error {
         }
     }
 }
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::FileNotFound(ref message) => write!(f, "file not found: {}", message),
+            Error::DirectoryNotFound(ref path) => {
+                write!(f, "directory not found: {}", path.display())
+            }
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::RemoteException(ref class, ref message) => write!(f, "{}: {}", class, message),
+            Error::Unsupported(ref message) => write!(f, "unsupported: {}", message),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
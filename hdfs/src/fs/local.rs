@@ -0,0 +1,244 @@
+use err::Error;
+use fs::FileSystem;
+use hdfs::{FileStatus, ObjectKind};
+use nix::unistd::{self, Gid, Uid};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The local disk, owners and groups are the numeric ids.
+#[derive(Default)]
+pub struct LocalFileSystem {}
+
+impl LocalFileSystem {
+    pub fn new() -> LocalFileSystem {
+        LocalFileSystem {}
+    }
+}
+
+fn map_error(path: &Path, e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::NotFound => Error::FileNotFound(path.display().to_string()),
+        _ => Error::Io(e),
+    }
+}
+
+fn file_status(path: PathBuf, metadata: &fs::Metadata) -> FileStatus {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_dir() {
+        ObjectKind::Directory
+    } else if file_type.is_file() {
+        ObjectKind::File
+    } else {
+        ObjectKind::Unknown
+    };
+    let symlink = if file_type.is_symlink() {
+        fs::read_link(&path).ok()
+    } else {
+        None
+    };
+    FileStatus {
+        kind,
+        len: if kind == ObjectKind::File {
+            metadata.len()
+        } else {
+            0
+        },
+        owner: metadata.uid().to_string(),
+        group: metadata.gid().to_string(),
+        permission: (metadata.mode() & 0o7777) as u16,
+        modification_time: (metadata.mtime() * 1000 + metadata.mtime_nsec() / 1_000_000) as u64,
+        access_time: (metadata.atime() * 1000 + metadata.atime_nsec() / 1_000_000) as u64,
+        replication: 1,
+        block_size: metadata.blksize(),
+        symlink,
+        path,
+    }
+}
+
+fn parse_id(path: &Path, id: &str) -> Result<u32, Error> {
+    id.parse().map_err(|_| {
+        Error::InvalidPath(PathBuf::from(format!(
+            "{}: {} is not a numeric id",
+            path.display(),
+            id
+        )))
+    })
+}
+
+impl FileSystem for LocalFileSystem {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        env::current_dir().map_err(From::from)
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        let metadata = fs::symlink_metadata(path).map_err(|e| map_error(path, e))?;
+        Ok(file_status(path.to_owned(), &metadata))
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        let status = self.stat(path)?;
+        if !status.is_dir() {
+            return Ok(vec![status]);
+        }
+        let mut entries = vec![];
+        for entry in fs::read_dir(path).map_err(|e| map_error(path, e))? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push(file_status(entry.path(), &metadata));
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        if fs::metadata(path).map_err(|e| map_error(path, e))?.is_dir() {
+            return Err(Error::FileNotFound(format!(
+                "Path is not a file: {}",
+                path.display()
+            )));
+        }
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!overwrite)
+            .open(path)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => Error::RemoteException(
+                    String::from("FileAlreadyExistsException"),
+                    path.display().to_string(),
+                ),
+                _ => Error::Io(e),
+            })?;
+        Ok(Box::new(file))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        let status = match self.stat(path) {
+            Ok(status) => status,
+            Err(Error::FileNotFound(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let result = if !status.is_dir() {
+            fs::remove_file(path)
+        } else if recursive {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_dir(path)
+        };
+        result.map(|_| true).map_err(|e| map_error(path, e))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        if self.exists(to)? {
+            return Err(Error::RemoteException(
+                String::from("FileAlreadyExistsException"),
+                to.display().to_string(),
+            ));
+        }
+        fs::rename(from, to).map_err(|e| map_error(from, e))
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        fs::create_dir_all(path)
+            .map(|_| true)
+            .map_err(|e| map_error(path, e))
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        fs::set_permissions(path, fs::Permissions::from_mode(u32::from(permission)))
+            .map_err(|e| map_error(path, e))
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        let uid = match owner {
+            Some(owner) => Some(Uid::from_raw(parse_id(path, owner)?)),
+            None => None,
+        };
+        let gid = match group {
+            Some(group) => Some(Gid::from_raw(parse_id(path, group)?)),
+            None => None,
+        };
+        unistd::chown(path, uid, gid).map_err(|e| Error::Io(io::Error::other(e)))
+    }
+
+    /// The local disk keeps a single copy, like Hadoop's `LocalFileSystem` it is a no-op.
+    fn set_replication(&self, path: &Path, _replication: u16) -> Result<(), Error> {
+        self.stat(path).map(|_| ())
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        let mut times = fs::FileTimes::new();
+        if let Some(time) = modification_time {
+            times = times.set_modified(UNIX_EPOCH + Duration::from_millis(time));
+        }
+        if let Some(time) = access_time {
+            times = times.set_accessed(UNIX_EPOCH + Duration::from_millis(time));
+        }
+        let file = fs::File::open(path).map_err(|e| map_error(path, e))?;
+        file.set_times(times).map_err(|e| map_error(path, e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use fs::{FileSystem, LocalFileSystem};
+    use fs_test::TempDir;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_create_list_delete() {
+        let temp_dir = TempDir::default();
+        let fs = LocalFileSystem::new();
+        let dir = temp_dir.path().join("a/b");
+        let file = dir.join("file");
+
+        fs.create(&file, false)
+            .unwrap()
+            .write_all(b"content")
+            .unwrap();
+        assert!(fs.create(&file, false).is_err());
+
+        let mut content = String::new();
+        fs.open(&file)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!("content", content);
+
+        let entries = fs.list(&dir).unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(7, entries[0].len);
+
+        fs.set_permission(&file, 0o600).unwrap();
+        fs.set_times(&file, Some(1_500_000_000_000), None).unwrap();
+        let status = fs.stat(&file).unwrap();
+        assert_eq!(0o600, status.permission);
+        assert_eq!(1_500_000_000_000, status.modification_time);
+
+        assert!(fs.delete(&temp_dir.path().join("a"), false).is_err());
+        assert!(fs.delete(&temp_dir.path().join("a"), true).unwrap());
+        assert!(!fs.exists(&file).unwrap());
+    }
+}
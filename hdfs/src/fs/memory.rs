@@ -0,0 +1,525 @@
+use err::Error;
+use fs::FileSystem;
+use hdfs::{FileStatus, ObjectKind};
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_BLOCK_SIZE: u64 = 128 * 1024 * 1024;
+const DEFAULT_REPLICATION: u16 = 3;
+
+struct Node {
+    kind: ObjectKind,
+    owner: String,
+    group: String,
+    permission: u16,
+    modification_time: u64,
+    access_time: u64,
+    replication: u16,
+    block_size: u64,
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+/// A namespace held in memory with the namenode semantics, for tests.
+pub struct MemoryFileSystem {
+    user: String,
+    group: String,
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn already_exists(path: &Path) -> Error {
+    Error::RemoteException(
+        String::from("FileAlreadyExistsException"),
+        path.display().to_string(),
+    )
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::FileNotFound(format!("File does not exist: {}", path.display()))
+}
+
+impl Default for MemoryFileSystem {
+    fn default() -> MemoryFileSystem {
+        MemoryFileSystem::new("hdfs", "supergroup")
+    }
+}
+
+impl MemoryFileSystem {
+    /// New files and directories belong to `user` and `group`.
+    pub fn new(user: &str, group: &str) -> MemoryFileSystem {
+        let fs = MemoryFileSystem {
+            user: user.to_owned(),
+            group: group.to_owned(),
+            nodes: Mutex::new(BTreeMap::new()),
+        };
+        let root = fs.node(ObjectKind::Directory);
+        fs.nodes.lock().unwrap().insert(PathBuf::from("/"), root);
+        fs
+    }
+
+    /// Creates a file with the given content, and its missing parents.
+    pub fn add_file<P: AsRef<Path>>(&self, path: P, content: &[u8]) -> Result<(), Error> {
+        self.create(path.as_ref(), true)?.write_all(content)?;
+        Ok(())
+    }
+
+    /// The namenode only takes a block size when a file is created, tests can change it anytime.
+    pub fn set_block_size<P: AsRef<Path>>(&self, path: P, block_size: u64) -> Result<(), Error> {
+        self.update(path.as_ref(), |node| {
+            if node.kind == ObjectKind::File {
+                node.block_size = block_size;
+            }
+        })
+    }
+
+    fn node(&self, kind: ObjectKind) -> Node {
+        let time = now();
+        let is_file = kind == ObjectKind::File;
+        Node {
+            kind,
+            owner: self.user.to_owned(),
+            group: self.group.to_owned(),
+            permission: if is_file { 0o644 } else { 0o755 },
+            modification_time: time,
+            access_time: if is_file { time } else { 0 },
+            replication: if is_file { DEFAULT_REPLICATION } else { 0 },
+            block_size: if is_file { DEFAULT_BLOCK_SIZE } else { 0 },
+            data: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    fn absolute(&self, path: &Path) -> PathBuf {
+        let mut absolute = if path.is_absolute() {
+            PathBuf::from("/")
+        } else {
+            self.home()
+        };
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => absolute.push(name),
+                Component::ParentDir => {
+                    absolute.pop();
+                }
+                _ => {}
+            }
+        }
+        absolute
+    }
+
+    fn home(&self) -> PathBuf {
+        PathBuf::from("/user").join(&self.user)
+    }
+
+    fn status(path: &Path, node: &Node) -> FileStatus {
+        FileStatus {
+            path: path.to_owned(),
+            kind: node.kind,
+            len: node.data.lock().unwrap().len() as u64,
+            owner: node.owner.to_owned(),
+            group: node.group.to_owned(),
+            permission: node.permission,
+            modification_time: node.modification_time,
+            access_time: node.access_time,
+            replication: node.replication,
+            block_size: node.block_size,
+            symlink: None,
+        }
+    }
+
+    fn mkdirs_locked(
+        &self,
+        nodes: &mut BTreeMap<PathBuf, Node>,
+        path: &Path,
+    ) -> Result<bool, Error> {
+        for ancestor in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            match nodes.get(ancestor) {
+                Some(node) if node.kind != ObjectKind::Directory => {
+                    return Err(Error::RemoteException(
+                        String::from("ParentNotDirectoryException"),
+                        ancestor.display().to_string(),
+                    ))
+                }
+                Some(_) => {}
+                None => {
+                    nodes.insert(ancestor.to_owned(), self.node(ObjectKind::Directory));
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn open_file(&self, path: &Path) -> Result<MemoryFile, Error> {
+        let path = self.absolute(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+        if node.kind != ObjectKind::File {
+            return Err(Error::FileNotFound(format!(
+                "Path is not a file: {}",
+                path.display()
+            )));
+        }
+        node.access_time = now();
+        let content = node.data.lock().unwrap().to_owned();
+        Ok(MemoryFile(Mode::Read(Cursor::new(content))))
+    }
+
+    pub fn create_file(&self, path: &Path, overwrite: bool) -> Result<MemoryFile, Error> {
+        let path = self.absolute(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        match nodes.get(&path) {
+            Some(node) if node.kind != ObjectKind::File || !overwrite => {
+                return Err(already_exists(&path))
+            }
+            _ => {}
+        }
+        if let Some(parent) = path.parent() {
+            self.mkdirs_locked(&mut nodes, parent)?;
+        }
+        let node = self.node(ObjectKind::File);
+        let file = MemoryFile(Mode::Write(node.data.clone()));
+        nodes.insert(path, node);
+        Ok(file)
+    }
+
+    fn update<F: FnOnce(&mut Node)>(&self, path: &Path, f: F) -> Result<(), Error> {
+        let path = self.absolute(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = nodes.get_mut(&path).ok_or_else(|| not_found(&path))?;
+        f(node);
+        Ok(())
+    }
+}
+
+enum Mode {
+    Read(Cursor<Vec<u8>>),
+    /// Appends straight to the file content, readers opened afterwards see the data.
+    Write(Arc<Mutex<Vec<u8>>>),
+}
+
+/// An open file of a `MemoryFileSystem`, a snapshot of the content when opened for
+/// reading. Like on HDFS, files opened for writing cannot seek.
+pub struct MemoryFile(Mode);
+
+fn wrong_mode(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0 {
+            Mode::Read(ref mut cursor) => cursor.read(buf),
+            Mode::Write(_) => Err(wrong_mode("file is opened for writing")),
+        }
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0 {
+            Mode::Read(_) => Err(wrong_mode("file is opened for reading")),
+            Mode::Write(ref data) => {
+                data.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self.0 {
+            Mode::Read(ref mut cursor) => cursor.seek(pos),
+            Mode::Write(ref data) => match pos {
+                SeekFrom::Current(0) | SeekFrom::End(0) => Ok(data.lock().unwrap().len() as u64),
+                _ => Err(wrong_mode("cannot seek a file opened for writing")),
+            },
+        }
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        Ok(self.home())
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        let path = self.absolute(path);
+        let nodes = self.nodes.lock().unwrap();
+        nodes
+            .get(&path)
+            .map(|node| MemoryFileSystem::status(&path, node))
+            .ok_or_else(|| not_found(&path))
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        let path = self.absolute(path);
+        let nodes = self.nodes.lock().unwrap();
+        let node = nodes.get(&path).ok_or_else(|| not_found(&path))?;
+        if node.kind != ObjectKind::Directory {
+            return Ok(vec![MemoryFileSystem::status(&path, node)]);
+        }
+        Ok(nodes
+            .iter()
+            .filter(|(child, _)| child.parent() == Some(path.as_path()))
+            .map(|(child, node)| MemoryFileSystem::status(child, node))
+            .collect())
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(Box::new(self.open_file(path)?))
+    }
+
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        Ok(Box::new(self.create_file(path, overwrite)?))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        let path = self.absolute(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(&path) {
+            return Ok(false);
+        }
+        let has_children = nodes
+            .keys()
+            .any(|child| child != &path && child.starts_with(&path));
+        if has_children && !recursive {
+            return Err(Error::RemoteException(
+                String::from("PathIsNotEmptyDirectoryException"),
+                format!("{} is non empty", path.display()),
+            ));
+        }
+        nodes.retain(|child, _| !child.starts_with(&path) || child == Path::new("/"));
+        Ok(true)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let (from, to) = (self.absolute(from), self.absolute(to));
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(&from) {
+            return Err(not_found(&from));
+        }
+        if nodes.contains_key(&to) {
+            return Err(already_exists(&to));
+        }
+        match to.parent().and_then(|parent| nodes.get(parent)) {
+            Some(parent) if parent.kind == ObjectKind::Directory => {}
+            _ => return Err(not_found(to.parent().unwrap_or(&to))),
+        }
+        if to.starts_with(&from) {
+            return Err(Error::RemoteException(
+                String::from("IOException"),
+                format!("{} is a descendant of {}", to.display(), from.display()),
+            ));
+        }
+        let moved: Vec<PathBuf> = nodes
+            .keys()
+            .filter(|path| path.starts_with(&from))
+            .cloned()
+            .collect();
+        for path in moved {
+            let node = nodes.remove(&path).unwrap();
+            let new_path = to.join(path.strip_prefix(&from).unwrap());
+            nodes.insert(new_path, node);
+        }
+        Ok(())
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        let path = self.absolute(path);
+        self.mkdirs_locked(&mut self.nodes.lock().unwrap(), &path)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        self.update(path, |node| node.permission = permission & 0o7777)
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        self.update(path, |node| {
+            if let Some(owner) = owner {
+                node.owner = owner.to_owned();
+            }
+            if let Some(group) = group {
+                node.group = group.to_owned();
+            }
+        })
+    }
+
+    /// Like the namenode, directories have no replication and ignore it.
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error> {
+        self.update(path, |node| {
+            if node.kind == ObjectKind::File {
+                node.replication = replication;
+            }
+        })
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        self.update(path, |node| {
+            if let Some(time) = modification_time {
+                node.modification_time = time;
+            }
+            if let Some(time) = access_time {
+                node.access_time = time;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use err::Error;
+    use fs::{FileSystem, MemoryFileSystem};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+
+    fn names(fs: &dyn FileSystem, path: &str) -> Vec<PathBuf> {
+        fs.list(Path::new(path))
+            .unwrap()
+            .into_iter()
+            .map(|s| s.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_create_and_read() {
+        let fs = MemoryFileSystem::new("etl", "hadoop");
+        {
+            let mut writer = fs.create(Path::new("/data/part-0"), false).unwrap();
+            writer.write_all(b"hello ").unwrap();
+            writer.write_all(b"world").unwrap();
+        }
+
+        let status = fs.stat(Path::new("/data/part-0")).unwrap();
+        assert!(status.is_file());
+        assert_eq!(11, status.len);
+        assert_eq!("etl", status.owner);
+        assert_eq!(3, status.replication);
+        assert!(fs.stat(Path::new("/data")).unwrap().is_dir());
+
+        let mut content = String::new();
+        fs.open(Path::new("/data/part-0"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!("hello world", content);
+
+        match fs.create(Path::new("/data/part-0"), false) {
+            Err(Error::RemoteException(class, _)) => {
+                assert_eq!("FileAlreadyExistsException", class)
+            }
+            _ => panic!("file should already exist"),
+        }
+        match fs.open(Path::new("/data")) {
+            Err(Error::FileNotFound(_)) => {}
+            _ => panic!("a directory cannot be opened"),
+        };
+    }
+
+    #[test]
+    fn test_relative_paths() {
+        let fs = MemoryFileSystem::new("etl", "hadoop");
+        fs.add_file("a/b", b"").unwrap();
+
+        assert_eq!(PathBuf::from("/user/etl"), fs.current_dir().unwrap());
+        assert!(fs.exists(Path::new("/user/etl/a/b")).unwrap());
+        assert!(fs.exists(Path::new("a/../a/b")).unwrap());
+    }
+
+    #[test]
+    fn test_list_delete_rename() {
+        let fs = MemoryFileSystem::default();
+        fs.add_file("/a/1", b"1").unwrap();
+        fs.add_file("/a/2", b"2").unwrap();
+        fs.add_file("/a/b/3", b"3").unwrap();
+        fs.add_file("/ab", b"").unwrap();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("/a/1"),
+                PathBuf::from("/a/2"),
+                PathBuf::from("/a/b")
+            ],
+            names(&fs, "/a")
+        );
+        assert_eq!(vec![PathBuf::from("/a/1")], names(&fs, "/a/1"));
+
+        assert!(fs.mkdirs(Path::new("/a/1/c")).is_err());
+
+        fs.rename(Path::new("/a/b"), Path::new("/c")).unwrap();
+        assert!(fs.exists(Path::new("/c/3")).unwrap());
+        assert!(!fs.exists(Path::new("/a/b")).unwrap());
+        assert!(fs.rename(Path::new("/c"), Path::new("/a/1")).is_err());
+        assert!(fs.rename(Path::new("/c"), Path::new("/c/d")).is_err());
+        assert!(fs.rename(Path::new("/c"), Path::new("/missing/d")).is_err());
+
+        assert!(fs.delete(Path::new("/a"), false).is_err());
+        assert!(fs.delete(Path::new("/a"), true).unwrap());
+        assert!(!fs.delete(Path::new("/a"), true).unwrap());
+        assert!(fs.exists(Path::new("/ab")).unwrap());
+    }
+
+    #[test]
+    fn test_set_attributes() {
+        let fs = MemoryFileSystem::default();
+        fs.add_file("/f", b"").unwrap();
+        let path = Path::new("/f");
+
+        fs.set_permission(path, 0o600).unwrap();
+        fs.set_owner(path, Some("etl"), None).unwrap();
+        fs.set_replication(path, 2).unwrap();
+        fs.set_times(path, Some(1000), Some(2000)).unwrap();
+
+        let status = fs.stat(path).unwrap();
+        assert_eq!(0o600, status.permission);
+        assert_eq!("etl", status.owner);
+        assert_eq!("supergroup", status.group);
+        assert_eq!(2, status.replication);
+        assert_eq!(1000, status.modification_time);
+        assert_eq!(2000, status.access_time);
+
+        match fs.set_permission(Path::new("/missing"), 0o600) {
+            Err(Error::FileNotFound(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_content_summary() {
+        let fs = MemoryFileSystem::default();
+        fs.add_file("/a/1", b"1").unwrap();
+        fs.add_file("/a/b/2", b"22").unwrap();
+        fs.add_file("/ab", b"333").unwrap();
+        fs.set_replication(Path::new("/a/b/2"), 2).unwrap();
+
+        let summary = fs.content_summary(Path::new("/a")).unwrap();
+        assert_eq!(3, summary.length);
+        assert_eq!(2, summary.file_count);
+        assert_eq!(2, summary.directory_count);
+        assert_eq!(7, summary.space_consumed);
+        assert_eq!(None, summary.quota);
+        assert_eq!(3, fs.content_summary(Path::new("/ab")).unwrap().length);
+    }
+}
@@ -0,0 +1,76 @@
+//! Operations shared by every backend, so code written against `FileSystem` can run
+//! on a cluster, on the local disk or in memory.
+use err::Error;
+use hdfs::{ContentSummary, FileStatus};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub mod local;
+pub mod memory;
+pub mod retry;
+
+pub use self::local::LocalFileSystem;
+pub use self::memory::{MemoryFile, MemoryFileSystem};
+pub use self::retry::{RetryFileSystem, RetryPolicy};
+
+/// Times are in milliseconds since epoch and permissions are the octal mode, like the
+/// namenode reports them.
+pub trait FileSystem {
+    fn current_dir(&self) -> Result<PathBuf, Error>;
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error>;
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error>;
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error>;
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error>;
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error>;
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error>;
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error>;
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error>;
+    fn set_owner(&self, path: &Path, owner: Option<&str>, group: Option<&str>)
+        -> Result<(), Error>;
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error>;
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error>;
+
+    fn exists(&self, path: &Path) -> Result<bool, Error> {
+        match self.stat(path) {
+            Ok(_) => Ok(true),
+            Err(Error::FileNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Backends without a native call list the whole tree, without quotas.
+    fn content_summary(&self, path: &Path) -> Result<ContentSummary, Error> {
+        let mut summary = ContentSummary::default();
+        let mut pending = vec![self.stat(path)?];
+        while let Some(status) = pending.pop() {
+            if status.is_dir() {
+                summary.directory_count += 1;
+                pending.extend(self.list(&status.path)?);
+            } else {
+                summary.file_count += 1;
+                summary.length += status.len;
+                summary.space_consumed += status.len * u64::from(status.replication.max(1));
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Limits the number of files and directories under `path`, itself included. `None`
+    /// clears the quota.
+    fn set_namespace_quota(&self, path: &Path, _quota: Option<u64>) -> Result<(), Error> {
+        Err(Error::Unsupported(format!("quotas of {}", path.display())))
+    }
+
+    /// Limits the bytes used under `path`, replicas included. `None` clears the quota.
+    fn set_space_quota(&self, path: &Path, _quota: Option<u64>) -> Result<(), Error> {
+        Err(Error::Unsupported(format!(
+            "space quotas of {}",
+            path.display()
+        )))
+    }
+}
@@ -0,0 +1,395 @@
+use config::Config;
+use err::Error;
+use fs::FileSystem;
+use hdfs::{ContentSummary, FileStatus};
+use rand::prelude::*;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const MAX_ATTEMPTS_KEY: &str = "dfs.client.retry.max.attempts";
+const BASE_DELAY_KEY: &str = "dfs.client.retry.sleep.base.millis";
+const MAX_DELAY_KEY: &str = "dfs.client.retry.sleep.max.millis";
+const RETRYABLE_KEY: &str = "dfs.client.retry.exceptions";
+
+/// When and how long to wait before trying an operation again.
+///
+/// Errors raised before the namenode executed anything, a standby namenode or a refused
+/// connection, are retried for every operation. A dropped connection leaves the outcome
+/// unknown, so only idempotent operations are retried then: metadata reads, reads,
+/// `mkdirs` and the `set_*` calls, but not `create`, `delete` or `rename`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one, 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Simple class names of the remote exceptions worth retrying
+    pub retryable: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_millis(15_000),
+            retryable: vec![
+                String::from("StandbyException"),
+                String::from("RetriableException"),
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Reads the `dfs.client.retry.*` keys, missing ones keep their default.
+    pub fn from_config(config: &Config) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_attempts: config
+                .get(MAX_ATTEMPTS_KEY)
+                .unwrap_or(default.max_attempts)
+                .max(1),
+            base_delay: config
+                .get(BASE_DELAY_KEY)
+                .map(Duration::from_millis)
+                .unwrap_or(default.base_delay),
+            max_delay: config
+                .get(MAX_DELAY_KEY)
+                .map(Duration::from_millis)
+                .unwrap_or(default.max_delay),
+            retryable: config
+                .get_string(RETRYABLE_KEY)
+                .map(|classes| {
+                    classes
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|class| !class.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or(default.retryable),
+        }
+    }
+
+    pub fn is_retryable(&self, error: &Error, idempotent: bool) -> bool {
+        match *error {
+            Error::RemoteException(ref class, _) => self.retryable.contains(class),
+            Error::Io(ref e) => match e.kind() {
+                io::ErrorKind::ConnectionRefused => true,
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::Interrupted => idempotent,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Exponential backoff with jitter: a random delay between half and all of
+    /// `base_delay * 2^(attempt - 1)`, capped at `max_delay`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let millis = delay.as_millis() as u64;
+        if millis < 2 {
+            return delay;
+        }
+        Duration::from_millis(thread_rng().gen_range(millis / 2, millis + 1))
+    }
+
+    /// Whether `error`, raised by the given attempt, deserves another one. Waits before
+    /// answering yes.
+    fn backoff(&self, error: &Error, attempt: u32, idempotent: bool) -> bool {
+        if attempt >= self.max_attempts || !self.is_retryable(error, idempotent) {
+            return false;
+        }
+        let delay = self.delay(attempt);
+        info!(
+            "Attempt {}/{} failed with {:?}, retrying in {:?}",
+            attempt, self.max_attempts, error, delay
+        );
+        thread::sleep(delay);
+        true
+    }
+
+    fn retry<T, F>(&self, idempotent: bool, f: F) -> Result<T, Error>
+    where
+        F: Fn() -> Result<T, Error>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(ref e) if self.backoff(e, attempt, idempotent) => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Applies a `RetryPolicy` to every call of another file system.
+///
+/// A read failing in the middle of a file reopens it and seeks back to where it was.
+/// Writes are not retried, only the `create` call opening the file.
+pub struct RetryFileSystem<F> {
+    inner: F,
+    policy: RetryPolicy,
+}
+
+impl<F: FileSystem> RetryFileSystem<F> {
+    pub fn new(inner: F, policy: RetryPolicy) -> RetryFileSystem<F> {
+        RetryFileSystem { inner, policy }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+struct RetryReader<'a, F: 'a> {
+    fs: &'a RetryFileSystem<F>,
+    path: PathBuf,
+    position: u64,
+    /// The open stream and its own position, behind `position` after a reopen
+    reader: Option<(Box<dyn Read + 'a>, u64)>,
+}
+
+impl<'a, F: FileSystem> RetryReader<'a, F> {
+    /// Reads into `buf`, or returns `None` after skipping some bytes of a reopened
+    /// stream still behind the position.
+    fn read_or_skip(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        if self.reader.is_none() {
+            self.reader = Some((self.fs.inner.open(&self.path)?, 0));
+        }
+        let (ref mut reader, ref mut offset) = *self.reader.as_mut().unwrap();
+        if *offset < self.position {
+            let mut skip = [0; 8192];
+            let len = skip.len().min((self.position - *offset) as usize);
+            let n = reader.read(&mut skip[..len])?;
+            if n == 0 {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{} shrank while reading it", self.path.display()),
+                )));
+            }
+            *offset += n as u64;
+            return Ok(None);
+        }
+        let n = reader.read(buf)?;
+        *offset += n as u64;
+        self.position += n as u64;
+        Ok(Some(n))
+    }
+}
+
+fn to_io_error(error: Error) -> io::Error {
+    match error {
+        Error::Io(e) => e,
+        Error::FileNotFound(message) => io::Error::new(io::ErrorKind::NotFound, message),
+        e => io::Error::other(format!("{:?}", e)),
+    }
+}
+
+impl<'a, F: FileSystem> Read for RetryReader<'a, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut attempt = 1;
+        loop {
+            match self.read_or_skip(buf) {
+                Ok(Some(n)) => return Ok(n),
+                // Progress was made, the stream deserves a new set of attempts
+                Ok(None) => attempt = 1,
+                Err(e) => {
+                    self.reader = None;
+                    if !self.fs.policy.backoff(&e, attempt, true) {
+                        return Err(to_io_error(e));
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+impl<F: FileSystem> FileSystem for RetryFileSystem<F> {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        self.policy.retry(true, || self.inner.current_dir())
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        self.policy.retry(true, || self.inner.stat(path))
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        self.policy.retry(true, || self.inner.list(path))
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        let reader = self.policy.retry(true, || self.inner.open(path))?;
+        Ok(Box::new(RetryReader {
+            fs: self,
+            path: path.to_owned(),
+            position: 0,
+            reader: Some((reader, 0)),
+        }))
+    }
+
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        self.policy
+            .retry(false, || self.inner.create(path, overwrite))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        self.policy
+            .retry(false, || self.inner.delete(path, recursive))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.policy.retry(false, || self.inner.rename(from, to))
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        self.policy.retry(true, || self.inner.mkdirs(path))
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        self.policy
+            .retry(true, || self.inner.set_permission(path, permission))
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        self.policy
+            .retry(true, || self.inner.set_owner(path, owner, group))
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error> {
+        self.policy
+            .retry(true, || self.inner.set_replication(path, replication))
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        self.policy.retry(true, || {
+            self.inner.set_times(path, modification_time, access_time)
+        })
+    }
+
+    fn content_summary(&self, path: &Path) -> Result<ContentSummary, Error> {
+        self.policy.retry(true, || self.inner.content_summary(path))
+    }
+
+    fn set_namespace_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), Error> {
+        self.policy
+            .retry(true, || self.inner.set_namespace_quota(path, quota))
+    }
+
+    fn set_space_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), Error> {
+        self.policy
+            .retry(true, || self.inner.set_space_quota(path, quota))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RetryPolicy;
+    use config::Config;
+    use err::Error;
+    use std::collections::HashMap;
+    use std::io;
+    use std::time::Duration;
+
+    fn standby() -> Error {
+        Error::RemoteException(String::from("StandbyException"), String::new())
+    }
+
+    fn io_error(kind: io::ErrorKind) -> Error {
+        Error::Io(io::Error::new(kind, "test"))
+    }
+
+    #[test]
+    fn test_retryable() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.is_retryable(&standby(), false));
+        assert!(policy.is_retryable(&io_error(io::ErrorKind::ConnectionRefused), false));
+        assert!(policy.is_retryable(&io_error(io::ErrorKind::ConnectionReset), true));
+        assert!(!policy.is_retryable(&io_error(io::ErrorKind::ConnectionReset), false));
+        assert!(!policy.is_retryable(&Error::FileNotFound(String::new()), true));
+        let access = Error::RemoteException(String::from("AccessControlException"), String::new());
+        assert!(!policy.is_retryable(&access, true));
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            ..RetryPolicy::default()
+        };
+
+        for _ in 0..20 {
+            let first = policy.delay(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.delay(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            let capped = policy.delay(40);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config_map = HashMap::new();
+        config_map.insert(
+            String::from("dfs.client.retry.max.attempts"),
+            String::from("3"),
+        );
+        config_map.insert(
+            String::from("dfs.client.retry.sleep.base.millis"),
+            String::from("10"),
+        );
+        config_map.insert(
+            String::from("dfs.client.retry.exceptions"),
+            String::from("StandbyException, SafeModeException"),
+        );
+        let policy = RetryPolicy::from_config(&Config { config_map });
+
+        assert_eq!(3, policy.max_attempts);
+        assert_eq!(Duration::from_millis(10), policy.base_delay);
+        assert_eq!(RetryPolicy::default().max_delay, policy.max_delay);
+        assert_eq!(
+            vec!["StandbyException", "SafeModeException"],
+            policy.retryable
+        );
+    }
+}
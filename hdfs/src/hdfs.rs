@@ -1,6 +1,7 @@
 #![allow(unused_imports)]
 use config;
 use err::Error;
+use fs;
 use libc::{
     c_char, c_int, c_short, c_uchar, c_void, int16_t, int32_t, int64_t, size_t, time_t, uint16_t,
 };
@@ -11,6 +12,7 @@ use std::fmt;
 use std::io;
 use std::io::Read;
 use std::io::Write;
+use std::io::{Seek, SeekFrom};
 use std::mem;
 use std::path::{self, Component, Path, PathBuf};
 use std::ptr;
@@ -26,6 +28,11 @@ pub struct HDFileSystem {
     raw: *const native::hdfsFS,
 }
 
+// SAFETY: a libhdfs3 `hdfsFS` is not tied to the thread that connected it and its
+// calls lock what they share, the same handle may be used by several threads at once.
+unsafe impl Send for HDFileSystem {}
+unsafe impl Sync for HDFileSystem {}
+
 pub fn list_gateway<P: AsRef<Path>>(config_path: P) -> Result<Vec<String>, Error> {
     let config = config::Config::new(config_path.as_ref())?;
 
@@ -98,7 +105,7 @@ pub fn get_hdfs<P: AsRef<Path>>(
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ObjectKind {
     Unknown,
     File,
@@ -133,6 +140,71 @@ impl DirEntry {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct FileStatus {
+    pub path: PathBuf,
+    pub kind: ObjectKind,
+    pub len: u64,
+    pub owner: String,
+    pub group: String,
+    pub permission: u16,
+    pub modification_time: u64,
+    pub access_time: u64,
+    pub replication: u16,
+    pub block_size: u64,
+    pub symlink: Option<PathBuf>,
+}
+
+impl FileStatus {
+    pub fn is_dir(&self) -> bool {
+        self.kind == ObjectKind::Directory
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == ObjectKind::File
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.path.to_owned()
+    }
+}
+
+/// Totals of a directory tree, like `hdfs dfs -count` reports them. Quotas are
+/// `None` when not set, or when the backend does not report them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContentSummary {
+    pub length: u64,
+    pub file_count: u64,
+    pub directory_count: u64,
+    /// Bytes used on the datanodes, replicas included
+    pub space_consumed: u64,
+    /// Whether the quotas come from the namenode, they are unknown otherwise
+    pub has_quotas: bool,
+    /// Maximum number of files and directories
+    pub quota: Option<u64>,
+    /// Maximum bytes used on the datanodes
+    pub space_quota: Option<u64>,
+}
+
+impl FileStatus {
+    fn from_file_info(file: &native::hdfsFileInfo) -> FileStatus {
+        let kind = ObjectKind::from_t_object_kind(&file.mKind);
+        FileStatus {
+            path: PathBuf::from(chars_to_str(file.mName)),
+            kind,
+            len: file.mSize as u64,
+            owner: chars_to_str(file.mOwner).to_owned(),
+            group: chars_to_str(file.mGroup).to_owned(),
+            permission: file.mPermissions as u16 & 0o7777,
+            modification_time: file.mLastMod as u64 * 1000,
+            access_time: file.mLastAccess as u64 * 1000,
+            replication: file.mReplication as u16,
+            block_size: file.mBlockSize as u64,
+            symlink: None,
+        }
+    }
+}
+
 pub struct ReadDir {
     pub path: PathBuf,
     pub kind: ObjectKind,
@@ -231,12 +303,7 @@ impl HDFileSystem {
         path: P,
         options: &OpenOptions,
     ) -> Result<File, Error> {
-        let mut flag = OFlag::empty();
-
-        flag.set(OFlag::O_CREAT, options.create);
-        flag.set(OFlag::O_WRONLY, options.write);
-        flag.set(OFlag::O_APPEND, options.append);
-        flag.set(OFlag::O_RDONLY, options.read);
+        let flag = options.flags();
         let path = path.as_ref();
         let path_str = path
             .to_str()
@@ -250,10 +317,16 @@ impl HDFileSystem {
             Ok(File {
                 fs: self.raw,
                 raw: f,
+                path: path.to_owned(),
             })
         }
     }
 
+    /// Fails like the namenode when `path` exists and `overwrite` is false.
+    pub fn create_file(&self, path: &Path, overwrite: bool) -> Result<File, Error> {
+        OpenOptions::for_create(overwrite).open(self, path)
+    }
+
     pub fn exists<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
         let path_str = path.as_ref().to_str();
         if path_str.is_none() {
@@ -275,6 +348,129 @@ impl HDFileSystem {
     }
 }
 
+fn path_to_chars(path: &Path) -> Result<*const c_char, Error> {
+    path.to_str()
+        .map(str_to_chars)
+        .ok_or_else(|| Error::InvalidPath(path.to_owned()))
+}
+
+/// libhdfs3 reports failures through its last error, falling back to errno.
+fn last_error() -> Error {
+    match Error::get_last_hdfs_error() {
+        Error::NoError() => Error::get_last_error(),
+        e => e,
+    }
+}
+
+fn check(res: c_int) -> Result<(), Error> {
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(last_error())
+    }
+}
+
+impl fs::FileSystem for HDFileSystem {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        HDFileSystem::current_dir(self)
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        let info = unsafe { native::hdfsGetPathInfo(self.raw, path_to_chars(path)?) };
+        if info.is_null() {
+            let error = last_error();
+            return if self.exists(path)? {
+                Err(error)
+            } else {
+                Err(Error::FileNotFound(path.display().to_string()))
+            };
+        }
+        let status = FileStatus::from_file_info(unsafe { &*info });
+        unsafe { native::hdfsFreeFileInfo(info, 1) };
+        Ok(status)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        let mut count: c_int = 0;
+        let infos =
+            unsafe { native::hdfsListDirectory(self.raw, path_to_chars(path)?, &mut count) };
+        if infos.is_null() {
+            // An empty directory is listed as null too
+            return fs::FileSystem::stat(self, path).map(|_| vec![]);
+        }
+        let entries = unsafe { slice::from_raw_parts(infos, count as usize) }
+            .iter()
+            .map(FileStatus::from_file_info)
+            .collect();
+        unsafe { native::hdfsFreeFileInfo(infos, count) };
+        Ok(entries)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        let file = OpenOptions::new().read(true).open(self, path)?;
+        Ok(Box::new(file))
+    }
+
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        Ok(Box::new(self.create_file(path, overwrite)?))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        if !self.exists(path)? {
+            return Ok(false);
+        }
+        let res = unsafe { native::hdfsDelete(self.raw, path_to_chars(path)?, recursive as c_int) };
+        check(res).map(|_| true)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        check(unsafe { native::hdfsRename(self.raw, path_to_chars(from)?, path_to_chars(to)?) })
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        check(unsafe { native::hdfsCreateDirectory(self.raw, path_to_chars(path)?) }).map(|_| true)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        check(unsafe { native::hdfsChmod(self.raw, path_to_chars(path)?, permission as c_short) })
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        let owner = owner.map(str_to_chars).unwrap_or(ptr::null());
+        let group = group.map(str_to_chars).unwrap_or(ptr::null());
+        check(unsafe { native::hdfsChown(self.raw, path_to_chars(path)?, owner, group) })
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error> {
+        check(unsafe {
+            native::hdfsSetReplication(self.raw, path_to_chars(path)?, replication as c_short)
+        })
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        // libhdfs3 keeps a time unchanged when given -1
+        let seconds = |time: Option<u64>| time.map(|t| (t / 1000) as time_t).unwrap_or(-1);
+        check(unsafe {
+            native::hdfsUtime(
+                self.raw,
+                path_to_chars(path)?,
+                seconds(modification_time),
+                seconds(access_time),
+            )
+        })
+    }
+}
+
 impl Drop for HDFileSystem {
     fn drop(&mut self) {
         let res = unsafe { native::hdfsDisconnect(self.raw) };
@@ -288,8 +484,14 @@ impl Drop for HDFileSystem {
 pub struct File {
     fs: *const native::hdfsFS,
     raw: *const native::hdfsFile,
+    path: PathBuf,
 }
 
+// SAFETY: an `hdfsFile` holds no thread-local state and may be used from another
+// thread, but not by two at once: `File` is not `Sync` and its calls take `&mut self`.
+// The `hdfsFS` it points to is kept open by its owner, which must outlive it.
+unsafe impl Send for File {}
+
 impl Read for File {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
         let read_byte = unsafe {
@@ -337,6 +539,44 @@ impl Write for File {
     }
 }
 
+/// Only files opened for reading can seek, libhdfs3 appends to the others.
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, std::io::Error> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => {
+                let current = unsafe { native::hdfsTell(self.fs, self.raw) };
+                if current < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                current + offset
+            }
+            SeekFrom::End(offset) => {
+                let path =
+                    path_to_chars(&self.path).map_err(|e| io::Error::other(format!("{:?}", e)))?;
+                let info = unsafe { native::hdfsGetPathInfo(self.fs, path) };
+                if info.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let len = unsafe { (*info).mSize };
+                unsafe { native::hdfsFreeFileInfo(info, 1) };
+                len + offset
+            }
+        };
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            ));
+        }
+        if unsafe { native::hdfsSeek(self.fs, self.raw, position) } == 0 {
+            Ok(position as u64)
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
 impl Drop for File {
     fn drop(&mut self) {
         unsafe { native::hdfsCloseFile(self.fs, self.raw) };
@@ -348,6 +588,7 @@ pub struct OpenOptions {
     write: bool,
     append: bool,
     create: bool,
+    truncate: bool,
 }
 
 impl Default for OpenOptions {
@@ -357,6 +598,7 @@ impl Default for OpenOptions {
             write: false,
             append: false,
             create: false,
+            truncate: false,
         }
     }
 }
@@ -371,6 +613,7 @@ impl OpenOptions {
             write: false,
             append: false,
             create: false,
+            truncate: false,
         }
     }
 
@@ -412,7 +655,64 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the option for truncating a previous file.
+    ///
+    /// libhdfs3 only replaces files opened for writing without `O_CREAT`, which this
+    /// option leaves out.
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.truncate = truncate;
+        if truncate {
+            self.write = true;
+        }
+        self
+    }
+
     pub fn open<P: AsRef<Path>>(&self, fs: &HDFileSystem, path: P) -> Result<File, Error> {
         fs.open_with_options(path, self)
     }
+
+    /// The namenode checks whether the file exists, no separate call races the create.
+    fn for_create(overwrite: bool) -> OpenOptions {
+        let mut options = OpenOptions::new();
+        options.read(false);
+        if overwrite {
+            options.truncate(true);
+        } else {
+            options.create(true);
+        }
+        options
+    }
+
+    fn flags(&self) -> OFlag {
+        let mut flag = OFlag::empty();
+        flag.set(OFlag::O_CREAT, self.create && !self.truncate);
+        flag.set(OFlag::O_WRONLY, self.write);
+        flag.set(OFlag::O_APPEND, self.append);
+        flag.set(OFlag::O_TRUNC, self.truncate);
+        flag.set(OFlag::O_RDONLY, self.read);
+        flag
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OpenOptions;
+    use nix::fcntl::OFlag;
+
+    #[test]
+    fn test_create_flags() {
+        // libhdfs3 overwrites with O_WRONLY alone, O_CREAT fails on existing files
+        assert_eq!(
+            OFlag::O_WRONLY | OFlag::O_TRUNC,
+            OpenOptions::for_create(true).flags()
+        );
+        assert_eq!(
+            OFlag::O_WRONLY | OFlag::O_CREAT,
+            OpenOptions::for_create(false).flags()
+        );
+        let mut options = OpenOptions::new();
+        assert_eq!(OFlag::O_RDONLY, options.flags());
+        options.append(true);
+        assert_eq!(OFlag::O_WRONLY | OFlag::O_APPEND, options.flags());
+    }
 }
@@ -1,13 +1,38 @@
 #[macro_use]
 extern crate log;
+#[cfg(feature = "object-store")]
+extern crate bytes;
+#[cfg(feature = "object-store")]
+extern crate chrono;
+extern crate crc32c;
 #[cfg(test)]
 extern crate fs_test;
+#[cfg(feature = "object-store")]
+extern crate futures;
 extern crate itertools;
 extern crate libc;
 extern crate nix;
+#[cfg(feature = "object-store")]
+extern crate object_store;
+extern crate prost;
 extern crate quick_xml;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(feature = "async")]
+extern crate tokio;
+extern crate ureq;
+#[cfg(feature = "async")]
+pub mod async_hdfs;
 pub mod config;
 pub mod err;
+pub mod fs;
 pub mod hdfs;
 mod native;
+pub mod rpc;
+#[cfg(feature = "object-store")]
+pub mod store;
 mod util;
+pub mod webhdfs;
@@ -115,12 +115,16 @@ extern "C" {
         length: tSize,
     ) -> tSize;
 
-    pub fn hdfsDelete(fs: *const hdfsFS, path: *const c_char) -> c_int;
+    pub fn hdfsDelete(fs: *const hdfsFS, path: *const c_char, recursive: c_int) -> c_int;
 
     pub fn hdfsExists(fs: *const hdfsFS, path: *const c_char) -> c_int;
 
     pub fn hdfsFlush(fs: *const hdfsFS, file: *const hdfsFile) -> c_int;
 
+    pub fn hdfsSeek(fs: *const hdfsFS, file: *const hdfsFile, desiredPos: tOffset) -> c_int;
+
+    pub fn hdfsTell(fs: *const hdfsFS, file: *const hdfsFile) -> tOffset;
+
     pub fn hdfsCopy(
         srcFS: *const hdfsFS,
         src: *const c_char,
@@ -133,8 +137,21 @@ extern "C" {
         dstFS: *const hdfsFS,
         dst: *const c_char,
     );
-    pub fn hdfsRename(srcFS: *const hdfsFS, src: *const c_char, dst: *const c_char);
+    pub fn hdfsRename(srcFS: *const hdfsFS, src: *const c_char, dst: *const c_char) -> c_int;
     pub fn hdfsCreateDirectory(fs: *const hdfsFS, path: *const c_char) -> c_int;
     pub fn hdfsGetPathInfo(fs: *const hdfsFS, path: *const c_char) -> *const hdfsFileInfo;
     pub fn hdfsGetWorkingDirectory(fs: *const hdfsFS, buffer: *const c_char, bufferSize: tSize);
+    pub fn hdfsSetReplication(
+        fs: *const hdfsFS,
+        path: *const c_char,
+        replication: c_short,
+    ) -> c_int;
+    pub fn hdfsChown(
+        fs: *const hdfsFS,
+        path: *const c_char,
+        owner: *const c_char,
+        group: *const c_char,
+    ) -> c_int;
+    pub fn hdfsChmod(fs: *const hdfsFS, path: *const c_char, mode: c_short) -> c_int;
+    pub fn hdfsUtime(fs: *const hdfsFS, path: *const c_char, mtime: tTime, atime: tTime) -> c_int;
 }
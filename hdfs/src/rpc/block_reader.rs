@@ -0,0 +1,253 @@
+use err::Error;
+use prost::Message;
+use rpc::proto::*;
+use std::cmp;
+use std::convert::TryFrom;
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const DATA_TRANSFER_VERSION: u16 = 28;
+const OP_READ_BLOCK: u8 = 81;
+const CHECKSUM_SIZE: usize = 4;
+const MAX_PACKET_LENGTH: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Checksum {
+    Null,
+    Crc32c,
+}
+
+/// Reads a range of a single block from a datanode with `OpReadBlock`, verifying
+/// the CRC32C of every chunk.
+pub struct BlockReader {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    checksum: Checksum,
+    bytes_per_checksum: usize,
+    packet: Vec<u8>,
+    position_in_packet: usize,
+    /// Bytes sent by the datanode before the requested offset, to align on a chunk
+    to_skip: usize,
+    /// Bytes still to hand to the caller
+    remaining: u64,
+    /// Offset in the block of the next byte coming from the datanode
+    offset_in_block: u64,
+    last_packet: bool,
+}
+
+impl BlockReader {
+    pub fn connect(
+        address: &str,
+        block: &LocatedBlockProto,
+        client_name: &str,
+        offset: u64,
+        len: u64,
+        timeout: Option<Duration>,
+    ) -> Result<BlockReader, Error> {
+        debug!(
+            "Reading block {} [{}, +{}] from {}",
+            block.b.block_id, offset, len, address
+        );
+        let stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let request = OpReadBlockProto {
+            header: ClientOperationHeaderProto {
+                base_header: BaseHeaderProto {
+                    block: block.b.to_owned(),
+                    token: Some(block.block_token.to_owned()),
+                },
+                client_name: client_name.to_owned(),
+            },
+            offset,
+            len,
+            send_checksums: Some(true),
+        };
+        let mut buf = DATA_TRANSFER_VERSION.to_be_bytes().to_vec();
+        buf.push(OP_READ_BLOCK);
+        request
+            .encode_length_delimited(&mut buf)
+            .map_err(|e| Error::Rpc(e.to_string()))?;
+        writer.write_all(&buf)?;
+
+        let response = read_delimited::<BlockOpResponseProto>(&mut reader)?;
+        if response.status != Status::Success as i32 {
+            return Err(Error::Rpc(format!(
+                "Datanode {} refused to read block {}: {:?} {}",
+                address,
+                block.b.block_id,
+                Status::try_from(response.status),
+                response.message.unwrap_or_default()
+            )));
+        }
+
+        let info = response.read_op_checksum_info.ok_or_else(|| {
+            Error::Rpc(String::from("Missing checksum info in read block response"))
+        })?;
+        let checksum = match ChecksumTypeProto::try_from(info.checksum.checksum_type) {
+            Ok(ChecksumTypeProto::ChecksumNull) => Checksum::Null,
+            Ok(ChecksumTypeProto::ChecksumCrc32c) => Checksum::Crc32c,
+            other => return Err(Error::Rpc(format!("Unsupported checksum type {:?}", other))),
+        };
+        if info.chunk_offset > offset {
+            return Err(Error::Rpc(format!(
+                "Datanode starts at {} after requested offset {}",
+                info.chunk_offset, offset
+            )));
+        }
+
+        Ok(BlockReader {
+            reader,
+            writer,
+            checksum,
+            bytes_per_checksum: cmp::max(info.checksum.bytes_per_checksum as usize, 1),
+            packet: vec![],
+            position_in_packet: 0,
+            to_skip: (offset - info.chunk_offset) as usize,
+            remaining: len,
+            offset_in_block: info.chunk_offset,
+            last_packet: false,
+        })
+    }
+
+    fn read_packet(&mut self) -> io::Result<()> {
+        let mut lengths = [0u8; 6];
+        self.reader.read_exact(&mut lengths)?;
+        let packet_length = u32::from_be_bytes([lengths[0], lengths[1], lengths[2], lengths[3]]);
+        let header_length = u16::from_be_bytes([lengths[4], lengths[5]]) as usize;
+
+        let mut header = vec![0u8; header_length];
+        self.reader.read_exact(&mut header)?;
+        let header = PacketHeaderProto::decode(header.as_slice())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // The packet length counts itself, the checksums and the data
+        let payload = (packet_length as usize)
+            .checked_sub(4)
+            .filter(|p| *p <= MAX_PACKET_LENGTH)
+            .ok_or_else(|| invalid_data(format!("Invalid packet length {}", packet_length)))?;
+        let data_len = header.data_len as usize;
+        let checksums_len = payload
+            .checked_sub(data_len)
+            .ok_or_else(|| invalid_data(format!("Invalid data length {}", data_len)))?;
+
+        let mut checksums = vec![0u8; checksums_len];
+        self.reader.read_exact(&mut checksums)?;
+        let mut data = vec![0u8; data_len];
+        self.reader.read_exact(&mut data)?;
+
+        if header.offset_in_block as u64 != self.offset_in_block && data_len > 0 {
+            return Err(invalid_data(format!(
+                "Expected packet at offset {} but got {}",
+                self.offset_in_block, header.offset_in_block
+            )));
+        }
+        self.verify(&data, &checksums)?;
+
+        self.offset_in_block += data_len as u64;
+        self.last_packet = header.last_packet_in_block || data_len == 0;
+        let skip = cmp::min(self.to_skip, data.len());
+        self.to_skip -= skip;
+        self.packet = data;
+        self.position_in_packet = skip;
+        Ok(())
+    }
+
+    fn verify(&self, data: &[u8], checksums: &[u8]) -> io::Result<()> {
+        if self.checksum == Checksum::Null {
+            return Ok(());
+        }
+        let chunks = data.chunks(self.bytes_per_checksum);
+        if chunks.len() * CHECKSUM_SIZE != checksums.len() {
+            return Err(invalid_data(format!(
+                "{} bytes of checksums for {} bytes of data",
+                checksums.len(),
+                data.len()
+            )));
+        }
+        for (i, (chunk, expected)) in chunks.zip(checksums.chunks(CHECKSUM_SIZE)).enumerate() {
+            let expected = u32::from_be_bytes([expected[0], expected[1], expected[2], expected[3]]);
+            if crc32c::crc32c(chunk) != expected {
+                return Err(invalid_data(format!(
+                    "Checksum error at offset {} in block",
+                    self.offset_in_block + (i * self.bytes_per_checksum) as u64
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells the datanode the whole range was read and verified.
+    fn send_checksum_ok(&mut self) -> io::Result<()> {
+        let status = ClientReadStatusProto {
+            status: Status::ChecksumOk as i32,
+        };
+        self.writer
+            .write_all(&status.encode_length_delimited_to_vec())
+    }
+}
+
+impl Read for BlockReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining > 0 && self.position_in_packet >= self.packet.len() {
+            if self.last_packet {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Block ended with {} bytes missing", self.remaining),
+                ));
+            }
+            self.read_packet()?;
+        }
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+
+        let available = &self.packet[self.position_in_packet..];
+        let count = cmp::min(
+            cmp::min(available.len(), buf.len()),
+            cmp::min(self.remaining, usize::MAX as u64) as usize,
+        );
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position_in_packet += count;
+        self.remaining -= count as u64;
+
+        if self.remaining == 0 {
+            // Failing to acknowledge is harmless, the datanode only logs it
+            if let Err(e) = self.send_checksum_ok() {
+                debug!("Cannot send read status: {}", e);
+            }
+        }
+        Ok(count)
+    }
+}
+
+fn read_delimited<M: Message + Default>(reader: &mut dyn Read) -> Result<M, Error> {
+    let mut length: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        length |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift > 63 {
+            return Err(Error::Rpc(String::from("Invalid varint")));
+        }
+    }
+    if length as usize > MAX_PACKET_LENGTH {
+        return Err(Error::Rpc(format!("Message of {} bytes", length)));
+    }
+    let mut buf = vec![0u8; length as usize];
+    reader.read_exact(&mut buf)?;
+    M::decode(buf.as_slice()).map_err(|e| Error::Rpc(e.to_string()))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,154 @@
+use err::Error;
+use prost::Message;
+use rand::prelude::*;
+use rpc::proto::*;
+use std::convert::TryFrom;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const RPC_HEADER: &[u8] = b"hrpc";
+const RPC_VERSION: u8 = 9;
+const RPC_SERVICE_CLASS: u8 = 0;
+const AUTH_PROTOCOL_NONE: u8 = 0;
+const CONNECTION_CONTEXT_CALL_ID: i32 = -3;
+const MAX_FRAME_LENGTH: usize = 128 * 1024 * 1024;
+
+/// A single Hadoop IPC connection, calls are sent one at a time.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    protocol: String,
+    client_id: Vec<u8>,
+    call_id: i32,
+}
+
+impl Connection {
+    pub fn connect(
+        address: &str,
+        user: &str,
+        protocol: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Connection, Error> {
+        debug!("Connecting to {} as {}", address, user);
+        let stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        stream.set_nodelay(true)?;
+
+        let mut client_id = vec![0u8; 16];
+        thread_rng().fill_bytes(&mut client_id);
+
+        let mut connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            protocol: protocol.to_owned(),
+            client_id,
+            call_id: 0,
+        };
+        connection.handshake(user)?;
+        Ok(connection)
+    }
+
+    fn handshake(&mut self, user: &str) -> Result<(), Error> {
+        self.writer.write_all(RPC_HEADER)?;
+        self.writer
+            .write_all(&[RPC_VERSION, RPC_SERVICE_CLASS, AUTH_PROTOCOL_NONE])?;
+
+        let header = self.request_header(CONNECTION_CONTEXT_CALL_ID);
+        let context = IpcConnectionContextProto {
+            user_info: Some(UserInformationProto {
+                effective_user: Some(user.to_owned()),
+                real_user: None,
+            }),
+            protocol: Some(self.protocol.to_owned()),
+        };
+        self.write_frame(&[
+            &header.encode_length_delimited_to_vec(),
+            &context.encode_length_delimited_to_vec(),
+        ])
+    }
+
+    /// Calls `method` of the connection protocol and waits for its response.
+    pub fn call<Req, Resp>(&mut self, method: &str, request: &Req) -> Result<Resp, Error>
+    where
+        Req: Message,
+        Resp: Message + Default,
+    {
+        let call_id = self.call_id;
+        self.call_id = self.call_id.wrapping_add(1) & i32::MAX;
+        debug!("Call {} {}", call_id, method);
+
+        let header = self.request_header(call_id);
+        let method_header = RequestHeaderProto {
+            method_name: method.to_owned(),
+            declaring_class_protocol_name: self.protocol.to_owned(),
+            client_protocol_version: 1,
+        };
+        self.write_frame(&[
+            &header.encode_length_delimited_to_vec(),
+            &method_header.encode_length_delimited_to_vec(),
+            &request.encode_length_delimited_to_vec(),
+        ])?;
+
+        let frame = self.read_frame()?;
+        let mut buf = frame.as_slice();
+        let response_header = RpcResponseHeaderProto::decode_length_delimited(&mut buf)
+            .map_err(|e| Error::Rpc(e.to_string()))?;
+
+        if response_header.call_id != call_id as u32 {
+            return Err(Error::Rpc(format!(
+                "Response for call {} while waiting for {}",
+                response_header.call_id, call_id
+            )));
+        }
+
+        match RpcStatusProto::try_from(response_header.status) {
+            Ok(RpcStatusProto::Success) => {
+                Resp::decode_length_delimited(&mut buf).map_err(|e| Error::Rpc(e.to_string()))
+            }
+            _ => Err(Error::remote(
+                response_header
+                    .exception_class_name
+                    .as_deref()
+                    .unwrap_or("RpcException"),
+                response_header.error_msg.unwrap_or_default(),
+            )),
+        }
+    }
+
+    fn request_header(&self, call_id: i32) -> RpcRequestHeaderProto {
+        RpcRequestHeaderProto {
+            rpc_kind: Some(RpcKindProto::RpcProtocolBuffer as i32),
+            rpc_op: Some(OperationProto::RpcFinalPacket as i32),
+            call_id,
+            client_id: self.client_id.to_owned(),
+            retry_count: Some(-1),
+        }
+    }
+
+    fn write_frame(&mut self, parts: &[&[u8]]) -> Result<(), Error> {
+        let length: usize = parts.iter().map(|p| p.len()).sum();
+        self.writer.write_all(&(length as u32).to_be_bytes())?;
+        for part in parts {
+            self.writer.write_all(part)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<Vec<u8>, Error> {
+        let mut length = [0u8; 4];
+        self.reader.read_exact(&mut length)?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length > MAX_FRAME_LENGTH {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("RPC response of {} bytes", length),
+            )));
+        }
+        let mut frame = vec![0u8; length];
+        self.reader.read_exact(&mut frame)?;
+        Ok(frame)
+    }
+}
@@ -0,0 +1,184 @@
+use err::Error;
+use rand::prelude::*;
+use rpc::block_reader::BlockReader;
+use rpc::proto::LocatedBlockProto;
+use rpc::NameNodeClient;
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How many bytes of block locations are asked to the namenode at once
+const PREFETCH_SIZE: u64 = 10 * 128 * 1024 * 1024;
+
+/// A file read straight from the datanodes, failing over between the replicas of
+/// a block when a datanode is unreachable or sends corrupted data.
+pub struct File<'a> {
+    client: &'a NameNodeClient,
+    path: String,
+    client_name: String,
+    length: u64,
+    blocks: Vec<LocatedBlockProto>,
+    position: u64,
+    reader: Option<(String, BlockReader)>,
+    dead_nodes: HashSet<String>,
+    refreshed: bool,
+}
+
+impl<'a> File<'a> {
+    pub(crate) fn open(client: &'a NameNodeClient, path: &Path) -> Result<File<'a>, Error> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(path.to_owned()))?
+            .to_owned();
+        let locations = client.get_block_locations(&path, 0, PREFETCH_SIZE)?;
+        Ok(File {
+            client,
+            path,
+            client_name: format!("DFSClient_rust_{}", thread_rng().next_u32()),
+            length: locations.file_length,
+            blocks: locations.blocks,
+            position: 0,
+            reader: None,
+            dead_nodes: HashSet::new(),
+            refreshed: false,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    fn locate(&mut self, refresh: bool) -> Result<LocatedBlockProto, Error> {
+        let position = self.position;
+        let find = |blocks: &[LocatedBlockProto]| {
+            blocks
+                .iter()
+                .find(|b| b.offset <= position && position < b.offset + b.b.num_bytes.unwrap_or(0))
+                .cloned()
+        };
+        if !refresh {
+            if let Some(block) = find(&self.blocks) {
+                return Ok(block);
+            }
+        }
+        let locations = self
+            .client
+            .get_block_locations(&self.path, position, PREFETCH_SIZE)?;
+        self.blocks = locations.blocks;
+        find(&self.blocks)
+            .ok_or_else(|| Error::Rpc(format!("No block of {} at offset {}", self.path, position)))
+    }
+
+    fn open_reader(&mut self) -> Result<(), Error> {
+        loop {
+            let block = self.locate(false)?;
+            let offset = self.position - block.offset;
+            let len = block.b.num_bytes.unwrap_or(0) - offset;
+
+            for location in &block.locs {
+                let address = format!("{}:{}", location.id.ip_addr, location.id.xfer_port);
+                if self.dead_nodes.contains(&address) {
+                    continue;
+                }
+                match BlockReader::connect(
+                    &address,
+                    &block,
+                    &self.client_name,
+                    offset,
+                    len,
+                    self.client.timeout(),
+                ) {
+                    Ok(reader) => {
+                        self.reader = Some((address, reader));
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Cannot read block {} from {}: {:?}",
+                            block.b.block_id, address, e
+                        );
+                        self.dead_nodes.insert(address);
+                    }
+                }
+            }
+
+            if self.refreshed {
+                return Err(Error::Rpc(format!(
+                    "Could not obtain block {} of {} from any datanode",
+                    block.b.block_id, self.path
+                )));
+            }
+            // Replicas may have moved, ask the namenode once more before giving up
+            self.refreshed = true;
+            self.dead_nodes.clear();
+            self.locate(true)?;
+        }
+    }
+}
+
+impl<'a> Read for File<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.position >= self.length {
+                return Ok(0);
+            }
+            if self.reader.is_none() {
+                self.open_reader().map_err(to_io_error)?;
+            }
+            let result = match self.reader {
+                Some((_, ref mut reader)) => reader.read(buf),
+                None => unreachable!(),
+            };
+            match result {
+                Ok(0) => self.reader = None,
+                Ok(count) => {
+                    self.position += count as u64;
+                    self.refreshed = false;
+                    return Ok(count);
+                }
+                Err(e) => {
+                    // Retry the rest of the block on another replica
+                    let (address, _) = self.reader.take().unwrap();
+                    warn!("Error reading from {}: {}", address, e);
+                    self.dead_nodes.insert(address);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Seek for File<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => self.length as i64 + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the file",
+            ));
+        }
+        if position as u64 != self.position {
+            self.position = position as u64;
+            self.reader = None;
+        }
+        Ok(self.position)
+    }
+}
+
+fn to_io_error(error: Error) -> io::Error {
+    match error {
+        Error::Io(e) => e,
+        Error::FileNotFound(message) => io::Error::new(io::ErrorKind::NotFound, message),
+        e => io::Error::other(format!("{:?}", e)),
+    }
+}
@@ -0,0 +1,14 @@
+//! Native Hadoop client, talks to the namenode over IPC and reads blocks from the
+//! datanodes with the DataTransferProtocol, without going through libhdfs3.
+mod block_reader;
+mod connection;
+mod file;
+mod namenode;
+pub mod proto;
+
+pub use self::file::File;
+
+pub use self::namenode::{
+    get_namenode_client, namenode_addresses, NameNodeClient, CLIENT_PROTOCOL, QUOTA_DONT_SET,
+    QUOTA_RESET,
+};
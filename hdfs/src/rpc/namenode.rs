@@ -0,0 +1,580 @@
+use config;
+use err::Error;
+use fs;
+use hdfs::{ContentSummary, FileStatus, ObjectKind};
+use prost::Message;
+use rpc::connection::Connection;
+use rpc::proto::*;
+use rpc::File;
+use std::convert::TryFrom;
+use std::env;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const CLIENT_PROTOCOL: &str = "org.apache.hadoop.hdfs.protocol.ClientProtocol";
+const HOST_STRING: &str = "host";
+const HOST_PORT: &str = "port";
+const DEFAULT_PORT: u16 = 8020;
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const DIRECTORY_PERMISSION: u32 = 0o755;
+const STANDBY_EXCEPTION: &str = "StandbyException";
+/// Quota value of `setQuota` leaving the quota as it is.
+pub const QUOTA_DONT_SET: u64 = i64::MAX as u64;
+/// Quota value of `setQuota` clearing the quota.
+pub const QUOTA_RESET: u64 = u64::MAX;
+/// Time value of `setTimes` leaving the time as it is.
+const TIME_DONT_SET: u64 = u64::MAX;
+
+/// Client for the `ClientNamenodeProtocol` metadata calls.
+///
+/// With HA nameservices every namenode is tried in turn until one of them is active.
+pub struct NameNodeClient {
+    addresses: Vec<String>,
+    user: String,
+    timeout: Option<Duration>,
+    state: Mutex<State>,
+}
+
+struct State {
+    current: usize,
+    connection: Option<Connection>,
+}
+
+pub fn get_namenode_client<P: AsRef<Path>>(
+    config_path: P,
+    host: Option<&str>,
+    effective_user: Option<&str>,
+) -> Result<NameNodeClient, Error> {
+    let config = config::Config::new(config_path.as_ref())?;
+    let addresses = namenode_addresses(&config, host)?;
+    let user = effective_user
+        .map(|u| u.to_owned())
+        .or_else(default_user)
+        .ok_or_else(|| Error::MissingConfig(String::from("user missing")))?;
+    Ok(NameNodeClient::new(addresses, &user))
+}
+
+/// Resolves the rpc addresses of the namenodes behind `host`, which may be a
+/// `host:port` pair, a HA nameservice or a plain hostname.
+pub fn namenode_addresses(
+    config: &config::Config,
+    host: Option<&str>,
+) -> Result<Vec<String>, Error> {
+    let host = host
+        .or_else(|| config.get_string(HOST_STRING))
+        .ok_or_else(|| Error::MissingConfig(String::from("host config missing")))?;
+
+    if host.contains(':') {
+        return Ok(vec![host.to_owned()]);
+    }
+
+    if let Some(namenodes) = config.get_string(&format!("dfs.ha.namenodes.{}", host)) {
+        let addresses: Vec<String> = namenodes
+            .split(',')
+            .filter_map(|nn| {
+                config.get_string(&format!("dfs.namenode.rpc-address.{}.{}", host, nn.trim()))
+            })
+            .map(|address| address.to_owned())
+            .collect();
+        if !addresses.is_empty() {
+            return Ok(addresses);
+        }
+    }
+
+    if let Some(address) = config.get_string(&format!("dfs.namenode.rpc-address.{}", host)) {
+        return Ok(vec![address.to_owned()]);
+    }
+
+    let port = config.get::<u16>(HOST_PORT).unwrap_or(DEFAULT_PORT);
+    Ok(vec![format!("{}:{}", host, port)])
+}
+
+fn default_user() -> Option<String> {
+    env::var("HADOOP_USER_NAME")
+        .or_else(|_| env::var("USER"))
+        .ok()
+}
+
+impl NameNodeClient {
+    pub fn new(addresses: Vec<String>, user: &str) -> NameNodeClient {
+        NameNodeClient {
+            addresses,
+            user: user.to_owned(),
+            timeout: Some(Duration::from_secs(DEFAULT_TIMEOUT_SECS)),
+            state: Mutex::new(State {
+                current: 0,
+                connection: None,
+            }),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn current_dir(&self) -> Result<PathBuf, Error> {
+        Ok(PathBuf::from("/user").join(&self.user))
+    }
+
+    pub fn path_info<P: AsRef<Path>>(&self, path: P) -> Result<FileStatus, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = GetFileInfoRequestProto {
+            src: path_str(&path)?,
+        };
+        let response: GetFileInfoResponseProto = self.call("getFileInfo", &request)?;
+        response
+            .fs
+            .map(|fs| file_status(path.to_owned(), fs))
+            .ok_or_else(|| Error::FileNotFound(path.display().to_string()))
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        match self.path_info(path) {
+            Ok(_) => Ok(true),
+            Err(Error::FileNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Lists a directory, following the namenode pagination until the listing is complete.
+    pub fn list_directory<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FileStatus>, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let src = path_str(&path)?;
+        let mut entries = vec![];
+        let mut start_after = vec![];
+
+        loop {
+            let request = GetListingRequestProto {
+                src: src.to_owned(),
+                start_after: start_after.to_owned(),
+                need_location: false,
+            };
+            let response: GetListingResponseProto = self.call("getListing", &request)?;
+            let listing = response
+                .dir_list
+                .ok_or_else(|| Error::FileNotFound(src.to_owned()))?;
+
+            if let Some(last) = listing.partial_listing.last() {
+                start_after = last.path.to_owned();
+            }
+            for entry in listing.partial_listing {
+                // Listing a file returns the file itself with an empty name
+                let child = if entry.path.is_empty() {
+                    path.to_owned()
+                } else {
+                    path.join(String::from_utf8_lossy(&entry.path).as_ref())
+                };
+                entries.push(file_status(child, entry));
+            }
+
+            if listing.remaining_entries == 0 || start_after.is_empty() {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn content_summary<P: AsRef<Path>>(&self, path: P) -> Result<ContentSummary, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = GetContentSummaryRequestProto {
+            path: path_str(&path)?,
+        };
+        let response: GetContentSummaryResponseProto = self.call("getContentSummary", &request)?;
+        Ok(content_summary(response.summary))
+    }
+
+    /// Sets the namespace and space quotas of a directory, see `QUOTA_DONT_SET` and
+    /// `QUOTA_RESET`.
+    pub fn set_quota<P: AsRef<Path>>(
+        &self,
+        path: P,
+        namespace_quota: u64,
+        space_quota: u64,
+    ) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = SetQuotaRequestProto {
+            path: path_str(&path)?,
+            namespace_quota,
+            storagespace_quota: space_quota,
+        };
+        let _: SetQuotaResponseProto = self.call("setQuota", &request)?;
+        Ok(())
+    }
+
+    pub fn set_permission<P: AsRef<Path>>(&self, path: P, permission: u16) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = SetPermissionRequestProto {
+            src: path_str(&path)?,
+            permission: FsPermissionProto {
+                perm: u32::from(permission),
+            },
+        };
+        let _: SetPermissionResponseProto = self.call("setPermission", &request)?;
+        Ok(())
+    }
+
+    /// What is left out stays as it is.
+    pub fn set_owner<P: AsRef<Path>>(
+        &self,
+        path: P,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = SetOwnerRequestProto {
+            src: path_str(&path)?,
+            username: owner.map(str::to_owned),
+            groupname: group.map(str::to_owned),
+        };
+        let _: SetOwnerResponseProto = self.call("setOwner", &request)?;
+        Ok(())
+    }
+
+    /// False when `path` is not a file.
+    pub fn set_replication<P: AsRef<Path>>(
+        &self,
+        path: P,
+        replication: u16,
+    ) -> Result<bool, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = SetReplicationRequestProto {
+            src: path_str(&path)?,
+            replication: u32::from(replication),
+        };
+        let response: SetReplicationResponseProto = self.call("setReplication", &request)?;
+        Ok(response.result)
+    }
+
+    /// Times in milliseconds since the epoch, those left out stay as they are.
+    pub fn set_times<P: AsRef<Path>>(
+        &self,
+        path: P,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = SetTimesRequestProto {
+            src: path_str(&path)?,
+            mtime: modification_time.unwrap_or(TIME_DONT_SET),
+            atime: access_time.unwrap_or(TIME_DONT_SET),
+        };
+        let _: SetTimesResponseProto = self.call("setTimes", &request)?;
+        Ok(())
+    }
+
+    /// Creates a directory and its missing parents.
+    pub fn mkdirs<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = MkdirsRequestProto {
+            src: path_str(&path)?,
+            masked: FsPermissionProto {
+                perm: DIRECTORY_PERMISSION,
+            },
+            create_parent: true,
+        };
+        let response: MkdirsResponseProto = self.call("mkdirs", &request)?;
+        Ok(response.result)
+    }
+
+    pub fn delete<P: AsRef<Path>>(&self, path: P, recursive: bool) -> Result<bool, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = DeleteRequestProto {
+            src: path_str(&path)?,
+            recursive,
+        };
+        let response: DeleteResponseProto = self.call("delete", &request)?;
+        Ok(response.result)
+    }
+
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to: Q,
+        overwrite: bool,
+    ) -> Result<(), Error> {
+        let request = Rename2RequestProto {
+            src: path_str(&self.absolute(from.as_ref())?)?,
+            dst: path_str(&self.absolute(to.as_ref())?)?,
+            overwrite_dest: overwrite,
+        };
+        let _: Rename2ResponseProto = self.call("rename2", &request)?;
+        Ok(())
+    }
+
+    pub fn get_block_locations<P: AsRef<Path>>(
+        &self,
+        path: P,
+        offset: u64,
+        length: u64,
+    ) -> Result<LocatedBlocksProto, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let request = GetBlockLocationsRequestProto {
+            src: path_str(&path)?,
+            offset,
+            length,
+        };
+        let response: GetBlockLocationsResponseProto = self.call("getBlockLocations", &request)?;
+        response
+            .locations
+            .ok_or_else(|| Error::FileNotFound(path.display().to_string()))
+    }
+
+    /// Opens a file for reading, data is fetched directly from the datanodes.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<File<'_>, Error> {
+        let path = self.absolute(path.as_ref())?;
+        File::open(self, &path)
+    }
+
+    fn absolute(&self, path: &Path) -> Result<PathBuf, Error> {
+        if path.is_absolute() {
+            Ok(path.to_owned())
+        } else {
+            Ok(self.current_dir()?.join(path))
+        }
+    }
+
+    fn call<Req, Resp>(&self, method: &str, request: &Req) -> Result<Resp, Error>
+    where
+        Req: Message,
+        Resp: Message + Default,
+    {
+        let mut state = self.state.lock().unwrap();
+        let mut failovers = 0;
+
+        loop {
+            let can_failover = failovers + 1 < self.addresses.len();
+
+            if state.connection.is_none() {
+                let address = &self.addresses[state.current];
+                match Connection::connect(address, &self.user, CLIENT_PROTOCOL, self.timeout) {
+                    Ok(connection) => state.connection = Some(connection),
+                    Err(e) => {
+                        warn!("Cannot connect to namenode {}: {:?}", address, e);
+                        if !can_failover {
+                            return Err(e);
+                        }
+                        self.failover(&mut state);
+                        failovers += 1;
+                        continue;
+                    }
+                }
+            }
+
+            let result = match state.connection {
+                Some(ref mut connection) => connection.call(method, request),
+                None => unreachable!(),
+            };
+
+            match result {
+                Err(Error::RemoteException(ref class, _))
+                    if class == STANDBY_EXCEPTION && can_failover =>
+                {
+                    self.failover(&mut state);
+                    failovers += 1;
+                }
+                Ok(_) | Err(Error::RemoteException(_, _)) | Err(Error::FileNotFound(_)) => {
+                    return result
+                }
+                Err(_) => {
+                    // The stream may be in the middle of a frame, don't reuse it
+                    state.connection = None;
+                    return result;
+                }
+            }
+        }
+    }
+
+    fn failover(&self, state: &mut State) {
+        state.connection = None;
+        state.current = (state.current + 1) % self.addresses.len();
+        info!("Failing over to namenode {}", self.addresses[state.current]);
+    }
+}
+
+fn path_str(path: &Path) -> Result<String, Error> {
+    path.to_str()
+        .map(|s| s.to_owned())
+        .ok_or_else(|| Error::InvalidPath(path.to_owned()))
+}
+
+fn file_status(path: PathBuf, proto: HdfsFileStatusProto) -> FileStatus {
+    let kind = match FileType::try_from(proto.file_type) {
+        Ok(FileType::IsDir) => ObjectKind::Directory,
+        Ok(FileType::IsFile) => ObjectKind::File,
+        _ => ObjectKind::Unknown,
+    };
+    FileStatus {
+        path,
+        kind,
+        len: proto.length,
+        owner: proto.owner,
+        group: proto.group,
+        permission: (proto.permission.perm & 0o7777) as u16,
+        modification_time: proto.modification_time,
+        access_time: proto.access_time,
+        replication: proto.block_replication.unwrap_or(0) as u16,
+        block_size: proto.blocksize.unwrap_or(0),
+        symlink: proto
+            .symlink
+            .map(|target| PathBuf::from(String::from_utf8_lossy(&target).into_owned())),
+    }
+}
+
+fn content_summary(proto: ContentSummaryProto) -> ContentSummary {
+    let quota = |value: u64| {
+        if (value as i64) < 0 {
+            None
+        } else {
+            Some(value)
+        }
+    };
+    ContentSummary {
+        length: proto.length,
+        file_count: proto.file_count,
+        directory_count: proto.directory_count,
+        space_consumed: proto.space_consumed,
+        has_quotas: true,
+        quota: quota(proto.quota),
+        space_quota: quota(proto.space_quota),
+    }
+}
+
+/// Writes are not implemented by the native client yet.
+impl fs::FileSystem for NameNodeClient {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        NameNodeClient::current_dir(self)
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        self.path_info(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        self.list_directory(path)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(Box::new(NameNodeClient::open(self, path)?))
+    }
+
+    fn create<'a>(&'a self, path: &Path, _overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        Err(unsupported("create", path))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        NameNodeClient::delete(self, path, recursive)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        NameNodeClient::rename(self, from, to, false)
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        NameNodeClient::mkdirs(self, path)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        NameNodeClient::set_permission(self, path, permission)
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        NameNodeClient::set_owner(self, path, owner, group)
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error> {
+        if NameNodeClient::set_replication(self, path, replication)? {
+            Ok(())
+        } else {
+            Err(Error::RemoteException(
+                String::from("IOException"),
+                format!("Cannot set the replication of {}", path.display()),
+            ))
+        }
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        NameNodeClient::set_times(self, path, modification_time, access_time)
+    }
+
+    fn content_summary(&self, path: &Path) -> Result<ContentSummary, Error> {
+        NameNodeClient::content_summary(self, path)
+    }
+
+    fn set_namespace_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), Error> {
+        NameNodeClient::set_quota(self, path, quota.unwrap_or(QUOTA_RESET), QUOTA_DONT_SET)
+    }
+
+    fn set_space_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), Error> {
+        NameNodeClient::set_quota(self, path, QUOTA_DONT_SET, quota.unwrap_or(QUOTA_RESET))
+    }
+}
+
+fn unsupported(operation: &str, path: &Path) -> Error {
+    Error::Unsupported(format!(
+        "{} {} with the native client",
+        operation,
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use config::Config;
+    use rpc::namenode_addresses;
+    use std::collections::HashMap;
+
+    fn config(pairs: &[(&str, &str)]) -> Config {
+        let config_map: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config { config_map }
+    }
+
+    #[test]
+    fn test_ha_nameservice_addresses() {
+        let config = config(&[
+            ("host", "prod"),
+            ("dfs.ha.namenodes.prod", "nn1, nn2"),
+            ("dfs.namenode.rpc-address.prod.nn1", "nn1.example.com:8020"),
+            ("dfs.namenode.rpc-address.prod.nn2", "nn2.example.com:8020"),
+        ]);
+        assert_eq!(
+            vec!["nn1.example.com:8020", "nn2.example.com:8020"],
+            namenode_addresses(&config, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_plain_host_addresses() {
+        let config = config(&[("host", "namenode"), ("port", "9000")]);
+        assert_eq!(
+            vec!["namenode:9000"],
+            namenode_addresses(&config, None).unwrap()
+        );
+        assert_eq!(
+            vec!["other:8020"],
+            namenode_addresses(&config, Some("other:8020")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,499 @@
+//! Subset of the Hadoop protobuf definitions used by the client (`RpcHeader.proto`,
+//! `IpcConnectionContext.proto`, `ProtobufRpcEngine.proto`, `hdfs.proto`,
+//! `ClientNamenodeProtocol.proto` and `datatransfer.proto`), written by hand so the
+//! build doesn't need protoc.
+#![allow(clippy::derive_partial_eq_without_eq)]
+use prost::{Enumeration, Message};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum RpcKindProto {
+    RpcBuiltin = 0,
+    RpcWritable = 1,
+    RpcProtocolBuffer = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum OperationProto {
+    RpcFinalPacket = 0,
+    RpcContinuationPacket = 1,
+    RpcCloseConnection = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum RpcStatusProto {
+    Success = 0,
+    Error = 1,
+    Fatal = 2,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RpcRequestHeaderProto {
+    #[prost(enumeration = "RpcKindProto", optional, tag = "1")]
+    pub rpc_kind: Option<i32>,
+    #[prost(enumeration = "OperationProto", optional, tag = "2")]
+    pub rpc_op: Option<i32>,
+    #[prost(sint32, required, tag = "3")]
+    pub call_id: i32,
+    #[prost(bytes = "vec", required, tag = "4")]
+    pub client_id: Vec<u8>,
+    #[prost(sint32, optional, tag = "5", default = "-1")]
+    pub retry_count: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RpcResponseHeaderProto {
+    #[prost(uint32, required, tag = "1")]
+    pub call_id: u32,
+    #[prost(enumeration = "RpcStatusProto", required, tag = "2")]
+    pub status: i32,
+    #[prost(uint32, optional, tag = "3")]
+    pub server_ipc_version_num: Option<u32>,
+    #[prost(string, optional, tag = "4")]
+    pub exception_class_name: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub error_msg: Option<String>,
+    #[prost(bytes = "vec", optional, tag = "7")]
+    pub client_id: Option<Vec<u8>>,
+    #[prost(sint32, optional, tag = "8", default = "-1")]
+    pub retry_count: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct UserInformationProto {
+    #[prost(string, optional, tag = "1")]
+    pub effective_user: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub real_user: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct IpcConnectionContextProto {
+    #[prost(message, optional, tag = "2")]
+    pub user_info: Option<UserInformationProto>,
+    #[prost(string, optional, tag = "3")]
+    pub protocol: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RequestHeaderProto {
+    #[prost(string, required, tag = "1")]
+    pub method_name: String,
+    #[prost(string, required, tag = "2")]
+    pub declaring_class_protocol_name: String,
+    #[prost(uint64, required, tag = "3")]
+    pub client_protocol_version: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FsPermissionProto {
+    #[prost(uint32, required, tag = "1")]
+    pub perm: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum FileType {
+    IsDir = 1,
+    IsFile = 2,
+    IsSymlink = 3,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct HdfsFileStatusProto {
+    #[prost(enumeration = "FileType", required, tag = "1")]
+    pub file_type: i32,
+    /// Local name of the entry, empty for `getFileInfo`
+    #[prost(bytes = "vec", required, tag = "2")]
+    pub path: Vec<u8>,
+    #[prost(uint64, required, tag = "3")]
+    pub length: u64,
+    #[prost(message, required, tag = "4")]
+    pub permission: FsPermissionProto,
+    #[prost(string, required, tag = "5")]
+    pub owner: String,
+    #[prost(string, required, tag = "6")]
+    pub group: String,
+    #[prost(uint64, required, tag = "7")]
+    pub modification_time: u64,
+    #[prost(uint64, required, tag = "8")]
+    pub access_time: u64,
+    #[prost(bytes = "vec", optional, tag = "9")]
+    pub symlink: Option<Vec<u8>>,
+    #[prost(uint32, optional, tag = "10", default = "0")]
+    pub block_replication: Option<u32>,
+    #[prost(uint64, optional, tag = "11", default = "0")]
+    pub blocksize: Option<u64>,
+    #[prost(uint64, optional, tag = "13", default = "0")]
+    pub file_id: Option<u64>,
+    #[prost(int32, optional, tag = "14", default = "-1")]
+    pub children_num: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct DirectoryListingProto {
+    #[prost(message, repeated, tag = "1")]
+    pub partial_listing: Vec<HdfsFileStatusProto>,
+    #[prost(uint32, required, tag = "2")]
+    pub remaining_entries: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetFileInfoRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetFileInfoResponseProto {
+    #[prost(message, optional, tag = "1")]
+    pub fs: Option<HdfsFileStatusProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetListingRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(bytes = "vec", required, tag = "2")]
+    pub start_after: Vec<u8>,
+    #[prost(bool, required, tag = "3")]
+    pub need_location: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetListingResponseProto {
+    #[prost(message, optional, tag = "1")]
+    pub dir_list: Option<DirectoryListingProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetContentSummaryRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub path: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetContentSummaryResponseProto {
+    #[prost(message, required, tag = "1")]
+    pub summary: ContentSummaryProto,
+}
+
+/// Quotas not set are sent as -1.
+#[derive(Clone, PartialEq, Message)]
+pub struct ContentSummaryProto {
+    #[prost(uint64, required, tag = "1")]
+    pub length: u64,
+    #[prost(uint64, required, tag = "2")]
+    pub file_count: u64,
+    #[prost(uint64, required, tag = "3")]
+    pub directory_count: u64,
+    #[prost(uint64, required, tag = "4")]
+    pub quota: u64,
+    #[prost(uint64, required, tag = "5")]
+    pub space_consumed: u64,
+    #[prost(uint64, required, tag = "6")]
+    pub space_quota: u64,
+}
+
+/// Quotas are left as they are with `i64::MAX` and cleared with -1.
+#[derive(Clone, PartialEq, Message)]
+pub struct SetQuotaRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub path: String,
+    #[prost(uint64, required, tag = "2")]
+    pub namespace_quota: u64,
+    #[prost(uint64, required, tag = "3")]
+    pub storagespace_quota: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetQuotaResponseProto {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetPermissionRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(message, required, tag = "2")]
+    pub permission: FsPermissionProto,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetPermissionResponseProto {}
+
+/// What is left out stays as it is.
+#[derive(Clone, PartialEq, Message)]
+pub struct SetOwnerRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(string, optional, tag = "2")]
+    pub username: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub groupname: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetOwnerResponseProto {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetReplicationRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(uint32, required, tag = "2")]
+    pub replication: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetReplicationResponseProto {
+    #[prost(bool, required, tag = "1")]
+    pub result: bool,
+}
+
+/// Times left as they are are sent as -1.
+#[derive(Clone, PartialEq, Message)]
+pub struct SetTimesRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(uint64, required, tag = "2")]
+    pub mtime: u64,
+    #[prost(uint64, required, tag = "3")]
+    pub atime: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetTimesResponseProto {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MkdirsRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(message, required, tag = "2")]
+    pub masked: FsPermissionProto,
+    #[prost(bool, required, tag = "3")]
+    pub create_parent: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MkdirsResponseProto {
+    #[prost(bool, required, tag = "1")]
+    pub result: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct DeleteRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(bool, required, tag = "2")]
+    pub recursive: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct DeleteResponseProto {
+    #[prost(bool, required, tag = "1")]
+    pub result: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Rename2RequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(string, required, tag = "2")]
+    pub dst: String,
+    #[prost(bool, required, tag = "3")]
+    pub overwrite_dest: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Rename2ResponseProto {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ExtendedBlockProto {
+    #[prost(string, required, tag = "1")]
+    pub pool_id: String,
+    #[prost(uint64, required, tag = "2")]
+    pub block_id: u64,
+    #[prost(uint64, required, tag = "3")]
+    pub generation_stamp: u64,
+    #[prost(uint64, optional, tag = "4", default = "0")]
+    pub num_bytes: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TokenProto {
+    #[prost(bytes = "vec", required, tag = "1")]
+    pub identifier: Vec<u8>,
+    #[prost(bytes = "vec", required, tag = "2")]
+    pub password: Vec<u8>,
+    #[prost(string, required, tag = "3")]
+    pub kind: String,
+    #[prost(string, required, tag = "4")]
+    pub service: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct DatanodeIdProto {
+    #[prost(string, required, tag = "1")]
+    pub ip_addr: String,
+    #[prost(string, required, tag = "2")]
+    pub host_name: String,
+    #[prost(string, required, tag = "3")]
+    pub datanode_uuid: String,
+    #[prost(uint32, required, tag = "4")]
+    pub xfer_port: u32,
+    #[prost(uint32, required, tag = "5")]
+    pub info_port: u32,
+    #[prost(uint32, required, tag = "6")]
+    pub ipc_port: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct DatanodeInfoProto {
+    #[prost(message, required, tag = "1")]
+    pub id: DatanodeIdProto,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct LocatedBlockProto {
+    #[prost(message, required, tag = "1")]
+    pub b: ExtendedBlockProto,
+    /// Offset of the first byte of the block in the file
+    #[prost(uint64, required, tag = "2")]
+    pub offset: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub locs: Vec<DatanodeInfoProto>,
+    #[prost(bool, required, tag = "4")]
+    pub corrupt: bool,
+    #[prost(message, required, tag = "5")]
+    pub block_token: TokenProto,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct LocatedBlocksProto {
+    #[prost(uint64, required, tag = "1")]
+    pub file_length: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub blocks: Vec<LocatedBlockProto>,
+    #[prost(bool, required, tag = "3")]
+    pub under_construction: bool,
+    #[prost(message, optional, tag = "4")]
+    pub last_block: Option<LocatedBlockProto>,
+    #[prost(bool, required, tag = "5")]
+    pub is_last_block_complete: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetBlockLocationsRequestProto {
+    #[prost(string, required, tag = "1")]
+    pub src: String,
+    #[prost(uint64, required, tag = "2")]
+    pub offset: u64,
+    #[prost(uint64, required, tag = "3")]
+    pub length: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetBlockLocationsResponseProto {
+    #[prost(message, optional, tag = "1")]
+    pub locations: Option<LocatedBlocksProto>,
+}
+
+// datatransfer.proto
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum Status {
+    Success = 0,
+    Error = 1,
+    ErrorChecksum = 2,
+    ErrorInvalid = 3,
+    ErrorExists = 4,
+    ErrorAccessToken = 5,
+    ChecksumOk = 6,
+    ErrorUnsupported = 7,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum ChecksumTypeProto {
+    ChecksumNull = 0,
+    ChecksumCrc32 = 1,
+    ChecksumCrc32c = 2,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BaseHeaderProto {
+    #[prost(message, required, tag = "1")]
+    pub block: ExtendedBlockProto,
+    #[prost(message, optional, tag = "2")]
+    pub token: Option<TokenProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ClientOperationHeaderProto {
+    #[prost(message, required, tag = "1")]
+    pub base_header: BaseHeaderProto,
+    #[prost(string, required, tag = "2")]
+    pub client_name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct OpReadBlockProto {
+    #[prost(message, required, tag = "1")]
+    pub header: ClientOperationHeaderProto,
+    #[prost(uint64, required, tag = "2")]
+    pub offset: u64,
+    #[prost(uint64, required, tag = "3")]
+    pub len: u64,
+    #[prost(bool, optional, tag = "4", default = "true")]
+    pub send_checksums: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ChecksumProto {
+    #[prost(enumeration = "ChecksumTypeProto", required, tag = "1")]
+    pub checksum_type: i32,
+    #[prost(uint32, required, tag = "2")]
+    pub bytes_per_checksum: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ReadOpChecksumInfoProto {
+    #[prost(message, required, tag = "1")]
+    pub checksum: ChecksumProto,
+    /// First byte sent by the datanode, the requested offset aligned on a chunk
+    #[prost(uint64, required, tag = "2")]
+    pub chunk_offset: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockOpResponseProto {
+    #[prost(enumeration = "Status", required, tag = "1")]
+    pub status: i32,
+    #[prost(string, optional, tag = "2")]
+    pub first_bad_link: Option<String>,
+    #[prost(message, optional, tag = "4")]
+    pub read_op_checksum_info: Option<ReadOpChecksumInfoProto>,
+    #[prost(string, optional, tag = "5")]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ClientReadStatusProto {
+    #[prost(enumeration = "Status", required, tag = "1")]
+    pub status: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct PacketHeaderProto {
+    #[prost(sfixed64, required, tag = "1")]
+    pub offset_in_block: i64,
+    #[prost(sfixed64, required, tag = "2")]
+    pub seqno: i64,
+    #[prost(bool, required, tag = "3")]
+    pub last_packet_in_block: bool,
+    #[prost(sfixed32, required, tag = "4")]
+    pub data_len: i32,
+    #[prost(bool, optional, tag = "5", default = "false")]
+    pub sync_block: Option<bool>,
+}
@@ -0,0 +1,757 @@
+//! An `ObjectStore` on top of a `Backend`, to read and write HDFS from the crates of the
+//! Arrow ecosystem.
+//!
+//! Objects are the files under a root directory, directories only show up as common
+//! prefixes. Puts are written aside and renamed in place, so readers never see a partial
+//! object. Two things differ from the usual object stores:
+//!
+//! * the file system trait has no concat, multipart uploads write their parts aside and
+//!   copy them into the object on completion, so their data is written twice;
+//! * HDFS renames do not replace files, overwriting deletes the object before renaming
+//!   the new one in place, and readers in between find no object at all.
+//!
+//! What a crashed writer leaves aside is removed once older than a day, when the next
+//! multipart upload starts.
+use async_hdfs::{AsyncFile, AsyncHdfs, Backend};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use err::Error as HdfsError;
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use hdfs::FileStatus;
+use object_store::path::Path as ObjectPath;
+use object_store::{
+    Attributes, Error, GetOptions, GetResult, GetResultPayload, ListResult, MultipartUpload,
+    ObjectMeta, ObjectStore, PutMode, PutMultipartOptions, PutOptions, PutPayload, PutResult,
+    Result, UploadPart,
+};
+use rand::{self, Rng};
+use std::cmp;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, ReadBuf};
+
+const STORE: &str = "HDFS";
+/// Puts and multipart uploads in progress, hidden from listings
+const STAGING_DIR: &str = ".object_store";
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// Milliseconds after which untouched staging files are abandoned
+const STAGING_EXPIRY: u64 = 24 * 60 * 60 * 1000;
+
+fn to_error(location: &str, error: HdfsError) -> Error {
+    let path = location.to_owned();
+    let class = match error {
+        HdfsError::FileNotFound(_) => {
+            return Error::NotFound {
+                path,
+                source: Box::new(error),
+            }
+        }
+        HdfsError::RemoteException(ref class, _) => class.to_owned(),
+        _ => String::new(),
+    };
+    match class.as_str() {
+        "FileAlreadyExistsException" => Error::AlreadyExists {
+            path,
+            source: Box::new(error),
+        },
+        "AccessControlException" => Error::PermissionDenied {
+            path,
+            source: Box::new(error),
+        },
+        _ => generic(error),
+    }
+}
+
+fn generic<E: ::std::error::Error + Send + Sync + 'static>(error: E) -> Error {
+    Error::Generic {
+        store: STORE,
+        source: Box::new(error),
+    }
+}
+
+fn not_found(location: &str) -> Error {
+    Error::NotFound {
+        path: location.to_owned(),
+        source: "object not found".into(),
+    }
+}
+
+fn already_exists(location: &str) -> Error {
+    Error::AlreadyExists {
+        path: location.to_owned(),
+        source: "object already exists".into(),
+    }
+}
+
+/// Changes with every write, unless two writes of the same length happen the same
+/// millisecond.
+fn e_tag(status: &FileStatus) -> String {
+    format!("{:x}-{:x}", status.modification_time, status.len)
+}
+
+fn object_meta(location: ObjectPath, status: &FileStatus) -> ObjectMeta {
+    ObjectMeta {
+        location,
+        last_modified: DateTime::<Utc>::from_timestamp_millis(status.modification_time as i64)
+            .unwrap_or_default(),
+        size: status.len,
+        e_tag: Some(e_tag(status)),
+        version: None,
+    }
+}
+
+fn child(prefix: &ObjectPath, name: &str) -> Option<ObjectPath> {
+    let location = if prefix.as_ref().is_empty() {
+        if name == STAGING_DIR {
+            return None;
+        }
+        name.to_owned()
+    } else {
+        format!("{}/{}", prefix, name)
+    };
+    // Files not written through the store may not be valid object paths
+    ObjectPath::parse(location).ok()
+}
+
+/// Statuses may hold full URIs, only their names are used.
+fn file_name(status: &FileStatus) -> Option<&str> {
+    status.path.file_name().and_then(|name| name.to_str())
+}
+
+fn staging_path(root: &Path) -> PathBuf {
+    root.join(STAGING_DIR)
+        .join(format!("{:016x}", rand::thread_rng().gen::<u64>()))
+}
+
+/// The object at `path`, directories are not objects.
+fn stat_object<F: Backend>(fs: &F, path: &Path, location: &ObjectPath) -> Result<FileStatus> {
+    let status = fs.stat(path).map_err(|e| to_error(location.as_ref(), e))?;
+    if status.is_dir() {
+        return Err(not_found(location.as_ref()));
+    }
+    Ok(status)
+}
+
+fn write_object<F: Backend>(fs: &F, path: &Path, payload: &PutPayload) -> Result<()> {
+    let location = path.display().to_string();
+    let mut writer = fs.create(path, false).map_err(|e| to_error(&location, e))?;
+    for chunk in payload.iter() {
+        writer.write_all(chunk).map_err(generic)?;
+    }
+    writer.flush().map_err(generic)
+}
+
+/// Moves the staged file into place, removing it on failure.
+fn commit<F: Backend>(
+    fs: &F,
+    staging: &Path,
+    path: &Path,
+    location: &ObjectPath,
+    overwrite: bool,
+) -> Result<PutResult> {
+    let result = rename(fs, staging, path, location, overwrite);
+    if result.is_err() {
+        let _ = fs.delete(staging, false);
+    }
+    result?;
+    let status = fs.stat(path).map_err(|e| to_error(location.as_ref(), e))?;
+    Ok(PutResult {
+        e_tag: Some(e_tag(&status)),
+        version: None,
+    })
+}
+
+/// Not atomic when overwriting: the object is deleted first, HDFS renames do not replace
+/// files.
+fn rename<F: Backend>(
+    fs: &F,
+    from: &Path,
+    to: &Path,
+    location: &ObjectPath,
+    overwrite: bool,
+) -> Result<()> {
+    let map_error = |e| to_error(location.as_ref(), e);
+    if overwrite && fs.exists(to).map_err(map_error)? {
+        fs.delete(to, false).map_err(map_error)?;
+    }
+    if let Some(parent) = to.parent() {
+        fs.mkdirs(parent).map_err(map_error)?;
+    }
+    // The rename is atomic, it fails when somebody else created the object meanwhile
+    fs.rename(from, to).map_err(|e| match fs.exists(to) {
+        Ok(true) if !overwrite => already_exists(location.as_ref()),
+        _ => map_error(e),
+    })
+}
+
+/// Removes what puts and uploads left in the staging directory and did not touch for
+/// `STAGING_EXPIRY`, ignoring errors: another store may be removing them too.
+fn clean_staging<F: Backend>(fs: &F, root: &Path, now: u64) {
+    let entries = match fs.list(&root.join(STAGING_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for status in entries {
+        let path = match file_name(&status) {
+            Some(name) => root.join(STAGING_DIR).join(name),
+            None => continue,
+        };
+        // Writing a part does not always change the time of the upload directory
+        let mut modified = status.modification_time;
+        if status.is_dir() {
+            match fs.list(&path) {
+                Ok(parts) => {
+                    for part in parts {
+                        modified = cmp::max(modified, part.modification_time);
+                    }
+                }
+                Err(_) => continue,
+            }
+        }
+        if modified.saturating_add(STAGING_EXPIRY) < now {
+            let _ = fs.delete(&path, true);
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Removes the directories left empty by a delete or a rename, up to the root.
+fn prune<F: Backend>(fs: &F, root: &Path, path: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) {
+            return;
+        }
+        match fs.list(dir) {
+            Ok(ref children) if children.is_empty() => {
+                if fs.delete(dir, false).is_err() {
+                    return;
+                }
+            }
+            _ => return,
+        }
+    }
+}
+
+fn list_objects<F: Backend>(
+    fs: &F,
+    dir: &Path,
+    prefix: &ObjectPath,
+    objects: &mut Vec<ObjectMeta>,
+) -> Result<()> {
+    let children = match fs.list(dir) {
+        Ok(children) => children,
+        // Removed while listing
+        Err(HdfsError::FileNotFound(_)) => return Ok(()),
+        Err(e) => return Err(to_error(prefix.as_ref(), e)),
+    };
+    for status in children {
+        let (name, location) = match file_name(&status)
+            .and_then(|name| child(prefix, name).map(|location| (name, location)))
+        {
+            Some(child) => child,
+            None => continue,
+        };
+        if status.is_dir() {
+            list_objects(fs, &dir.join(name), &location, objects)?;
+        } else {
+            objects.push(object_meta(location, &status));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` is a directory, `prefix` only lists directories.
+fn is_dir<F: Backend>(fs: &F, path: &Path, prefix: &ObjectPath) -> Result<bool> {
+    match fs.stat(path) {
+        Ok(status) => Ok(status.is_dir()),
+        Err(HdfsError::FileNotFound(_)) => Ok(false),
+        Err(e) => Err(to_error(prefix.as_ref(), e)),
+    }
+}
+
+/// Reads the requested range of an object.
+struct ObjectStream<F: Backend> {
+    file: AsyncFile<F>,
+    remaining: u64,
+    buffer: Vec<u8>,
+}
+
+impl<F: Backend> Stream for ObjectStream<F> {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Bytes>>> {
+        let stream = self.get_mut();
+        if stream.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let len = cmp::min(stream.remaining, CHUNK_SIZE as u64) as usize;
+        stream.buffer.resize(len, 0);
+        let mut read_buf = ReadBuf::new(&mut stream.buffer);
+        match Pin::new(&mut stream.file).poll_read(cx, &mut read_buf) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                let result = result.and_then(|_| match read_buf.filled().len() {
+                    0 => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                    _ => Ok(Bytes::copy_from_slice(read_buf.filled())),
+                });
+                match result {
+                    Ok(bytes) => {
+                        stream.remaining -= bytes.len() as u64;
+                        Poll::Ready(Some(Ok(bytes)))
+                    }
+                    Err(e) => {
+                        stream.remaining = 0;
+                        Poll::Ready(Some(Err(generic(e))))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Objects stored under a root directory of a `Backend`.
+pub struct HdfsObjectStore<F: Backend> {
+    fs: AsyncHdfs<F>,
+    root: PathBuf,
+}
+
+impl<F: Backend> HdfsObjectStore<F> {
+    /// `root` is absolute, object paths are relative to it.
+    pub fn new<P: AsRef<Path>>(fs: AsyncHdfs<F>, root: P) -> HdfsObjectStore<F> {
+        HdfsObjectStore {
+            fs,
+            root: root.as_ref().to_owned(),
+        }
+    }
+
+    pub fn inner(&self) -> &AsyncHdfs<F> {
+        &self.fs
+    }
+
+    fn path(&self, location: &ObjectPath) -> PathBuf {
+        match location.as_ref() {
+            "" => self.root.clone(),
+            location => self.root.join(location),
+        }
+    }
+
+    /// Runs `call` on the pool with the backend and the root.
+    fn blocking<T, C>(&self, call: C) -> BoxFuture<'static, Result<T>>
+    where
+        T: Send + 'static,
+        C: FnOnce(&F, &Path) -> Result<T> + Send + 'static,
+    {
+        let root = self.root.clone();
+        self.fs
+            .spawn(move |fs| call(fs, &root))
+            .map(|result| {
+                result
+                    .unwrap_or_else(|_| Err(generic(io::Error::other("blocking thread panicked"))))
+            })
+            .boxed()
+    }
+
+    fn transfer(
+        &self,
+        from: &ObjectPath,
+        to: &ObjectPath,
+        overwrite: bool,
+        keep_source: bool,
+    ) -> BoxFuture<'static, Result<()>> {
+        let (from_path, to_path) = (self.path(from), self.path(to));
+        let (from, to) = (from.clone(), to.clone());
+        self.blocking(move |fs, root| {
+            stat_object(fs, &from_path, &from)?;
+            if keep_source {
+                let staging = staging_path(root);
+                copy_object(fs, &from_path, &staging, &from)?;
+                commit(fs, &staging, &to_path, &to, overwrite).map(|_| ())
+            } else {
+                rename(fs, &from_path, &to_path, &to, overwrite)?;
+                prune(fs, root, &from_path);
+                Ok(())
+            }
+        })
+    }
+}
+
+fn copy_object<F: Backend>(fs: &F, from: &Path, to: &Path, location: &ObjectPath) -> Result<()> {
+    let mut reader = fs.open(from).map_err(|e| to_error(location.as_ref(), e))?;
+    let result = fs
+        .create(to, false)
+        .map_err(|e| to_error(location.as_ref(), e))
+        .and_then(|mut writer| {
+            io::copy(&mut reader, &mut writer).map_err(generic)?;
+            writer.flush().map_err(generic)
+        });
+    if result.is_err() {
+        let _ = fs.delete(to, false);
+    }
+    result
+}
+
+impl<F: Backend> fmt::Display for HdfsObjectStore<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HdfsObjectStore({})", self.root.display())
+    }
+}
+
+impl<F: Backend> fmt::Debug for HdfsObjectStore<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HdfsObjectStore")
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+// The methods are those `async_trait` generates, async fns need a newer edition
+impl<F: Backend> ObjectStore for HdfsObjectStore<F> {
+    fn put_opts<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        location: &'life1 ObjectPath,
+        payload: PutPayload,
+        opts: PutOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<PutResult>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        let overwrite = match opts.mode {
+            PutMode::Overwrite => true,
+            PutMode::Create => false,
+            PutMode::Update(_) => return future::ready(Err(Error::NotImplemented)).boxed(),
+        };
+        let path = self.path(location);
+        let location = location.clone();
+        self.blocking(move |fs, root| {
+            let staging = staging_path(root);
+            if let Err(e) = write_object(fs, &staging, &payload) {
+                let _ = fs.delete(&staging, false);
+                return Err(e);
+            }
+            commit(fs, &staging, &path, &location, overwrite)
+        })
+    }
+
+    fn put_multipart_opts<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        location: &'life1 ObjectPath,
+        _opts: PutMultipartOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<Box<dyn MultipartUpload>>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        let upload = HdfsUpload {
+            fs: self.fs.clone(),
+            root: self.root.clone(),
+            path: self.path(location),
+            location: location.clone(),
+            staging: staging_path(&self.root),
+            parts: 0,
+        };
+        let staging = upload.staging.clone();
+        self.blocking(move |fs, root| {
+            clean_staging(fs, root, now());
+            fs.mkdirs(&staging)
+                .map_err(|e| to_error(&staging.display().to_string(), e))?;
+            Ok(Box::new(upload) as Box<dyn MultipartUpload>)
+        })
+    }
+
+    fn get_opts<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        location: &'life1 ObjectPath,
+        options: GetOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<GetResult>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        let path = self.path(location);
+        let location = location.clone();
+        let async_fs = self.fs.clone();
+        self.blocking(move |fs, _| {
+            let meta = object_meta(location.clone(), &stat_object(fs, &path, &location)?);
+            options.check_preconditions(&meta)?;
+            let range = match options.range {
+                Some(ref range) => range.as_range(meta.size).map_err(generic)?,
+                None => 0..meta.size,
+            };
+            if options.head {
+                return Ok((meta, range, None));
+            }
+            let mut file = fs
+                .open_file(&path)
+                .map_err(|e| to_error(location.as_ref(), e))?;
+            file.seek(SeekFrom::Start(range.start)).map_err(generic)?;
+            Ok((meta, range, Some(file)))
+        })
+        .map(move |result| {
+            let (meta, range, file) = result?;
+            let stream = match file {
+                Some(file) => ObjectStream {
+                    file: AsyncFile::new(async_fs, file),
+                    remaining: range.end - range.start,
+                    buffer: vec![],
+                }
+                .boxed(),
+                None => stream::empty().boxed(),
+            };
+            Ok(GetResult {
+                payload: GetResultPayload::Stream(stream),
+                meta,
+                range,
+                attributes: Attributes::default(),
+            })
+        })
+        .boxed()
+    }
+
+    fn delete<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        location: &'life1 ObjectPath,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        let path = self.path(location);
+        let location = location.clone();
+        self.blocking(move |fs, root| {
+            match stat_object(fs, &path, &location) {
+                Ok(_) => {}
+                // Like most object stores, deleting a missing object succeeds
+                Err(Error::NotFound { .. }) => return Ok(()),
+                Err(e) => return Err(e),
+            }
+            fs.delete(&path, false)
+                .map_err(|e| to_error(location.as_ref(), e))?;
+            prune(fs, root, &path);
+            Ok(())
+        })
+    }
+
+    fn list(&self, prefix: Option<&ObjectPath>) -> BoxStream<'static, Result<ObjectMeta>> {
+        let prefix = prefix.cloned().unwrap_or_default();
+        let path = self.path(&prefix);
+        let objects = self.blocking(move |fs, _| {
+            let mut objects = vec![];
+            if is_dir(fs, &path, &prefix)? {
+                list_objects(fs, &path, &prefix, &mut objects)?;
+            }
+            Ok(objects)
+        });
+        stream::once(objects)
+            .map(|result| {
+                stream::iter(match result {
+                    Ok(objects) => objects.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                })
+            })
+            .flatten()
+            .boxed()
+    }
+
+    fn list_with_delimiter<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        prefix: Option<&'life1 ObjectPath>,
+    ) -> Pin<Box<dyn Future<Output = Result<ListResult>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        let prefix = prefix.cloned().unwrap_or_default();
+        let path = self.path(&prefix);
+        self.blocking(move |fs, _| {
+            let mut result = ListResult {
+                common_prefixes: vec![],
+                objects: vec![],
+            };
+            if !is_dir(fs, &path, &prefix)? {
+                return Ok(result);
+            }
+            let children = fs.list(&path).map_err(|e| to_error(prefix.as_ref(), e))?;
+            for status in children {
+                let location = match file_name(&status).and_then(|name| child(&prefix, name)) {
+                    Some(location) => location,
+                    None => continue,
+                };
+                if status.is_dir() {
+                    result.common_prefixes.push(location);
+                } else {
+                    result.objects.push(object_meta(location, &status));
+                }
+            }
+            result.common_prefixes.sort();
+            result.objects.sort_by(|a, b| a.location.cmp(&b.location));
+            Ok(result)
+        })
+    }
+
+    fn copy<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 self,
+        from: &'life1 ObjectPath,
+        to: &'life2 ObjectPath,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        self.transfer(from, to, true, true)
+    }
+
+    fn rename<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 self,
+        from: &'life1 ObjectPath,
+        to: &'life2 ObjectPath,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        self.transfer(from, to, true, false)
+    }
+
+    fn copy_if_not_exists<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 self,
+        from: &'life1 ObjectPath,
+        to: &'life2 ObjectPath,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        self.transfer(from, to, false, true)
+    }
+
+    fn rename_if_not_exists<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 self,
+        from: &'life1 ObjectPath,
+        to: &'life2 ObjectPath,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        self.transfer(from, to, false, false)
+    }
+}
+
+/// Parts are numbered files in a staging directory, copied one after the other into
+/// the object on completion. Completing overwrites the object the way puts do, without
+/// atomicity.
+pub struct HdfsUpload<F: Backend> {
+    fs: AsyncHdfs<F>,
+    root: PathBuf,
+    path: PathBuf,
+    location: ObjectPath,
+    staging: PathBuf,
+    parts: usize,
+}
+
+impl<F: Backend> fmt::Debug for HdfsUpload<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HdfsUpload")
+            .field("location", &self.location)
+            .field("staging", &self.staging)
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+impl<F: Backend> HdfsUpload<F> {
+    fn blocking<T, C>(&self, call: C) -> BoxFuture<'static, Result<T>>
+    where
+        T: Send + 'static,
+        C: FnOnce(&F) -> Result<T> + Send + 'static,
+    {
+        HdfsObjectStore::new(self.fs.clone(), &self.root).blocking(move |fs, _| call(fs))
+    }
+}
+
+fn part_path(staging: &Path, part: usize) -> PathBuf {
+    staging.join(format!("{:08}", part))
+}
+
+fn concat<F: Backend>(fs: &F, staging: &Path, parts: usize, to: &Path) -> Result<()> {
+    let location = staging.display().to_string();
+    let mut writer = fs.create(to, false).map_err(|e| to_error(&location, e))?;
+    for part in 0..parts {
+        let mut reader = fs
+            .open(&part_path(staging, part))
+            .map_err(|e| to_error(&location, e))?;
+        io::copy(&mut reader, &mut writer).map_err(generic)?;
+    }
+    writer.flush().map_err(generic)
+}
+
+impl<F: Backend> MultipartUpload for HdfsUpload<F> {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        let part = part_path(&self.staging, self.parts);
+        self.parts += 1;
+        self.blocking(move |fs| write_object(fs, &part, &data))
+    }
+
+    fn complete<'life0, 'async_trait>(
+        &'life0 mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<PutResult>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        Self: 'async_trait,
+    {
+        let (staging, parts) = (self.staging.clone(), self.parts);
+        let object = staging_path(&self.root);
+        let (path, location) = (self.path.clone(), self.location.clone());
+        self.blocking(move |fs| {
+            let result = concat(fs, &staging, parts, &object);
+            if result.is_err() {
+                let _ = fs.delete(&object, false);
+            }
+            result?;
+            let result = commit(fs, &object, &path, &location, true);
+            let _ = fs.delete(&staging, true);
+            result
+        })
+    }
+
+    fn abort<'life0, 'async_trait>(
+        &'life0 mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        Self: 'async_trait,
+    {
+        let staging = self.staging.clone();
+        self.blocking(move |fs| {
+            fs.delete(&staging, true)
+                .map(|_| ())
+                .map_err(|e| to_error(&staging.display().to_string(), e))
+        })
+    }
+}
@@ -0,0 +1,746 @@
+//! WebHDFS REST protocol, shared by the namenode's embedded server and HttpFS gateways.
+use err::Error;
+use fs;
+use hdfs::{ContentSummary, FileStatus, ObjectKind};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const WEBHDFS_PREFIX: &str = "/webhdfs/v1";
+const OCTET_STREAM: &str = "application/octet-stream";
+const MAX_REDIRECTS: usize = 5;
+/// Data buffered by a `Writer` before it uploads on its own.
+const UPLOAD_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flavor {
+    /// Namenode WebHDFS: `OPEN`, `CREATE` and `APPEND` answer with a `307` pointing
+    /// to a datanode, and uploads are done in two steps.
+    NameNode,
+    /// HttpFS gateway: data goes through the gateway itself and uploads are sent
+    /// in a single request flagged with `data=true`.
+    HttpFs,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WebHdfsOptions {
+    /// Base url of the server, e.g. `http://gateway:14000`
+    pub url: String,
+    /// User sent as `user.name` for pseudo authentication
+    pub user: Option<String>,
+    /// Delegation token, takes precedence over `user`
+    pub delegation: Option<String>,
+    /// Extra headers sent with every request (proxy authentication, ...)
+    pub headers: Vec<(String, String)>,
+}
+
+pub struct WebHdfs {
+    agent: ureq::Agent,
+    flavor: Flavor,
+    options: WebHdfsOptions,
+    home: Mutex<Option<PathBuf>>,
+}
+
+pub fn get_webhdfs(options: WebHdfsOptions) -> WebHdfs {
+    WebHdfs::new(Flavor::NameNode, options)
+}
+
+pub fn get_httpfs(options: WebHdfsOptions) -> WebHdfs {
+    WebHdfs::new(Flavor::HttpFs, options)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFileStatus {
+    #[serde(default)]
+    path_suffix: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    length: u64,
+    #[serde(default)]
+    owner: String,
+    #[serde(default)]
+    group: String,
+    #[serde(default)]
+    permission: String,
+    #[serde(default)]
+    modification_time: u64,
+    #[serde(default)]
+    access_time: u64,
+    #[serde(default)]
+    replication: u16,
+    #[serde(default)]
+    block_size: u64,
+    symlink: Option<String>,
+}
+
+impl RawFileStatus {
+    fn into_file_status(self, path: PathBuf) -> FileStatus {
+        let kind = match self.kind.as_str() {
+            "FILE" => ObjectKind::File,
+            "DIRECTORY" => ObjectKind::Directory,
+            _ => ObjectKind::Unknown,
+        };
+        FileStatus {
+            path,
+            kind,
+            len: self.length,
+            owner: self.owner,
+            group: self.group,
+            permission: u16::from_str_radix(&self.permission, 8).unwrap_or(0),
+            modification_time: self.modification_time,
+            access_time: self.access_time,
+            replication: self.replication,
+            block_size: self.block_size,
+            symlink: self.symlink.map(PathBuf::from),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FileStatusResponse {
+    file_status: RawFileStatus,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FileStatusesResponse {
+    file_statuses: FileStatuses,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FileStatuses {
+    file_status: Vec<RawFileStatus>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContentSummaryResponse {
+    content_summary: RawContentSummary,
+}
+
+/// Quotas not set are reported as -1.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawContentSummary {
+    directory_count: u64,
+    file_count: u64,
+    length: u64,
+    #[serde(default)]
+    space_consumed: u64,
+    #[serde(default = "no_quota")]
+    quota: i64,
+    #[serde(default = "no_quota")]
+    space_quota: i64,
+}
+
+fn no_quota() -> i64 {
+    -1
+}
+
+impl RawContentSummary {
+    fn into_content_summary(self) -> ContentSummary {
+        let quota = |value: i64| if value < 0 { None } else { Some(value as u64) };
+        ContentSummary {
+            length: self.length,
+            file_count: self.file_count,
+            directory_count: self.directory_count,
+            space_consumed: self.space_consumed,
+            has_quotas: true,
+            quota: quota(self.quota),
+            space_quota: quota(self.space_quota),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BooleanResponse {
+    boolean: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PathResponse {
+    path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RemoteExceptionResponse {
+    remote_exception: RemoteException,
+}
+
+#[derive(Deserialize)]
+struct RemoteException {
+    exception: String,
+    #[serde(default)]
+    message: String,
+}
+
+pub struct File {
+    reader: Box<dyn Read + Send + Sync>,
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.reader.read(buf)
+    }
+}
+
+/// Buffers what is written and uploads it when flushed: the first upload creates the
+/// file and the next ones append to it. Dropping uploads what is left, its errors are
+/// lost then.
+pub struct Writer<'a> {
+    fs: &'a WebHdfs,
+    path: PathBuf,
+    overwrite: bool,
+    created: bool,
+    buffer: Vec<u8>,
+}
+
+impl<'a> Writer<'a> {
+    fn upload(&mut self) -> Result<(), Error> {
+        if !self.created {
+            self.fs
+                .create(&self.path, self.overwrite, &self.buffer[..])?;
+            self.created = true;
+        } else if !self.buffer.is_empty() {
+            self.fs.append(&self.path, &self.buffer[..])?;
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<'a> Write for Writer<'a> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        if !self.buffer.is_empty() && self.buffer.len() + buf.len() > UPLOAD_SIZE {
+            self.flush()?;
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.upload().map_err(|e| match e {
+            Error::Io(e) => e,
+            e => io::Error::other(e),
+        })
+    }
+}
+
+impl<'a> Drop for Writer<'a> {
+    fn drop(&mut self) {
+        let _ = self.upload();
+    }
+}
+
+impl WebHdfs {
+    pub fn new(flavor: Flavor, options: WebHdfsOptions) -> WebHdfs {
+        WebHdfs {
+            agent: ureq::AgentBuilder::new().redirects(0).build(),
+            flavor,
+            options,
+            home: Mutex::new(None),
+        }
+    }
+
+    pub fn flavor(&self) -> Flavor {
+        self.flavor
+    }
+
+    pub fn current_dir(&self) -> Result<PathBuf, Error> {
+        let mut home = self.home.lock().unwrap();
+        if let Some(ref path) = *home {
+            return Ok(path.to_owned());
+        }
+        let url = self.url(Path::new("/"), "GETHOMEDIRECTORY", &[])?;
+        let response: PathResponse = self.json(self.request("GET", &url).call())?;
+        let path = PathBuf::from(response.path);
+        *home = Some(path.to_owned());
+        Ok(path)
+    }
+
+    pub fn path_info<P: AsRef<Path>>(&self, path: P) -> Result<FileStatus, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let url = self.url(&path, "GETFILESTATUS", &[])?;
+        let response: FileStatusResponse = self.json(self.request("GET", &url).call())?;
+        Ok(response.file_status.into_file_status(path))
+    }
+
+    pub fn list_directory<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FileStatus>, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let url = self.url(&path, "LISTSTATUS", &[])?;
+        let response: FileStatusesResponse = self.json(self.request("GET", &url).call())?;
+        Ok(response
+            .file_statuses
+            .file_status
+            .into_iter()
+            .map(|raw| {
+                // LISTSTATUS on a file returns the file itself with an empty suffix
+                let child = if raw.path_suffix.is_empty() {
+                    path.to_owned()
+                } else {
+                    path.join(&raw.path_suffix)
+                };
+                raw.into_file_status(child)
+            })
+            .collect())
+    }
+
+    pub fn content_summary<P: AsRef<Path>>(&self, path: P) -> Result<ContentSummary, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let url = self.url(&path, "GETCONTENTSUMMARY", &[])?;
+        let response: ContentSummaryResponse = self.json(self.request("GET", &url).call())?;
+        Ok(response.content_summary.into_content_summary())
+    }
+
+    /// Sets `quota`, `namespacequota` or `storagespacequota`, -1 clearing it. The quota
+    /// left out stays as it is.
+    fn set_quota(&self, path: &Path, param: &str, quota: Option<u64>) -> Result<(), Error> {
+        let path = self.absolute(path)?;
+        let quota = quota.map_or_else(|| String::from("-1"), |quota| quota.to_string());
+        let url = self.url(&path, "SETQUOTA", &[(param, &quota)])?;
+        check(self.request("PUT", &url).call())?;
+        Ok(())
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        match self.path_info(path) {
+            Ok(_) => Ok(true),
+            Err(Error::FileNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn mkdirs<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let url = self.url(&path, "MKDIRS", &[])?;
+        let response: BooleanResponse = self.json(self.request("PUT", &url).call())?;
+        Ok(response.boolean)
+    }
+
+    pub fn set_permission<P: AsRef<Path>>(&self, path: P, permission: u16) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let permission = format!("{:o}", permission);
+        let url = self.url(&path, "SETPERMISSION", &[("permission", &permission)])?;
+        check(self.request("PUT", &url).call())?;
+        Ok(())
+    }
+
+    /// What is left out stays as it is.
+    pub fn set_owner<P: AsRef<Path>>(
+        &self,
+        path: P,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let params: Vec<_> = vec![("owner", owner), ("group", group)]
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect();
+        let url = self.url(&path, "SETOWNER", &params)?;
+        check(self.request("PUT", &url).call())?;
+        Ok(())
+    }
+
+    /// False when `path` is not a file.
+    pub fn set_replication<P: AsRef<Path>>(
+        &self,
+        path: P,
+        replication: u16,
+    ) -> Result<bool, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let replication = replication.to_string();
+        let url = self.url(&path, "SETREPLICATION", &[("replication", &replication)])?;
+        let response: BooleanResponse = self.json(self.request("PUT", &url).call())?;
+        Ok(response.boolean)
+    }
+
+    /// Times in milliseconds since the epoch, -1 leaving the time left out as it is.
+    pub fn set_times<P: AsRef<Path>>(
+        &self,
+        path: P,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let time = |time: Option<u64>| time.map_or_else(|| String::from("-1"), |t| t.to_string());
+        let modification_time = time(modification_time);
+        let access_time = time(access_time);
+        let url = self.url(
+            &path,
+            "SETTIMES",
+            &[
+                ("modificationtime", &modification_time),
+                ("accesstime", &access_time),
+            ],
+        )?;
+        check(self.request("PUT", &url).call())?;
+        Ok(())
+    }
+
+    pub fn delete<P: AsRef<Path>>(&self, path: P, recursive: bool) -> Result<bool, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let recursive = if recursive { "true" } else { "false" };
+        let url = self.url(&path, "DELETE", &[("recursive", recursive)])?;
+        let response: BooleanResponse = self.json(self.request("DELETE", &url).call())?;
+        Ok(response.boolean)
+    }
+
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<bool, Error> {
+        let from = self.absolute(from.as_ref())?;
+        let to = self.absolute(to.as_ref())?;
+        let to_str = to
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(to.to_owned()))?;
+        let url = self.url(&from, "RENAME", &[("destination", to_str)])?;
+        let response: BooleanResponse = self.json(self.request("PUT", &url).call())?;
+        Ok(response.boolean)
+    }
+
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<File, Error> {
+        self.open_at(path, 0)
+    }
+
+    /// Opens a file for reading, starting at `offset`.
+    pub fn open_at<P: AsRef<Path>>(&self, path: P, offset: u64) -> Result<File, Error> {
+        let path = self.absolute(path.as_ref())?;
+        let offset = offset.to_string();
+        let url = self.url(&path, "OPEN", &[("offset", offset.as_str())])?;
+        let mut response = check(self.request("GET", &url).call())?;
+
+        // The namenode never serves data itself, HttpFS normally does unless a load
+        // balancer in front of it redirects us to another gateway.
+        let mut hops = 0;
+        while let Some(location) = redirect_location(&response) {
+            hops += 1;
+            if hops > MAX_REDIRECTS {
+                return Err(Error::Http(format!("too many redirects opening {}", url)));
+            }
+            let location = self.resolve_location(&location);
+            debug!("OPEN redirected to {}", location);
+            response = check(self.request("GET", &location).call())?;
+        }
+
+        Ok(File {
+            reader: response.into_reader(),
+        })
+    }
+
+    /// Creates a file, uploading everything `data` yields.
+    pub fn create<P: AsRef<Path>, R: Read>(
+        &self,
+        path: P,
+        overwrite: bool,
+        data: R,
+    ) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        let overwrite = if overwrite { "true" } else { "false" };
+        self.upload("PUT", &path, "CREATE", &[("overwrite", overwrite)], data)
+    }
+
+    /// A file written as it is flushed, see `Writer`.
+    pub fn writer<P: AsRef<Path>>(&self, path: P, overwrite: bool) -> Result<Writer<'_>, Error> {
+        Ok(Writer {
+            fs: self,
+            path: self.absolute(path.as_ref())?,
+            overwrite,
+            created: false,
+            buffer: vec![],
+        })
+    }
+
+    /// Appends everything `data` yields to an existing file.
+    pub fn append<P: AsRef<Path>, R: Read>(&self, path: P, data: R) -> Result<(), Error> {
+        let path = self.absolute(path.as_ref())?;
+        self.upload("POST", &path, "APPEND", &[], data)
+    }
+
+    fn upload<R: Read>(
+        &self,
+        method: &str,
+        path: &Path,
+        op: &str,
+        params: &[(&str, &str)],
+        data: R,
+    ) -> Result<(), Error> {
+        match self.flavor {
+            Flavor::HttpFs => {
+                let mut params = params.to_vec();
+                params.push(("data", "true"));
+                let url = self.url(path, op, &params)?;
+                let request = self.request(method, &url).set("Content-Type", OCTET_STREAM);
+                let response = check(request.send(data))?;
+                // Without data=true HttpFS answers with a redirect to itself, by then the
+                // body is already gone so there is nothing sensible to retry.
+                if redirect_location(&response).is_some() {
+                    return Err(Error::Http(format!("{} {} was redirected", op, url)));
+                }
+                Ok(())
+            }
+            Flavor::NameNode => {
+                let url = self.url(path, op, params)?;
+                let response = check(self.request(method, &url).call())?;
+                let location = redirect_location(&response).ok_or_else(|| {
+                    Error::Http(format!("{} {} did not redirect to a datanode", op, url))
+                })?;
+                debug!("{} redirected to {}", op, location);
+                self.upload_to(method, &location, data)
+            }
+        }
+    }
+
+    fn upload_to<R: Read>(&self, method: &str, location: &str, data: R) -> Result<(), Error> {
+        let request = self
+            .request(method, location)
+            .set("Content-Type", OCTET_STREAM);
+        check(request.send(data)).map(|_| ())
+    }
+
+    fn absolute(&self, path: &Path) -> Result<PathBuf, Error> {
+        if path.is_absolute() {
+            Ok(path.to_owned())
+        } else {
+            Ok(self.current_dir()?.join(path))
+        }
+    }
+
+    fn url(&self, path: &Path, op: &str, params: &[(&str, &str)]) -> Result<String, Error> {
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| Error::InvalidPath(path.to_owned()))?;
+        let mut url = format!(
+            "{}{}{}?op={}",
+            self.options.url.trim_end_matches('/'),
+            WEBHDFS_PREFIX,
+            encode(path_str, true),
+            op
+        );
+        if let Some(ref token) = self.options.delegation {
+            push_param(&mut url, "delegation", token);
+        } else if let Some(ref user) = self.options.user {
+            push_param(&mut url, "user.name", user);
+        }
+        for (key, value) in params {
+            push_param(&mut url, key, value);
+        }
+        Ok(url)
+    }
+
+    /// Datanode locations returned by the namenode are absolute and already carry
+    /// the credentials, HttpFS may return a location relative to the gateway.
+    fn resolve_location(&self, location: &str) -> String {
+        if location.starts_with('/') {
+            format!("{}{}", self.options.url.trim_end_matches('/'), location)
+        } else {
+            location.to_owned()
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        debug!("{} {}", method, url);
+        self.options
+            .headers
+            .iter()
+            .fold(self.agent.request(method, url), |request, (key, value)| {
+                request.set(key, value)
+            })
+    }
+
+    fn json<T>(&self, result: Result<ureq::Response, ureq::Error>) -> Result<T, Error>
+    where
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        let body = check(result)?.into_string()?;
+        serde_json::from_str(&body).map_err(|e| Error::Http(format!("{}: {}", e, body)))
+    }
+}
+
+fn check(result: Result<ureq::Response, ureq::Error>) -> Result<ureq::Response, Error> {
+    match result {
+        Ok(response) => Ok(response),
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            match serde_json::from_str::<RemoteExceptionResponse>(&body) {
+                Ok(RemoteExceptionResponse { remote_exception }) => Err(Error::remote(
+                    &remote_exception.exception,
+                    remote_exception.message,
+                )),
+                Err(_) => Err(Error::Http(format!("HTTP {}: {}", code, body))),
+            }
+        }
+        Err(ureq::Error::Transport(transport)) => Err(Error::Http(transport.to_string())),
+    }
+}
+
+fn redirect_location(response: &ureq::Response) -> Option<String> {
+    match response.status() {
+        301 | 302 | 303 | 307 | 308 => response.header("Location").map(|l| l.to_owned()),
+        _ => None,
+    }
+}
+
+fn push_param(url: &mut String, key: &str, value: &str) {
+    url.push('&');
+    url.push_str(&encode(key, false));
+    url.push('=');
+    url.push_str(&encode(value, false));
+}
+
+fn encode(s: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Files created are uploaded when flushed, see `Writer`.
+impl fs::FileSystem for WebHdfs {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        WebHdfs::current_dir(self)
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        self.path_info(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        self.list_directory(path)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(Box::new(WebHdfs::open(self, path)?))
+    }
+
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        Ok(Box::new(self.writer(path, overwrite)?))
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        WebHdfs::delete(self, path, recursive)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        if WebHdfs::rename(self, from, to)? {
+            Ok(())
+        } else {
+            Err(Error::RemoteException(
+                String::from("IOException"),
+                format!("Cannot rename {} to {}", from.display(), to.display()),
+            ))
+        }
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        WebHdfs::mkdirs(self, path)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        WebHdfs::set_permission(self, path, permission)
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        WebHdfs::set_owner(self, path, owner, group)
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error> {
+        if WebHdfs::set_replication(self, path, replication)? {
+            Ok(())
+        } else {
+            Err(Error::RemoteException(
+                String::from("IOException"),
+                format!("Cannot set the replication of {}", path.display()),
+            ))
+        }
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        WebHdfs::set_times(self, path, modification_time, access_time)
+    }
+
+    fn content_summary(&self, path: &Path) -> Result<ContentSummary, Error> {
+        WebHdfs::content_summary(self, path)
+    }
+
+    fn set_namespace_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), Error> {
+        WebHdfs::set_quota(self, path, "namespacequota", quota)
+    }
+
+    fn set_space_quota(&self, path: &Path, quota: Option<u64>) -> Result<(), Error> {
+        WebHdfs::set_quota(self, path, "storagespacequota", quota)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use webhdfs::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!("/a%20b/c%3Dd", encode("/a b/c=d", true));
+        assert_eq!("%2Fa", encode("/a", false));
+    }
+
+    #[test]
+    fn test_url_auth() {
+        let mut options = WebHdfsOptions {
+            url: String::from("http://gw:14000/"),
+            user: Some(String::from("etl")),
+            ..Default::default()
+        };
+        let fs = get_httpfs(options.clone());
+        assert_eq!(
+            "http://gw:14000/webhdfs/v1/tmp?op=LISTSTATUS&user.name=etl",
+            fs.url(Path::new("/tmp"), "LISTSTATUS", &[]).unwrap()
+        );
+
+        options.delegation = Some(String::from("token"));
+        let fs = get_httpfs(options);
+        assert_eq!(
+            "http://gw:14000/webhdfs/v1/tmp?op=OPEN&delegation=token&offset=0",
+            fs.url(Path::new("/tmp"), "OPEN", &[("offset", "0")])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_content_summary() {
+        let body = r#"{"ContentSummary": {"directoryCount": 2, "fileCount": 3, "length": 24930,
+            "quota": -1, "spaceConsumed": 74790, "spaceQuota": 1073741824}}"#;
+        let response: ContentSummaryResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            ContentSummary {
+                length: 24930,
+                file_count: 3,
+                directory_count: 2,
+                space_consumed: 74790,
+                has_quotas: true,
+                quota: None,
+                space_quota: Some(1 << 30),
+            },
+            response.content_summary.into_content_summary()
+        );
+    }
+}
@@ -0,0 +1,369 @@
+extern crate hdfs;
+extern crate tokio;
+
+use hdfs::async_hdfs::{AsyncHdfs, Backend, BlockingPool};
+use hdfs::err::Error;
+use hdfs::fs::{FileSystem, MemoryFile, MemoryFileSystem};
+use hdfs::hdfs::FileStatus;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, ReadBuf};
+use tokio::runtime::{Builder, Runtime};
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().build().unwrap()
+}
+
+fn content() -> Vec<u8> {
+    (0..100_000).map(|i| (i % 251) as u8).collect()
+}
+
+fn memory_fs() -> AsyncHdfs<MemoryFileSystem> {
+    let fs = MemoryFileSystem::default();
+    fs.add_file("/data/file", &content()).unwrap();
+    AsyncHdfs::new(fs, 2)
+}
+
+/// A memory file system logging when it and its files are dropped, its reads taking a
+/// while so that a file can be dropped while the pool still uses it.
+struct Tracked {
+    fs: MemoryFileSystem,
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+struct TrackedFile {
+    file: MemoryFile,
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.log.lock().unwrap().push("fs");
+    }
+}
+
+impl Drop for TrackedFile {
+    fn drop(&mut self) {
+        self.log.lock().unwrap().push("file");
+    }
+}
+
+impl Read for TrackedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        thread::sleep(Duration::from_millis(50));
+        self.file.read(buf)
+    }
+}
+
+impl Write for TrackedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for TrackedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl FileSystem for Tracked {
+    fn current_dir(&self) -> Result<PathBuf, Error> {
+        self.fs.current_dir()
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStatus, Error> {
+        self.fs.stat(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<FileStatus>, Error> {
+        self.fs.list(path)
+    }
+
+    fn open<'a>(&'a self, path: &Path) -> Result<Box<dyn Read + 'a>, Error> {
+        self.fs.open(path)
+    }
+
+    fn create<'a>(&'a self, path: &Path, overwrite: bool) -> Result<Box<dyn Write + 'a>, Error> {
+        self.fs.create(path, overwrite)
+    }
+
+    fn delete(&self, path: &Path, recursive: bool) -> Result<bool, Error> {
+        self.fs.delete(path, recursive)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.fs.rename(from, to)
+    }
+
+    fn mkdirs(&self, path: &Path) -> Result<bool, Error> {
+        self.fs.mkdirs(path)
+    }
+
+    fn set_permission(&self, path: &Path, permission: u16) -> Result<(), Error> {
+        self.fs.set_permission(path, permission)
+    }
+
+    fn set_owner(
+        &self,
+        path: &Path,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<(), Error> {
+        self.fs.set_owner(path, owner, group)
+    }
+
+    fn set_replication(&self, path: &Path, replication: u16) -> Result<(), Error> {
+        self.fs.set_replication(path, replication)
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        modification_time: Option<u64>,
+        access_time: Option<u64>,
+    ) -> Result<(), Error> {
+        self.fs.set_times(path, modification_time, access_time)
+    }
+}
+
+impl Backend for Tracked {
+    type File = TrackedFile;
+
+    fn open_file(&self, path: &Path) -> Result<TrackedFile, Error> {
+        Ok(TrackedFile {
+            file: self.fs.open_file(path)?,
+            log: self.log.clone(),
+        })
+    }
+
+    fn create_file(&self, path: &Path, overwrite: bool) -> Result<TrackedFile, Error> {
+        Ok(TrackedFile {
+            file: self.fs.create_file(path, overwrite)?,
+            log: self.log.clone(),
+        })
+    }
+}
+
+fn tracked_fs() -> (AsyncHdfs<Tracked>, Arc<Mutex<Vec<&'static str>>>) {
+    let fs = MemoryFileSystem::default();
+    fs.add_file("/data/file", &content()).unwrap();
+    let log = Arc::new(Mutex::new(vec![]));
+    let fs = Tracked {
+        fs,
+        log: log.clone(),
+    };
+    (AsyncHdfs::new(fs, 1), log)
+}
+
+#[test]
+fn test_metadata() {
+    let rt = runtime();
+    let fs = memory_fs();
+
+    assert_eq!(
+        PathBuf::from("/user/hdfs"),
+        rt.block_on(fs.current_dir()).unwrap()
+    );
+    assert!(rt.block_on(fs.mkdirs("/data/dir")).unwrap());
+    assert!(rt.block_on(fs.stat("/data/dir")).unwrap().is_dir());
+    rt.block_on(fs.rename("/data/dir", "/data/other")).unwrap();
+    rt.block_on(fs.set_permission("/data/file", 0o600)).unwrap();
+    assert_eq!(
+        0o600,
+        rt.block_on(fs.stat("/data/file")).unwrap().permission
+    );
+
+    let mut names: Vec<_> = rt
+        .block_on(fs.list("/data"))
+        .unwrap()
+        .into_iter()
+        .map(|s| s.path)
+        .collect();
+    names.sort();
+    assert_eq!(
+        vec![PathBuf::from("/data/file"), PathBuf::from("/data/other")],
+        names
+    );
+
+    assert!(rt.block_on(fs.delete("/data/other", false)).unwrap());
+    assert!(!rt.block_on(fs.exists("/data/other")).unwrap());
+    match rt.block_on(fs.stat("/data/missing")) {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_write_and_read() {
+    let rt = runtime();
+    let fs = memory_fs();
+    let data = content();
+
+    let mut file = rt.block_on(fs.create("/data/copy", false)).unwrap();
+    rt.block_on(file.write_all(&data)).unwrap();
+    rt.block_on(file.shutdown()).unwrap();
+    drop(file);
+
+    let mut file = rt.block_on(fs.open("/data/copy")).unwrap();
+    let mut read = vec![];
+    rt.block_on(file.read_to_end(&mut read)).unwrap();
+    assert_eq!(data, read);
+
+    assert!(rt.block_on(fs.create("/data/copy", false)).is_err());
+}
+
+#[test]
+fn test_write_errors_surface_on_flush() {
+    let rt = runtime();
+    let fs = memory_fs();
+
+    // Opened for reading, the write itself is accepted before failing on the pool
+    let mut file = rt.block_on(fs.open("/data/file")).unwrap();
+    assert_eq!(4, rt.block_on(file.write(b"data")).unwrap());
+    assert!(rt.block_on(file.flush()).is_err());
+}
+
+#[test]
+fn test_seek() {
+    let rt = runtime();
+    let fs = memory_fs();
+    let data = content();
+    let mut file = rt.block_on(fs.open("/data/file")).unwrap();
+
+    assert_eq!(1000, rt.block_on(file.seek(SeekFrom::Start(1000))).unwrap());
+    let mut buf = [0; 100];
+    rt.block_on(file.read_exact(&mut buf)).unwrap();
+    assert_eq!(&data[1000..1100], &buf[..]);
+
+    assert_eq!(
+        1050,
+        rt.block_on(file.seek(SeekFrom::Current(-50))).unwrap()
+    );
+    assert_eq!(1050, rt.block_on(file.stream_position()).unwrap());
+    assert_eq!(
+        data.len() as u64 - 10,
+        rt.block_on(file.seek(SeekFrom::End(-10))).unwrap()
+    );
+    let mut rest = vec![];
+    rt.block_on(file.read_to_end(&mut rest)).unwrap();
+    assert_eq!(&data[data.len() - 10..], rest.as_slice());
+}
+
+#[test]
+fn test_abandoned_read_keeps_data() {
+    let rt = runtime();
+    let fs = memory_fs();
+    let data = content();
+    let mut file = rt.block_on(fs.open("/data/file")).unwrap();
+
+    // Poll a read once and give up on it, like a timeout or a select would
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut buf = [0; 4096];
+    let mut read_buf = ReadBuf::new(&mut buf);
+    let mut read = vec![];
+    if let Poll::Ready(result) = Pin::new(&mut file).poll_read(&mut cx, &mut read_buf) {
+        result.unwrap();
+        read.extend_from_slice(read_buf.filled());
+    }
+
+    rt.block_on(file.read_to_end(&mut read)).unwrap();
+    assert_eq!(data, read);
+
+    // Seeking after an abandoned read accounts for the data nobody got
+    let mut file = rt.block_on(fs.open("/data/file")).unwrap();
+    let mut read_buf = ReadBuf::new(&mut buf);
+    let returned = match Pin::new(&mut file).poll_read(&mut cx, &mut read_buf) {
+        Poll::Ready(result) => result.map(|_| read_buf.filled().len()).unwrap(),
+        Poll::Pending => 0,
+    };
+    assert_eq!(
+        returned as u64,
+        rt.block_on(file.stream_position()).unwrap()
+    );
+}
+
+#[test]
+fn test_dropped_call_still_completes() {
+    let rt = runtime();
+    let fs = AsyncHdfs::new(MemoryFileSystem::default(), 1);
+
+    drop(fs.mkdirs("/dropped"));
+
+    // A single thread runs the calls in order, the dropped one came first
+    assert!(rt.block_on(fs.exists("/dropped")).unwrap());
+    assert_eq!(1, fs.inner().list(Path::new("/")).unwrap().len());
+}
+
+#[test]
+fn test_pool_is_bounded() {
+    let rt = runtime();
+    let pool = BlockingPool::new(2);
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let receivers: Vec<_> = (0..8)
+        .map(|i| {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            pool.spawn(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                i
+            })
+        })
+        .collect();
+
+    for (i, receiver) in receivers.into_iter().enumerate() {
+        assert_eq!(i, rt.block_on(receiver).unwrap());
+    }
+    assert_eq!(2, max_running.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_file_closed_before_fs() {
+    let rt = runtime();
+    let (fs, log) = tracked_fs();
+    let file = rt.block_on(fs.open("/data/file")).unwrap();
+    drop(fs);
+    assert!(log.lock().unwrap().is_empty());
+    drop(file);
+    assert_eq!(vec!["file", "fs"], *log.lock().unwrap());
+}
+
+#[test]
+fn test_busy_file_closed_before_fs() {
+    let rt = runtime();
+    let (fs, log) = tracked_fs();
+    let mut file = rt.block_on(fs.open("/data/file")).unwrap();
+
+    // Drop both while the pool is still reading from the file
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut buf = [0; 4096];
+    let mut read_buf = ReadBuf::new(&mut buf);
+    assert!(Pin::new(&mut file)
+        .poll_read(&mut cx, &mut read_buf)
+        .is_pending());
+    drop(file);
+    drop(fs);
+
+    for _ in 0..100 {
+        if log.lock().unwrap().len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(vec!["file", "fs"], *log.lock().unwrap());
+}
@@ -0,0 +1,767 @@
+#![allow(dead_code)]
+//! Fake namenode and datanodes speaking the Hadoop wire protocols over local sockets.
+use hdfs::rpc::proto::*;
+use hdfs::rpc::{NameNodeClient, CLIENT_PROTOCOL, QUOTA_DONT_SET};
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct Entry {
+    pub is_dir: bool,
+    pub length: u64,
+    pub blocks: Vec<Block>,
+    pub attributes: Attributes,
+}
+
+/// Changed by the attribute calls, `status` has defaults for the others.
+#[derive(Clone, Default)]
+pub struct Attributes {
+    pub permission: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub replication: Option<u32>,
+    pub modification_time: Option<u64>,
+    pub access_time: Option<u64>,
+}
+
+/// A block of a file, stored on the datanodes at the given addresses.
+#[derive(Clone)]
+pub struct Block {
+    pub id: u64,
+    pub len: u64,
+    pub datanodes: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Namespace {
+    pub entries: BTreeMap<String, Entry>,
+    /// Namespace and space quotas by directory.
+    pub quotas: HashMap<String, (u64, u64)>,
+    pub standby: bool,
+    pub page_size: usize,
+    pub users: Vec<String>,
+    pub connections: usize,
+    pub calls: Vec<String>,
+}
+
+type RpcResult = Result<Vec<u8>, (String, String)>;
+
+/// In-process namenode speaking the Hadoop IPC framing over a local socket.
+pub struct FakeNameNode {
+    pub address: String,
+    pub namespace: Arc<Mutex<Namespace>>,
+}
+
+impl FakeNameNode {
+    pub fn start() -> FakeNameNode {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut namespace = Namespace {
+            page_size: 1000,
+            ..Default::default()
+        };
+        namespace.entries.insert(
+            String::from("/"),
+            Entry {
+                is_dir: true,
+                length: 0,
+                blocks: vec![],
+                attributes: Attributes::default(),
+            },
+        );
+        let namespace = Arc::new(Mutex::new(namespace));
+        let shared = namespace.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let namespace = shared.clone();
+                thread::spawn(move || serve(stream, namespace));
+            }
+        });
+        FakeNameNode { address, namespace }
+    }
+
+    pub fn standby() -> FakeNameNode {
+        let namenode = FakeNameNode::start();
+        namenode.namespace.lock().unwrap().standby = true;
+        namenode
+    }
+
+    pub fn dir(self, path: &str) -> FakeNameNode {
+        self.insert(path, true, 0)
+    }
+
+    pub fn file(self, path: &str, length: u64) -> FakeNameNode {
+        self.insert(path, false, length)
+    }
+
+    /// Adds a file made of the given blocks, its length is the sum of theirs.
+    pub fn blocks(self, path: &str, blocks: Vec<Block>) -> FakeNameNode {
+        let length = blocks.iter().map(|b| b.len).sum();
+        self.namespace.lock().unwrap().entries.insert(
+            path.to_owned(),
+            Entry {
+                is_dir: false,
+                length,
+                blocks,
+                attributes: Attributes::default(),
+            },
+        );
+        self
+    }
+
+    fn insert(self, path: &str, is_dir: bool, length: u64) -> FakeNameNode {
+        self.namespace.lock().unwrap().entries.insert(
+            path.to_owned(),
+            Entry {
+                is_dir,
+                length,
+                blocks: vec![],
+                attributes: Attributes::default(),
+            },
+        );
+        self
+    }
+
+    pub fn client(&self) -> NameNodeClient {
+        NameNodeClient::new(vec![self.address.to_owned()], "etl")
+    }
+}
+
+fn read_frame(reader: &mut dyn Read) -> Option<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length).ok()?;
+    let mut frame = vec![0u8; u32::from_be_bytes(length) as usize];
+    reader.read_exact(&mut frame).ok()?;
+    Some(frame)
+}
+
+fn serve(stream: TcpStream, namespace: Arc<Mutex<Namespace>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+
+    let mut connection_header = [0u8; 7];
+    reader.read_exact(&mut connection_header).unwrap();
+    assert_eq!(b"hrpc\x09\x00\x00", &connection_header);
+
+    let frame = read_frame(&mut reader).unwrap();
+    let mut buf = frame.as_slice();
+    let header = RpcRequestHeaderProto::decode_length_delimited(&mut buf).unwrap();
+    assert_eq!(-3, header.call_id);
+    assert_eq!(16, header.client_id.len());
+    let context = IpcConnectionContextProto::decode_length_delimited(&mut buf).unwrap();
+    assert_eq!(Some(CLIENT_PROTOCOL), context.protocol.as_deref());
+    {
+        let mut namespace = namespace.lock().unwrap();
+        namespace.connections += 1;
+        namespace
+            .users
+            .push(context.user_info.unwrap().effective_user.unwrap());
+    }
+
+    while let Some(frame) = read_frame(&mut reader) {
+        let mut buf = frame.as_slice();
+        let header = RpcRequestHeaderProto::decode_length_delimited(&mut buf).unwrap();
+        let method = RequestHeaderProto::decode_length_delimited(&mut buf).unwrap();
+        assert_eq!(CLIENT_PROTOCOL, method.declaring_class_protocol_name);
+
+        let result = {
+            let mut namespace = namespace.lock().unwrap();
+            namespace.calls.push(method.method_name.to_owned());
+            if namespace.standby {
+                Err((
+                    String::from("org.apache.hadoop.ipc.StandbyException"),
+                    String::from("Operation category READ is not supported in state standby"),
+                ))
+            } else {
+                dispatch(&mut namespace, &method.method_name, buf)
+            }
+        };
+
+        let mut response_header = RpcResponseHeaderProto {
+            call_id: header.call_id as u32,
+            status: RpcStatusProto::Success as i32,
+            server_ipc_version_num: Some(9),
+            exception_class_name: None,
+            error_msg: None,
+            client_id: Some(header.client_id),
+            retry_count: None,
+        };
+        let mut response = vec![];
+        match result {
+            Ok(body) => {
+                response_header
+                    .encode_length_delimited(&mut response)
+                    .unwrap();
+                response.extend(body);
+            }
+            Err((class, message)) => {
+                response_header.status = RpcStatusProto::Error as i32;
+                response_header.exception_class_name = Some(class);
+                response_header.error_msg = Some(message);
+                response_header
+                    .encode_length_delimited(&mut response)
+                    .unwrap();
+            }
+        }
+        writer
+            .write_all(&(response.len() as u32).to_be_bytes())
+            .unwrap();
+        writer.write_all(&response).unwrap();
+    }
+}
+
+fn status(name: &str, entry: &Entry) -> HdfsFileStatusProto {
+    let attributes = &entry.attributes;
+    HdfsFileStatusProto {
+        file_type: if entry.is_dir {
+            FileType::IsDir as i32
+        } else {
+            FileType::IsFile as i32
+        },
+        path: name.as_bytes().to_vec(),
+        length: entry.length,
+        permission: FsPermissionProto {
+            perm: attributes
+                .permission
+                .unwrap_or(if entry.is_dir { 0o755 } else { 0o644 }),
+        },
+        owner: attributes
+            .owner
+            .clone()
+            .unwrap_or_else(|| String::from("etl")),
+        group: attributes
+            .group
+            .clone()
+            .unwrap_or_else(|| String::from("hadoop")),
+        modification_time: attributes.modification_time.unwrap_or(1_500_000_000_000),
+        access_time: attributes.access_time.unwrap_or(1_500_000_000_000),
+        symlink: None,
+        block_replication: Some(if entry.is_dir {
+            0
+        } else {
+            attributes.replication.unwrap_or(3)
+        }),
+        blocksize: Some(if entry.is_dir { 0 } else { 134_217_728 }),
+        file_id: None,
+        children_num: None,
+    }
+}
+
+fn parent(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) => "/",
+        Some(i) => &path[..i],
+        None => "",
+    }
+}
+
+fn not_found(path: &str) -> (String, String) {
+    (
+        String::from("java.io.FileNotFoundException"),
+        format!("File does not exist: {}", path),
+    )
+}
+
+fn attributes<'a>(
+    namespace: &'a mut Namespace,
+    path: &str,
+) -> Result<&'a mut Attributes, (String, String)> {
+    namespace
+        .entries
+        .get_mut(path)
+        .map(|entry| &mut entry.attributes)
+        .ok_or_else(|| not_found(path))
+}
+
+fn dispatch(namespace: &mut Namespace, method: &str, mut buf: &[u8]) -> RpcResult {
+    let mut response = vec![];
+    match method {
+        "getFileInfo" => {
+            let request = GetFileInfoRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let fs = namespace
+                .entries
+                .get(&request.src)
+                .map(|entry| status("", entry));
+            GetFileInfoResponseProto { fs }
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "getListing" => {
+            let request = GetListingRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let dir_list = match namespace.entries.get(&request.src) {
+                None => None,
+                Some(entry) if !entry.is_dir => Some(DirectoryListingProto {
+                    partial_listing: vec![status("", entry)],
+                    remaining_entries: 0,
+                }),
+                Some(_) => {
+                    let start_after = String::from_utf8(request.start_after.clone()).unwrap();
+                    let children: Vec<_> = namespace
+                        .entries
+                        .iter()
+                        .filter(|(path, _)| path.as_str() != "/" && parent(path) == request.src)
+                        .map(|(path, entry)| (path.rsplit('/').next().unwrap(), entry))
+                        .filter(|(name, _)| *name > start_after.as_str())
+                        .collect();
+                    let page = children.len().min(namespace.page_size);
+                    Some(DirectoryListingProto {
+                        partial_listing: children[..page]
+                            .iter()
+                            .map(|(name, entry)| status(name, entry))
+                            .collect(),
+                        remaining_entries: (children.len() - page) as u32,
+                    })
+                }
+            };
+            GetListingResponseProto { dir_list }
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "mkdirs" => {
+            let request = MkdirsRequestProto::decode_length_delimited(&mut buf).unwrap();
+            assert!(request.create_parent);
+            let mut path = request.src.as_str();
+            while path != "/" {
+                if let Some(entry) = namespace.entries.get(path) {
+                    if !entry.is_dir {
+                        return Err((
+                            String::from("org.apache.hadoop.fs.ParentNotDirectoryException"),
+                            path.to_owned(),
+                        ));
+                    }
+                }
+                namespace.entries.insert(
+                    path.to_owned(),
+                    Entry {
+                        is_dir: true,
+                        length: 0,
+                        blocks: vec![],
+                        attributes: Attributes::default(),
+                    },
+                );
+                path = parent(path);
+            }
+            MkdirsResponseProto { result: true }
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "delete" => {
+            let request = DeleteRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let prefix = format!("{}/", request.src);
+            let children = namespace
+                .entries
+                .keys()
+                .filter(|path| path.starts_with(&prefix))
+                .count();
+            let result = if !namespace.entries.contains_key(&request.src) {
+                false
+            } else if children > 0 && !request.recursive {
+                return Err((
+                    String::from("org.apache.hadoop.fs.PathIsNotEmptyDirectoryException"),
+                    format!("{} is non empty", request.src),
+                ));
+            } else {
+                namespace
+                    .entries
+                    .retain(|path, _| path != &request.src && !path.starts_with(&prefix));
+                true
+            };
+            DeleteResponseProto { result }
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "rename2" => {
+            let request = Rename2RequestProto::decode_length_delimited(&mut buf).unwrap();
+            if !namespace.entries.contains_key(&request.src) {
+                return Err(not_found(&request.src));
+            }
+            if namespace.entries.contains_key(&request.dst) && !request.overwrite_dest {
+                return Err((
+                    String::from("org.apache.hadoop.fs.FileAlreadyExistsException"),
+                    request.dst,
+                ));
+            }
+            let prefix = format!("{}/", request.src);
+            let moved: Vec<_> = namespace
+                .entries
+                .iter()
+                .filter(|(path, _)| **path == request.src || path.starts_with(&prefix))
+                .map(|(path, entry)| (path.to_owned(), entry.clone()))
+                .collect();
+            for (path, entry) in moved {
+                namespace.entries.remove(&path);
+                let new_path = format!("{}{}", request.dst, &path[request.src.len()..]);
+                namespace.entries.insert(new_path, entry);
+            }
+            Rename2ResponseProto {}
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "getContentSummary" => {
+            let request = GetContentSummaryRequestProto::decode_length_delimited(&mut buf).unwrap();
+            if !namespace.entries.contains_key(&request.path) {
+                return Err(not_found(&request.path));
+            }
+            let prefix = format!("{}/", request.path.trim_end_matches('/'));
+            let (quota, space_quota) = namespace
+                .quotas
+                .get(&request.path)
+                .cloned()
+                .unwrap_or((u64::MAX, u64::MAX));
+            let mut summary = ContentSummaryProto {
+                quota,
+                space_quota,
+                ..Default::default()
+            };
+            for (path, entry) in &namespace.entries {
+                if *path != request.path && !path.starts_with(&prefix) {
+                    continue;
+                }
+                if entry.is_dir {
+                    summary.directory_count += 1;
+                } else {
+                    summary.file_count += 1;
+                    summary.length += entry.length;
+                    summary.space_consumed += entry.length * 3;
+                }
+            }
+            GetContentSummaryResponseProto { summary }
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "setQuota" => {
+            let request = SetQuotaRequestProto::decode_length_delimited(&mut buf).unwrap();
+            if !namespace.entries.contains_key(&request.path) {
+                return Err(not_found(&request.path));
+            }
+            let quotas = namespace
+                .quotas
+                .entry(request.path)
+                .or_insert((u64::MAX, u64::MAX));
+            if request.namespace_quota != QUOTA_DONT_SET {
+                quotas.0 = request.namespace_quota;
+            }
+            if request.storagespace_quota != QUOTA_DONT_SET {
+                quotas.1 = request.storagespace_quota;
+            }
+            SetQuotaResponseProto {}
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "setPermission" => {
+            let request = SetPermissionRequestProto::decode_length_delimited(&mut buf).unwrap();
+            attributes(namespace, &request.src)?.permission = Some(request.permission.perm);
+            SetPermissionResponseProto {}
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "setOwner" => {
+            let request = SetOwnerRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let attributes = attributes(namespace, &request.src)?;
+            if request.username.is_some() {
+                attributes.owner = request.username;
+            }
+            if request.groupname.is_some() {
+                attributes.group = request.groupname;
+            }
+            SetOwnerResponseProto {}
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "setReplication" => {
+            let request = SetReplicationRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let is_file = !namespace
+                .entries
+                .get(&request.src)
+                .ok_or_else(|| not_found(&request.src))?
+                .is_dir;
+            if is_file {
+                attributes(namespace, &request.src)?.replication = Some(request.replication);
+            }
+            SetReplicationResponseProto { result: is_file }
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "setTimes" => {
+            let request = SetTimesRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let attributes = attributes(namespace, &request.src)?;
+            if request.mtime != u64::MAX {
+                attributes.modification_time = Some(request.mtime);
+            }
+            if request.atime != u64::MAX {
+                attributes.access_time = Some(request.atime);
+            }
+            SetTimesResponseProto {}
+                .encode_length_delimited(&mut response)
+                .unwrap();
+        }
+        "getBlockLocations" => {
+            let request = GetBlockLocationsRequestProto::decode_length_delimited(&mut buf).unwrap();
+            let locations = match namespace.entries.get(&request.src) {
+                None => return Err(not_found(&request.src)),
+                Some(entry) if entry.is_dir => {
+                    return Err((
+                        String::from("java.io.FileNotFoundException"),
+                        format!("Path is not a file: {}", request.src),
+                    ))
+                }
+                Some(entry) => located_blocks(entry, request.offset, request.length),
+            };
+            GetBlockLocationsResponseProto {
+                locations: Some(locations),
+            }
+            .encode_length_delimited(&mut response)
+            .unwrap();
+        }
+        _ => {
+            return Err((
+                String::from("org.apache.hadoop.ipc.RpcNoSuchMethodException"),
+                method.to_owned(),
+            ))
+        }
+    }
+    Ok(response)
+}
+
+fn located_blocks(entry: &Entry, offset: u64, length: u64) -> LocatedBlocksProto {
+    let mut blocks = vec![];
+    let mut block_offset = 0;
+    for block in &entry.blocks {
+        let end = block_offset + block.len;
+        if end > offset && block_offset < offset.saturating_add(length) {
+            blocks.push(LocatedBlockProto {
+                b: ExtendedBlockProto {
+                    pool_id: String::from("BP-1"),
+                    block_id: block.id,
+                    generation_stamp: 1001,
+                    num_bytes: Some(block.len),
+                },
+                offset: block_offset,
+                locs: block
+                    .datanodes
+                    .iter()
+                    .map(|address| {
+                        let (ip, port) = address.split_at(address.rfind(':').unwrap());
+                        DatanodeInfoProto {
+                            id: DatanodeIdProto {
+                                ip_addr: ip.to_owned(),
+                                host_name: String::from("localhost"),
+                                datanode_uuid: address.to_owned(),
+                                xfer_port: port[1..].parse().unwrap(),
+                                info_port: 0,
+                                ipc_port: 0,
+                            },
+                        }
+                    })
+                    .collect(),
+                corrupt: false,
+                block_token: TokenProto::default(),
+            });
+        }
+        block_offset = end;
+    }
+    LocatedBlocksProto {
+        file_length: entry.length,
+        blocks,
+        under_construction: false,
+        last_block: None,
+        is_last_block_complete: true,
+    }
+}
+
+pub const BYTES_PER_CHECKSUM: usize = 512;
+const CHUNKS_PER_PACKET: usize = 4;
+
+#[derive(Default)]
+pub struct Storage {
+    pub blocks: HashMap<u64, Vec<u8>>,
+    /// Blocks sent with a flipped byte
+    pub corrupted: Vec<u64>,
+    /// (block id, offset, len) of every OpReadBlock received
+    pub reads: Vec<(u64, u64, u64)>,
+    pub checksum_ok: usize,
+}
+
+/// In-process datanode answering `OpReadBlock` with CRC32C checksummed packets.
+pub struct FakeDataNode {
+    pub address: String,
+    pub storage: Arc<Mutex<Storage>>,
+}
+
+impl FakeDataNode {
+    pub fn start() -> FakeDataNode {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let storage = Arc::new(Mutex::new(Storage::default()));
+        let shared = storage.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let storage = shared.clone();
+                thread::spawn(move || serve_block(stream, storage));
+            }
+        });
+        FakeDataNode { address, storage }
+    }
+
+    pub fn block(self, id: u64, data: &[u8]) -> FakeDataNode {
+        self.storage
+            .lock()
+            .unwrap()
+            .blocks
+            .insert(id, data.to_vec());
+        self
+    }
+
+    pub fn corrupt(self, id: u64) -> FakeDataNode {
+        self.storage.lock().unwrap().corrupted.push(id);
+        self
+    }
+
+    pub fn reads(&self) -> Vec<(u64, u64, u64)> {
+        self.storage.lock().unwrap().reads.to_owned()
+    }
+
+    /// Waits a bit for the client acknowledgements, they are sent asynchronously.
+    pub fn checksum_ok(&self, expected: usize) -> usize {
+        for _ in 0..100 {
+            let count = self.storage.lock().unwrap().checksum_ok;
+            if count >= expected {
+                return count;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.storage.lock().unwrap().checksum_ok
+    }
+}
+
+/// An address nothing listens on.
+pub fn unreachable_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn read_delimited<M: Message + Default>(reader: &mut dyn Read) -> Option<M> {
+    let mut length = 0usize;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte).ok()?;
+        length |= ((byte[0] & 0x7f) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let mut buf = vec![0u8; length];
+    reader.read_exact(&mut buf).ok()?;
+    M::decode(buf.as_slice()).ok()
+}
+
+fn write_packet(writer: &mut dyn Write, offset: u64, seqno: i64, data: &[u8], sent: &[u8]) {
+    let last = data.is_empty();
+    let header = PacketHeaderProto {
+        offset_in_block: offset as i64,
+        seqno,
+        last_packet_in_block: last,
+        data_len: data.len() as i32,
+        sync_block: None,
+    }
+    .encode_to_vec();
+    let mut checksums = vec![];
+    for chunk in data.chunks(BYTES_PER_CHECKSUM) {
+        checksums.extend_from_slice(&crc32c::crc32c(chunk).to_be_bytes());
+    }
+    let packet_length = (4 + checksums.len() + data.len()) as u32;
+    let mut buf = packet_length.to_be_bytes().to_vec();
+    buf.extend_from_slice(&(header.len() as u16).to_be_bytes());
+    buf.extend(header);
+    buf.extend(checksums);
+    buf.extend_from_slice(sent);
+    writer.write_all(&buf).unwrap();
+}
+
+fn serve_block(stream: TcpStream, storage: Arc<Mutex<Storage>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+
+    let mut op = [0u8; 3];
+    reader.read_exact(&mut op).unwrap();
+    assert_eq!([0, 28, 81], op);
+    let request: OpReadBlockProto = read_delimited(&mut reader).unwrap();
+    let id = request.header.base_header.block.block_id;
+
+    let (data, corrupted) = {
+        let mut storage = storage.lock().unwrap();
+        storage.reads.push((id, request.offset, request.len));
+        (
+            storage.blocks.get(&id).cloned(),
+            storage.corrupted.contains(&id),
+        )
+    };
+    let data = match data {
+        Some(data) => data,
+        None => {
+            let response = BlockOpResponseProto {
+                status: Status::Error as i32,
+                first_bad_link: None,
+                read_op_checksum_info: None,
+                message: Some(format!("Replica not found for block {}", id)),
+            };
+            writer
+                .write_all(&response.encode_length_delimited_to_vec())
+                .unwrap();
+            return;
+        }
+    };
+
+    // Like a real datanode, start on a chunk boundary
+    let chunk_offset = request.offset - request.offset % BYTES_PER_CHECKSUM as u64;
+    let end = std::cmp::min(request.offset + request.len, data.len() as u64) as usize;
+    let response = BlockOpResponseProto {
+        status: Status::Success as i32,
+        first_bad_link: None,
+        read_op_checksum_info: Some(ReadOpChecksumInfoProto {
+            checksum: ChecksumProto {
+                checksum_type: ChecksumTypeProto::ChecksumCrc32c as i32,
+                bytes_per_checksum: BYTES_PER_CHECKSUM as u32,
+            },
+            chunk_offset,
+        }),
+        message: None,
+    };
+    writer
+        .write_all(&response.encode_length_delimited_to_vec())
+        .unwrap();
+
+    let mut offset = chunk_offset as usize;
+    let mut seqno = 0;
+    while offset < end {
+        let packet_end = std::cmp::min(offset + BYTES_PER_CHECKSUM * CHUNKS_PER_PACKET, data.len());
+        let packet = &data[offset..packet_end];
+        let mut sent = packet.to_vec();
+        if corrupted {
+            sent[0] ^= 0xff;
+        }
+        write_packet(&mut writer, offset as u64, seqno, packet, &sent);
+        offset = packet_end;
+        seqno += 1;
+    }
+    write_packet(&mut writer, offset as u64, seqno, &[], &[]);
+
+    reader
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    if let Some(status) = read_delimited::<ClientReadStatusProto>(&mut reader) {
+        if status.status == Status::ChecksumOk as i32 {
+            storage.lock().unwrap().checksum_ok += 1;
+        }
+    }
+}
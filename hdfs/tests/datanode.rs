@@ -0,0 +1,192 @@
+extern crate crc32c;
+extern crate hdfs;
+extern crate prost;
+
+mod common;
+
+use common::{unreachable_address, Block, FakeDataNode, FakeNameNode};
+use hdfs::err::Error;
+use std::io::{self, Read, Seek, SeekFrom};
+
+fn content(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+fn block(id: u64, len: usize, datanodes: &[&FakeDataNode]) -> Block {
+    Block {
+        id,
+        len: len as u64,
+        datanodes: datanodes.iter().map(|d| d.address.to_owned()).collect(),
+    }
+}
+
+#[test]
+fn test_read_multiple_blocks() {
+    let data = content(7234);
+    let datanode = FakeDataNode::start()
+        .block(1, &data[..3000])
+        .block(2, &data[3000..6000])
+        .block(3, &data[6000..]);
+    let namenode = FakeNameNode::start().blocks(
+        "/data/file",
+        vec![
+            block(1, 3000, &[&datanode]),
+            block(2, 3000, &[&datanode]),
+            block(3, 1234, &[&datanode]),
+        ],
+    );
+    let client = namenode.client();
+
+    let mut file = client.open("/data/file").unwrap();
+    assert_eq!(7234, file.len());
+    let mut read = vec![];
+    file.read_to_end(&mut read).unwrap();
+    assert_eq!(data, read);
+
+    assert_eq!(
+        vec![(1, 0, 3000), (2, 0, 3000), (3, 0, 1234)],
+        datanode.reads()
+    );
+    assert_eq!(3, datanode.checksum_ok(3));
+}
+
+#[test]
+fn test_seek_unaligned() {
+    let data = content(6000);
+    let datanode = FakeDataNode::start()
+        .block(1, &data[..3000])
+        .block(2, &data[3000..]);
+    let namenode = FakeNameNode::start().blocks(
+        "/file",
+        vec![block(1, 3000, &[&datanode]), block(2, 3000, &[&datanode])],
+    );
+    let client = namenode.client();
+    let mut file = client.open("/file").unwrap();
+
+    // Crosses the boundary between both blocks
+    assert_eq!(2990, file.seek(SeekFrom::Start(2990)).unwrap());
+    let mut buf = [0u8; 20];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&data[2990..3010], &buf[..]);
+
+    assert_eq!(5995, file.seek(SeekFrom::End(-5)).unwrap());
+    let mut rest = vec![];
+    file.read_to_end(&mut rest).unwrap();
+    assert_eq!(&data[5995..], &rest[..]);
+
+    file.seek(SeekFrom::Current(-1000)).unwrap();
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&data[5000..5020], &buf[..]);
+
+    assert!(file.seek(SeekFrom::Current(-10_000)).is_err());
+    assert_eq!((1, 2990, 10), datanode.reads()[0]);
+}
+
+#[test]
+fn test_failover_on_corrupted_replica() {
+    let data = content(5000);
+    let corrupted = FakeDataNode::start().block(1, &data).corrupt(1);
+    let healthy = FakeDataNode::start().block(1, &data);
+    let namenode =
+        FakeNameNode::start().blocks("/file", vec![block(1, 5000, &[&corrupted, &healthy])]);
+    let client = namenode.client();
+
+    let mut read = vec![];
+    client
+        .open("/file")
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap();
+    assert_eq!(data, read);
+    assert_eq!(1, corrupted.reads().len());
+    assert_eq!(0, corrupted.checksum_ok(0));
+    assert_eq!(1, healthy.checksum_ok(1));
+}
+
+#[test]
+fn test_failover_on_unreachable_datanode() {
+    let data = content(1000);
+    let healthy = FakeDataNode::start().block(1, &data);
+    let namenode = FakeNameNode::start().blocks(
+        "/file",
+        vec![Block {
+            id: 1,
+            len: 1000,
+            datanodes: vec![unreachable_address(), healthy.address.to_owned()],
+        }],
+    );
+    let client = namenode.client();
+
+    let mut read = vec![];
+    client
+        .open("/file")
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap();
+    assert_eq!(data, read);
+}
+
+#[test]
+fn test_failover_on_missing_replica() {
+    let data = content(1000);
+    let empty = FakeDataNode::start();
+    let healthy = FakeDataNode::start().block(1, &data);
+    let namenode = FakeNameNode::start().blocks("/file", vec![block(1, 1000, &[&empty, &healthy])]);
+    let client = namenode.client();
+
+    let mut read = vec![];
+    client
+        .open("/file")
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap();
+    assert_eq!(data, read);
+    assert_eq!(1, empty.reads().len());
+}
+
+#[test]
+fn test_all_replicas_corrupted() {
+    let data = content(1000);
+    let first = FakeDataNode::start().block(1, &data).corrupt(1);
+    let second = FakeDataNode::start().block(1, &data).corrupt(1);
+    let namenode = FakeNameNode::start().blocks("/file", vec![block(1, 1000, &[&first, &second])]);
+    let client = namenode.client();
+
+    let mut read = vec![];
+    let error = client
+        .open("/file")
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::Other, error.kind());
+    // Both replicas are tried again after refreshing the block locations
+    assert_eq!(2, first.reads().len());
+    assert_eq!(2, second.reads().len());
+}
+
+#[test]
+fn test_open_missing_file() {
+    let namenode = FakeNameNode::start().dir("/data");
+    let client = namenode.client();
+
+    match client.open("/data/missing") {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other.map(|f| f.len())),
+    }
+    match client.open("/data") {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other.map(|f| f.len())),
+    }
+}
+
+#[test]
+fn test_empty_file() {
+    let namenode = FakeNameNode::start().blocks("/empty", vec![]);
+    let client = namenode.client();
+
+    let mut file = client.open("/empty").unwrap();
+    assert!(file.is_empty());
+    let mut read = vec![];
+    file.read_to_end(&mut read).unwrap();
+    assert!(read.is_empty());
+}
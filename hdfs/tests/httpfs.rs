@@ -0,0 +1,538 @@
+extern crate hdfs;
+
+use hdfs::err::Error;
+use hdfs::fs::FileSystem;
+use hdfs::webhdfs::{self, WebHdfsOptions};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, Clone)]
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: &str) -> Response {
+        Response {
+            status,
+            headers: vec![(
+                String::from("Content-Type"),
+                String::from("application/json"),
+            )],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn data(body: &[u8]) -> Response {
+        Response {
+            status: 200,
+            headers: vec![],
+            body: body.to_vec(),
+        }
+    }
+
+    fn redirect(location: &str) -> Response {
+        Response {
+            status: 307,
+            headers: vec![(String::from("Location"), location.to_owned())],
+            body: vec![],
+        }
+    }
+
+    fn created() -> Response {
+        Response {
+            status: 201,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+}
+
+/// Minimal HTTP/1.1 server answering every request with `handler`, one request per connection.
+struct StubServer {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl StubServer {
+    fn start<F>(handler: F) -> StubServer
+    where
+        F: Fn(&Request) -> Response + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let request = read_request(&stream);
+                recorded.lock().unwrap().push(request.clone());
+                write_response(&mut stream, handler(&request));
+            }
+        });
+        StubServer { url, requests }
+    }
+
+    fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Request {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap().to_owned();
+    let target = parts.next().unwrap().to_owned();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut kv = line.splitn(2, ':');
+        headers.insert(
+            kv.next().unwrap().trim().to_lowercase(),
+            kv.next().unwrap_or("").trim().to_owned(),
+        );
+    }
+
+    let mut body = vec![];
+    if let Some(len) = headers.get("content-length") {
+        body.resize(len.parse().unwrap(), 0);
+        reader.read_exact(&mut body).unwrap();
+    } else if headers.get("transfer-encoding").map(String::as_str) == Some("chunked") {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).unwrap();
+            let size = usize::from_str_radix(size.trim(), 16).unwrap();
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).unwrap();
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    }
+
+    let mut split = target.splitn(2, '?');
+    let path = decode(split.next().unwrap());
+    let query = split
+        .next()
+        .unwrap_or("")
+        .split('&')
+        .filter(|kv| !kv.is_empty())
+        .map(|kv| {
+            let mut kv = kv.splitn(2, '=');
+            (decode(kv.next().unwrap()), decode(kv.next().unwrap_or("")))
+        })
+        .collect();
+
+    Request {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }
+}
+
+fn write_response(stream: &mut TcpStream, response: Response) {
+    let mut head = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (key, value) in response.headers {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(&response.body).unwrap();
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            decoded.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).unwrap()
+}
+
+fn options(url: &str) -> WebHdfsOptions {
+    WebHdfsOptions {
+        url: url.to_owned(),
+        user: Some(String::from("etl")),
+        delegation: None,
+        headers: vec![],
+    }
+}
+
+const FILE_STATUS: &str = r#"{"FileStatus":{"accessTime":1,"blockSize":134217728,"group":"hadoop",
+    "length":42,"modificationTime":2,"owner":"etl","pathSuffix":"","permission":"644",
+    "replication":3,"type":"FILE"}}"#;
+
+#[test]
+fn test_path_info_uses_pseudo_auth() {
+    let server = StubServer::start(|_| Response::json(200, FILE_STATUS));
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    let status = fs.path_info("/data/part-0").unwrap();
+    assert!(status.is_file());
+    assert_eq!("/data/part-0", status.path.to_str().unwrap());
+    assert_eq!(42, status.len);
+    assert_eq!(0o644, status.permission);
+    assert_eq!(3, status.replication);
+    assert_eq!("etl", status.owner);
+
+    let requests = server.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("GET", requests[0].method);
+    assert_eq!("/webhdfs/v1/data/part-0", requests[0].path);
+    assert_eq!("GETFILESTATUS", requests[0].query["op"]);
+    assert_eq!("etl", requests[0].query["user.name"]);
+}
+
+#[test]
+fn test_delegation_token_replaces_user_name() {
+    let server = StubServer::start(|_| Response::json(200, FILE_STATUS));
+    let mut options = options(&server.url);
+    options.delegation = Some(String::from("HAAFZXRs+token"));
+    let fs = webhdfs::get_httpfs(options);
+
+    fs.path_info("/data").unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!("HAAFZXRs+token", request.query["delegation"]);
+    assert!(!request.query.contains_key("user.name"));
+}
+
+#[test]
+fn test_custom_headers_are_sent() {
+    let server = StubServer::start(|_| Response::json(200, FILE_STATUS));
+    let mut options = options(&server.url);
+    options.headers = vec![(String::from("X-Proxy-Auth"), String::from("secret"))];
+    let fs = webhdfs::get_httpfs(options);
+
+    fs.path_info("/data").unwrap();
+
+    assert_eq!("secret", server.requests()[0].headers["x-proxy-auth"]);
+}
+
+#[test]
+fn test_list_directory() {
+    let server = StubServer::start(|_| {
+        Response::json(
+            200,
+            r#"{"FileStatuses":{"FileStatus":[
+            {"pathSuffix":"a","type":"DIRECTORY","length":0,"permission":"755"},
+            {"pathSuffix":"b.parquet","type":"FILE","length":10,"permission":"644"}]}}"#,
+        )
+    });
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    let entries = fs.list_directory("/data").unwrap();
+    assert_eq!(2, entries.len());
+    assert!(entries[0].is_dir());
+    assert_eq!("/data/a", entries[0].path.to_str().unwrap());
+    assert!(entries[1].is_file());
+    assert_eq!("/data/b.parquet", entries[1].path.to_str().unwrap());
+    assert_eq!("LISTSTATUS", server.requests()[0].query["op"]);
+}
+
+#[test]
+fn test_missing_file() {
+    let server = StubServer::start(|_| {
+        Response::json(
+            404,
+            r#"{"RemoteException":{"exception":"FileNotFoundException",
+            "javaClassName":"java.io.FileNotFoundException","message":"File does not exist: /nope"}}"#,
+        )
+    });
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    match fs.path_info("/nope") {
+        Err(Error::FileNotFound(message)) => assert_eq!("File does not exist: /nope", message),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(!fs.exists("/nope").unwrap());
+}
+
+#[test]
+fn test_remote_exception() {
+    let server = StubServer::start(|_| {
+        Response::json(
+            403,
+            r#"{"RemoteException":{"exception":"AccessControlException",
+            "javaClassName":"org.apache.hadoop.security.AccessControlException","message":"denied"}}"#,
+        )
+    });
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    match fs.mkdirs("/secret") {
+        Err(Error::RemoteException(class, message)) => {
+            assert_eq!("AccessControlException", class);
+            assert_eq!("denied", message);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_relative_path_uses_home_directory() {
+    let server = StubServer::start(|request| match request.query["op"].as_str() {
+        "GETHOMEDIRECTORY" => Response::json(200, r#"{"Path":"/user/etl"}"#),
+        _ => Response::json(200, FILE_STATUS),
+    });
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    fs.path_info("data").unwrap();
+    fs.path_info("other").unwrap();
+
+    let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(
+        vec![
+            "/webhdfs/v1/",
+            "/webhdfs/v1/user/etl/data",
+            "/webhdfs/v1/user/etl/other"
+        ],
+        paths
+    );
+}
+
+#[test]
+fn test_metadata_operations() {
+    let server = StubServer::start(|_| Response::json(200, r#"{"boolean":true}"#));
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    assert!(fs.mkdirs("/a/b").unwrap());
+    assert!(fs.rename("/a/b", "/a/c d").unwrap());
+    assert!(fs.delete("/a", true).unwrap());
+
+    let requests = server.requests();
+    assert_eq!(
+        ("PUT", "MKDIRS"),
+        (&requests[0].method[..], &requests[0].query["op"][..])
+    );
+    assert_eq!(
+        ("PUT", "RENAME"),
+        (&requests[1].method[..], &requests[1].query["op"][..])
+    );
+    assert_eq!("/a/c d", requests[1].query["destination"]);
+    assert_eq!(
+        ("DELETE", "DELETE"),
+        (&requests[2].method[..], &requests[2].query["op"][..])
+    );
+    assert_eq!("true", requests[2].query["recursive"]);
+}
+
+#[test]
+fn test_attribute_setters() {
+    let server = StubServer::start(|request| match request.query["op"].as_str() {
+        "SETREPLICATION" => Response::json(200, r#"{"boolean":true}"#),
+        _ => Response::json(200, ""),
+    });
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    fs.set_permission("/a", 0o750).unwrap();
+    fs.set_owner("/a", Some("etl"), None).unwrap();
+    assert!(fs.set_replication("/a/file", 2).unwrap());
+    fs.set_times("/a", Some(1000), None).unwrap();
+
+    let requests = server.requests();
+    let ops: Vec<_> = requests
+        .iter()
+        .map(|request| (&request.method[..], &request.query["op"][..]))
+        .collect();
+    assert_eq!(
+        vec![
+            ("PUT", "SETPERMISSION"),
+            ("PUT", "SETOWNER"),
+            ("PUT", "SETREPLICATION"),
+            ("PUT", "SETTIMES"),
+        ],
+        ops
+    );
+    assert_eq!("750", requests[0].query["permission"]);
+    assert_eq!("etl", requests[1].query["owner"]);
+    assert!(!requests[1].query.contains_key("group"));
+    assert_eq!("2", requests[2].query["replication"]);
+    assert_eq!("1000", requests[3].query["modificationtime"]);
+    assert_eq!("-1", requests[3].query["accesstime"]);
+}
+
+#[test]
+fn test_httpfs_open_is_served_by_gateway() {
+    let server = StubServer::start(|_| Response::data(b"hello"));
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    let mut content = String::new();
+    fs.open_at("/data/file", 2)
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+
+    assert_eq!("hello", content);
+    let requests = server.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("OPEN", requests[0].query["op"]);
+    assert_eq!("2", requests[0].query["offset"]);
+}
+
+#[test]
+fn test_namenode_open_follows_datanode_redirect() {
+    let datanode = StubServer::start(|_| Response::data(b"from datanode"));
+    let location = format!(
+        "{}/webhdfs/v1/data/file?op=OPEN&namenoderpcaddress=nn:8020&offset=0",
+        datanode.url
+    );
+    let namenode = StubServer::start(move |_| Response::redirect(&location));
+    let mut options = options(&namenode.url);
+    options.headers = vec![(String::from("X-Proxy-Auth"), String::from("secret"))];
+    let fs = webhdfs::get_webhdfs(options);
+
+    let mut content = String::new();
+    fs.open("/data/file")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+
+    assert_eq!("from datanode", content);
+    assert_eq!(1, namenode.requests().len());
+    let requests = datanode.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("nn:8020", requests[0].query["namenoderpcaddress"]);
+    assert_eq!("secret", requests[0].headers["x-proxy-auth"]);
+}
+
+#[test]
+fn test_httpfs_create_sends_data_in_one_request() {
+    let server = StubServer::start(|_| Response::created());
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    fs.create("/data/new", true, &b"payload"[..]).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("PUT", requests[0].method);
+    assert_eq!("CREATE", requests[0].query["op"]);
+    assert_eq!("true", requests[0].query["data"]);
+    assert_eq!("true", requests[0].query["overwrite"]);
+    assert_eq!(
+        "application/octet-stream",
+        requests[0].headers["content-type"]
+    );
+    assert_eq!(b"payload".to_vec(), requests[0].body);
+}
+
+#[test]
+fn test_httpfs_create_redirect_is_an_error() {
+    let server = StubServer::start(|_| Response::redirect("/webhdfs/v1/data/new?op=CREATE"));
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    match fs.create("/data/new", false, &b"payload"[..]) {
+        Err(Error::Http(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_namenode_create_is_done_in_two_steps() {
+    let datanode = StubServer::start(|_| Response::created());
+    let location = format!("{}/webhdfs/v1/data/new?op=CREATE", datanode.url);
+    let namenode = StubServer::start(move |_| Response::redirect(&location));
+    let fs = webhdfs::get_webhdfs(options(&namenode.url));
+
+    fs.create("/data/new", false, &b"payload"[..]).unwrap();
+
+    let requests = namenode.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("PUT", requests[0].method);
+    assert!(requests[0].body.is_empty());
+    assert!(!requests[0].query.contains_key("data"));
+
+    let requests = datanode.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("PUT", requests[0].method);
+    assert_eq!(b"payload".to_vec(), requests[0].body);
+}
+
+#[test]
+fn test_append() {
+    let server = StubServer::start(|_| Response::data(b""));
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    fs.append("/data/log", &b"more"[..]).unwrap();
+
+    let requests = server.requests();
+    assert_eq!("POST", requests[0].method);
+    assert_eq!("APPEND", requests[0].query["op"]);
+    assert_eq!(b"more".to_vec(), requests[0].body);
+}
+
+#[test]
+fn test_create_through_the_trait_uploads_on_flush() {
+    let server = StubServer::start(|request| match request.method.as_str() {
+        "PUT" => Response::created(),
+        _ => Response::data(b""),
+    });
+    let fs = webhdfs::get_httpfs(options(&server.url));
+    let fs: &dyn FileSystem = &fs;
+
+    let mut file = fs.create(Path::new("/data/new"), true).unwrap();
+    file.write_all(b"pay").unwrap();
+    file.write_all(b"load").unwrap();
+    assert!(server.requests().is_empty());
+    file.flush().unwrap();
+    file.write_all(b"more").unwrap();
+    drop(file);
+
+    let requests = server.requests();
+    assert_eq!(2, requests.len());
+    assert_eq!("CREATE", requests[0].query["op"]);
+    assert_eq!("true", requests[0].query["overwrite"]);
+    assert_eq!(b"payload".to_vec(), requests[0].body);
+    assert_eq!("APPEND", requests[1].query["op"]);
+    assert_eq!(b"more".to_vec(), requests[1].body);
+}
+
+#[test]
+fn test_create_through_the_trait_makes_empty_files() {
+    let server = StubServer::start(|_| Response::created());
+    let fs = webhdfs::get_httpfs(options(&server.url));
+
+    drop(FileSystem::create(&fs, Path::new("/data/empty"), false).unwrap());
+
+    let requests = server.requests();
+    assert_eq!(1, requests.len());
+    assert_eq!("CREATE", requests[0].query["op"]);
+    assert_eq!("false", requests[0].query["overwrite"]);
+    assert!(requests[0].body.is_empty());
+}
@@ -0,0 +1,130 @@
+extern crate futures;
+extern crate hdfs;
+extern crate object_store;
+extern crate tokio;
+
+use futures::TryStreamExt;
+use hdfs::async_hdfs::AsyncHdfs;
+use hdfs::fs::{FileSystem, MemoryFileSystem};
+use hdfs::store::HdfsObjectStore;
+use object_store::integration::*;
+use object_store::path::Path as ObjectPath;
+use object_store::{Error, GetOptions, GetRange, ObjectStore};
+use std::path::Path;
+use tokio::runtime::{Builder, Runtime};
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().build().unwrap()
+}
+
+fn memory_store() -> HdfsObjectStore<MemoryFileSystem> {
+    HdfsObjectStore::new(AsyncHdfs::new(MemoryFileSystem::default(), 4), "/store")
+}
+
+#[test]
+fn test_conformance() {
+    let rt = runtime();
+    let store = memory_store();
+
+    rt.block_on(put_get_delete_list(&store));
+    rt.block_on(get_opts(&store));
+    rt.block_on(put_opts(&store, false));
+    rt.block_on(list_uses_directories_correctly(&store));
+    rt.block_on(list_with_delimiter(&store));
+    rt.block_on(rename_and_copy(&store));
+    rt.block_on(copy_if_not_exists(&store));
+    rt.block_on(copy_rename_nonexistent_object(&store));
+    rt.block_on(stream_get(&store));
+    rt.block_on(multipart_out_of_order(&store));
+
+    let error = rt
+        .block_on(get_nonexistent_object(&store, None))
+        .unwrap_err();
+    assert!(matches!(error, Error::NotFound { .. }), "{}", error);
+}
+
+#[test]
+fn test_objects_are_files_under_the_root() {
+    let rt = runtime();
+    let store = memory_store();
+    let fs = store.inner().inner();
+    fs.add_file("/outside", b"data").unwrap();
+
+    let location = ObjectPath::from("dir/object");
+    rt.block_on(store.put(&location, "content".into())).unwrap();
+    assert_eq!(7, fs.stat(Path::new("/store/dir/object")).unwrap().len);
+
+    // Staged puts and uploads do not show up
+    let upload = rt.block_on(store.put_multipart(&ObjectPath::from("pending")));
+    let objects: Vec<_> = rt
+        .block_on(store.list(None).map_ok(|meta| meta.location).try_collect())
+        .unwrap();
+    assert_eq!(vec![location.clone()], objects);
+    drop(upload);
+
+    // Directories left empty go away with their last object
+    rt.block_on(store.delete(&location)).unwrap();
+    assert!(!fs.exists(Path::new("/store/dir")).unwrap());
+    assert!(fs.exists(Path::new("/store")).unwrap());
+}
+
+#[test]
+fn test_get_ranges_of_large_objects() {
+    let rt = runtime();
+    let store = memory_store();
+    let data: Vec<u8> = (0..3_000_000).map(|i| (i % 251) as u8).collect();
+    let location = ObjectPath::from("large");
+    rt.block_on(store.put(&location, data.clone().into()))
+        .unwrap();
+
+    let bytes = rt
+        .block_on(store.get_range(&location, 1_000_000..2_500_000))
+        .unwrap();
+    assert_eq!(&data[1_000_000..2_500_000], &bytes[..]);
+
+    let options = GetOptions {
+        range: Some(GetRange::Suffix(10)),
+        ..GetOptions::default()
+    };
+    let result = rt.block_on(store.get_opts(&location, options)).unwrap();
+    assert_eq!(2_999_990..3_000_000, result.range);
+    assert_eq!(
+        &data[2_999_990..],
+        &rt.block_on(result.bytes()).unwrap()[..]
+    );
+}
+
+#[test]
+fn test_stale_staging_files_are_removed() {
+    let rt = runtime();
+    let store = memory_store();
+    let fs = store.inner().inner();
+    let two_days_ago = 1_000 * (std::time::UNIX_EPOCH.elapsed().unwrap().as_secs() - 2 * 86_400);
+    fs.add_file("/store/.object_store/put", b"data").unwrap();
+    fs.add_file("/store/.object_store/upload/00000000", b"part")
+        .unwrap();
+    fs.add_file("/store/.object_store/active/00000000", b"part")
+        .unwrap();
+    fs.add_file("/store/.object_store/active/00000001", b"part")
+        .unwrap();
+    for path in &[
+        "/store/.object_store/put",
+        "/store/.object_store/upload",
+        "/store/.object_store/upload/00000000",
+        "/store/.object_store/active",
+        "/store/.object_store/active/00000000",
+    ] {
+        fs.set_times(Path::new(path), Some(two_days_ago), None)
+            .unwrap();
+    }
+
+    let _upload = rt
+        .block_on(store.put_multipart(&ObjectPath::from("object")))
+        .unwrap();
+    assert!(!fs.exists(Path::new("/store/.object_store/put")).unwrap());
+    assert!(!fs.exists(Path::new("/store/.object_store/upload")).unwrap());
+    // A recent part keeps its upload
+    assert!(fs
+        .exists(Path::new("/store/.object_store/active/00000000"))
+        .unwrap());
+}
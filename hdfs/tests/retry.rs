@@ -0,0 +1,123 @@
+extern crate fs_test;
+extern crate hdfs;
+
+use fs_test::{FakeHdfs, Fault, FaultyFs, Operation};
+use hdfs::err::Error;
+use hdfs::fs::{FileSystem, RetryFileSystem, RetryPolicy};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(2),
+        ..RetryPolicy::default()
+    }
+}
+
+fn content() -> Vec<u8> {
+    (0..50_000).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_metadata_retried_on_standby() {
+    let faulty =
+        FaultyFs::new(FakeHdfs::new().dir("/data"), 0).script(vec![Fault::Standby, Fault::Standby]);
+    let fs = RetryFileSystem::new(faulty, policy(3));
+
+    assert!(fs.stat(Path::new("/data")).unwrap().is_dir());
+    assert_eq!(2, fs.inner().stats().standby);
+    assert_eq!(3, fs.inner().stats().calls);
+}
+
+#[test]
+fn test_gives_up_after_max_attempts() {
+    let faulty = FaultyFs::new(FakeHdfs::new().dir("/data"), 0).standby_rate(1.0);
+    let fs = RetryFileSystem::new(faulty, policy(4));
+
+    match fs.list(Path::new("/data")) {
+        Err(Error::RemoteException(class, _)) => assert_eq!("StandbyException", class),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(4, fs.inner().stats().calls);
+}
+
+#[test]
+fn test_not_retried_errors() {
+    let fake = FakeHdfs::new();
+    fake.inject_error(Operation::Stat, "/denied", || {
+        Error::RemoteException(String::from("AccessControlException"), String::new())
+    });
+    let faulty = FaultyFs::new(fake, 0);
+    let fs = RetryFileSystem::new(faulty, policy(5));
+
+    assert!(fs.stat(Path::new("/denied")).is_err());
+    assert!(!fs.exists(Path::new("/missing")).unwrap());
+    assert_eq!(2, fs.inner().stats().calls);
+}
+
+#[test]
+fn test_non_idempotent_only_retried_before_execution() {
+    let fake = FakeHdfs::new().file("/a", b"").file("/b", b"");
+    fake.inject_error(Operation::Delete, "/b", || {
+        Error::Io(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+    });
+    let faulty = FaultyFs::new(fake, 0).script(vec![Fault::Standby]);
+    let fs = RetryFileSystem::new(faulty, policy(5));
+
+    assert!(fs.delete(Path::new("/a"), false).unwrap());
+    assert!(fs.delete(Path::new("/b"), false).is_err());
+    assert_eq!(3, fs.inner().stats().calls);
+
+    // mkdirs is idempotent and goes through the same errors
+    fs.inner()
+        .inner()
+        .inject_error(Operation::Mkdirs, "/c", || {
+            Error::Io(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        });
+    assert!(fs.mkdirs(Path::new("/c")).is_err());
+    assert_eq!(8, fs.inner().stats().calls);
+}
+
+#[test]
+fn test_read_reopens_and_seeks() {
+    let data = content();
+    let faulty = FaultyFs::new(FakeHdfs::new().file("/file", &data), 5)
+        .partial_io(true)
+        .drop_rate(0.2);
+    let fs = RetryFileSystem::new(faulty, policy(20));
+
+    let mut read = vec![];
+    fs.open(Path::new("/file"))
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap();
+
+    assert_eq!(data, read);
+    assert!(fs.inner().stats().drops > 0);
+}
+
+#[test]
+fn test_read_gives_up() {
+    let faulty = FaultyFs::new(FakeHdfs::new().file("/file", &content()), 0).drop_rate(1.0);
+    let fs = RetryFileSystem::new(faulty, policy(3));
+
+    let mut reader = fs.open(Path::new("/file")).unwrap();
+    let error = reader.read(&mut [0; 10]).unwrap_err();
+    assert_eq!(io::ErrorKind::ConnectionReset, error.kind());
+    assert_eq!(3, fs.inner().stats().drops);
+}
+
+#[test]
+fn test_create_retried_writes_not() {
+    let faulty =
+        FaultyFs::new(FakeHdfs::new(), 0).script(vec![Fault::Standby, Fault::None, Fault::Drop]);
+    let fs = RetryFileSystem::new(faulty, policy(5));
+
+    let mut writer = fs.create(Path::new("/file"), false).unwrap();
+    assert!(writer.write(b"data").is_err());
+    assert!(writer.write(b"data").is_ok());
+    assert_eq!(1, fs.inner().stats().drops);
+}
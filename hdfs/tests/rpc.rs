@@ -0,0 +1,268 @@
+extern crate crc32c;
+extern crate hdfs;
+extern crate prost;
+
+mod common;
+
+use common::FakeNameNode;
+use hdfs::err::Error;
+use hdfs::fs::FileSystem;
+use hdfs::rpc::NameNodeClient;
+use std::net::TcpListener;
+use std::path::Path;
+
+fn names(entries: &[hdfs::hdfs::FileStatus]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|e| e.path.to_str().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn test_path_info() {
+    let namenode = FakeNameNode::start().dir("/data").file("/data/part-0", 42);
+    let client = namenode.client();
+
+    let file = client.path_info("/data/part-0").unwrap();
+    assert!(file.is_file());
+    assert_eq!("/data/part-0", file.path.to_str().unwrap());
+    assert_eq!(42, file.len);
+    assert_eq!(0o644, file.permission);
+    assert_eq!(3, file.replication);
+    assert_eq!(134_217_728, file.block_size);
+    assert_eq!("etl", file.owner);
+    assert_eq!("hadoop", file.group);
+
+    let dir = client.path_info("/data").unwrap();
+    assert!(dir.is_dir());
+    assert_eq!(0o755, dir.permission);
+
+    match client.path_info("/missing") {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other.map(|s| s.path)),
+    }
+    assert!(client.exists("/data").unwrap());
+    assert!(!client.exists("/missing").unwrap());
+}
+
+#[test]
+fn test_connection_is_reused() {
+    let namenode = FakeNameNode::start().dir("/data");
+    let client = namenode.client();
+
+    for _ in 0..5 {
+        client.path_info("/data").unwrap();
+    }
+
+    let namespace = namenode.namespace.lock().unwrap();
+    assert_eq!(1, namespace.connections);
+    assert_eq!(vec!["etl"], namespace.users);
+    assert_eq!(5, namespace.calls.len());
+}
+
+#[test]
+fn test_relative_path_is_under_home() {
+    let namenode = FakeNameNode::start()
+        .dir("/user")
+        .dir("/user/etl")
+        .file("/user/etl/a", 1);
+    let client = namenode.client();
+
+    assert_eq!("/user/etl", client.current_dir().unwrap().to_str().unwrap());
+    assert_eq!(
+        "/user/etl/a",
+        client.path_info("a").unwrap().path.to_str().unwrap()
+    );
+}
+
+#[test]
+fn test_list_directory_follows_pagination() {
+    let namenode = FakeNameNode::start()
+        .dir("/data")
+        .file("/data/a", 1)
+        .file("/data/b", 1)
+        .dir("/data/c")
+        .file("/data/c/nested", 1)
+        .file("/data/d", 1)
+        .file("/data/e", 1);
+    namenode.namespace.lock().unwrap().page_size = 2;
+    let client = namenode.client();
+
+    let entries = client.list_directory("/data").unwrap();
+    assert_eq!(
+        vec!["/data/a", "/data/b", "/data/c", "/data/d", "/data/e"],
+        names(&entries)
+    );
+    assert!(entries[2].is_dir());
+    assert_eq!(3, namenode.namespace.lock().unwrap().calls.len());
+}
+
+#[test]
+fn test_list_file_and_missing_directory() {
+    let namenode = FakeNameNode::start().file("/file", 3);
+    let client = namenode.client();
+
+    assert_eq!(
+        vec!["/file"],
+        names(&client.list_directory("/file").unwrap())
+    );
+    match client.list_directory("/missing") {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other.map(|e| names(&e))),
+    }
+}
+
+#[test]
+fn test_mkdirs_delete_rename() {
+    let namenode = FakeNameNode::start();
+    let client = namenode.client();
+
+    assert!(client.mkdirs("/a/b/c").unwrap());
+    assert!(client.path_info("/a/b").unwrap().is_dir());
+
+    match client.delete("/a", false) {
+        Err(Error::RemoteException(class, _)) => {
+            assert_eq!("PathIsNotEmptyDirectoryException", class)
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    client.rename("/a/b", "/a/d", false).unwrap();
+    assert!(!client.exists("/a/b/c").unwrap());
+    assert!(client.exists("/a/d/c").unwrap());
+
+    match client.rename("/missing", "/a/e", false) {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    assert!(client.delete("/a", true).unwrap());
+    assert!(!client.exists("/a/d/c").unwrap());
+    assert!(!client.delete("/a", true).unwrap());
+}
+
+#[test]
+fn test_content_summary() {
+    let namenode = FakeNameNode::start()
+        .dir("/data")
+        .dir("/data/a")
+        .file("/data/a/part-0", 100)
+        .file("/data/part-0", 20)
+        .file("/database", 1);
+    let client = namenode.client();
+
+    let summary = client.content_summary("/data").unwrap();
+    assert_eq!(120, summary.length);
+    assert_eq!(2, summary.file_count);
+    assert_eq!(2, summary.directory_count);
+    assert_eq!(360, summary.space_consumed);
+    assert_eq!(None, summary.quota);
+    assert_eq!(None, summary.space_quota);
+
+    match client.content_summary("/missing") {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_set_quotas() {
+    let namenode = FakeNameNode::start().dir("/data").file("/data/part-0", 20);
+    let client = namenode.client();
+
+    client
+        .set_namespace_quota(Path::new("/data"), Some(100))
+        .unwrap();
+    client
+        .set_space_quota(Path::new("/data"), Some(1 << 30))
+        .unwrap();
+    let summary = client.content_summary("/data").unwrap();
+    assert_eq!(Some(100), summary.quota);
+    assert_eq!(Some(1 << 30), summary.space_quota);
+
+    client
+        .set_namespace_quota(Path::new("/data"), None)
+        .unwrap();
+    let summary = client.content_summary("/data").unwrap();
+    assert_eq!(None, summary.quota);
+    assert_eq!(Some(1 << 30), summary.space_quota);
+
+    match client.set_space_quota(Path::new("/missing"), Some(1)) {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_attribute_setters() {
+    let namenode = FakeNameNode::start().dir("/data").file("/data/part-0", 20);
+    let client = namenode.client();
+    let fs: &dyn FileSystem = &client;
+    let file = Path::new("/data/part-0");
+
+    fs.set_permission(file, 0o600).unwrap();
+    fs.set_owner(file, Some("hive"), None).unwrap();
+    fs.set_replication(file, 2).unwrap();
+    fs.set_times(file, Some(1_600_000_000_000), None).unwrap();
+    let status = fs.stat(file).unwrap();
+    assert_eq!(0o600, status.permission);
+    assert_eq!(("hive", "hadoop"), (&status.owner[..], &status.group[..]));
+    assert_eq!(2, status.replication);
+    assert_eq!(1_600_000_000_000, status.modification_time);
+    assert_eq!(1_500_000_000_000, status.access_time);
+
+    fs.set_owner(file, None, Some("analysts")).unwrap();
+    assert_eq!("hive", fs.stat(file).unwrap().owner);
+    assert_eq!("analysts", fs.stat(file).unwrap().group);
+
+    // Directories have no replication
+    assert!(!client.set_replication("/data", 2).unwrap());
+    match fs.set_replication(Path::new("/data"), 2) {
+        Err(Error::RemoteException(ref class, _)) if class == "IOException" => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match fs.set_permission(Path::new("/missing"), 0o600) {
+        Err(Error::FileNotFound(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_failover_to_active_namenode() {
+    let standby = FakeNameNode::standby();
+    let active = FakeNameNode::start().dir("/data");
+    let client = NameNodeClient::new(
+        vec![standby.address.to_owned(), active.address.to_owned()],
+        "etl",
+    );
+
+    assert!(client.path_info("/data").unwrap().is_dir());
+    assert!(client.path_info("/data").unwrap().is_dir());
+
+    // The client stays on the active namenode once it found it
+    assert_eq!(1, standby.namespace.lock().unwrap().calls.len());
+    assert_eq!(2, active.namespace.lock().unwrap().calls.len());
+}
+
+#[test]
+fn test_failover_on_unreachable_namenode() {
+    let unreachable = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    };
+    let active = FakeNameNode::start().dir("/data");
+    let client = NameNodeClient::new(vec![unreachable, active.address.to_owned()], "etl");
+
+    assert!(client.exists("/data").unwrap());
+}
+
+#[test]
+fn test_standby_only() {
+    let standby = FakeNameNode::standby();
+    let client = standby.client();
+
+    match client.path_info("/data") {
+        Err(Error::RemoteException(class, _)) => assert_eq!("StandbyException", class),
+        other => panic!("unexpected {:?}", other.map(|s| s.path)),
+    }
+}
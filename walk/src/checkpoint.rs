@@ -0,0 +1,362 @@
+//! Saved state of a walk, to resume it after a restart without returning anything
+//! twice. The format is text, one line per field or entry left to walk, with tabs,
+//! new lines and backslashes of paths escaped.
+
+use err::Error;
+use ignore::IgnoreRules;
+use parallel::Dir;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walk::Node;
+
+const HEADER: &str = "walk-checkpoint 1";
+const PARALLEL_HEADER: &str = "parallel-walk-checkpoint 1";
+
+/// What is left of a walk after the last entry returned. Resuming needs a walk built
+/// the same way, on the same path.
+#[derive(Debug)]
+pub struct Checkpoint {
+    pub(crate) path: PathBuf,
+    /// Alternatives of the pattern started, the last one being walked.
+    pub(crate) roots_started: usize,
+    /// The last directory returned, not listed yet.
+    pub(crate) pending: Option<(usize, PathBuf)>,
+    pub(crate) nodes: Vec<Node>,
+    /// Rules of the ignore files read, by directory, for what is left below them.
+    pub(crate) ignores: Vec<(PathBuf, IgnoreRules)>,
+}
+
+/// What is left of a parallel walk: the directories whose entries were not all
+/// returned, each with the last one that was.
+#[derive(Debug)]
+pub struct ParallelCheckpoint {
+    pub(crate) path: PathBuf,
+    /// Roots returned, in the order of the alternatives of the pattern.
+    pub(crate) roots_returned: usize,
+    pub(crate) dirs: Vec<Dir>,
+    /// A sorted walk starts over, skipping the errors returned, which come first, and
+    /// the entries up to the last one returned.
+    pub(crate) sorted: bool,
+    pub(crate) sorted_errors: usize,
+    pub(crate) sorted_after: Option<PathBuf>,
+}
+
+/// Escapes a path for the checkpoints of what goes with a walk.
+pub fn escape(path: &Path) -> Result<String, Error> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| Error::PathConversionError(path.as_os_str().to_owned()))?;
+    Ok(escape_str(path))
+}
+
+fn escape_str(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+pub fn unescape(path: &str) -> Result<PathBuf, Error> {
+    unescape_str(path).map(PathBuf::from)
+}
+
+fn unescape_str(path: &str) -> Result<String, Error> {
+    let mut unescaped = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\\') => unescaped.push('\\'),
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                _ => return Err(invalid(path)),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Ok(unescaped)
+}
+
+fn parse<F: FromStr>(field: &str, line: &str) -> Result<F, Error> {
+    field.parse().map_err(|_| invalid(line))
+}
+
+fn invalid(line: &str) -> Error {
+    Error::InvalidCheckpoint(format!("unexpected line: {}", line))
+}
+
+fn read_header(lines: &mut io::Lines<&mut dyn BufRead>, expected: &str) -> Result<(), Error> {
+    match lines.next() {
+        Some(Ok(ref header)) if header == expected => Ok(()),
+        Some(Err(e)) => Err(e.into()),
+        _ => Err(Error::InvalidCheckpoint(String::from("not a checkpoint"))),
+    }
+}
+
+/// Writes to a local file replaced at once, so that a crash leaves the previous one.
+pub fn save<F>(file: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    let mut temporary = file.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let mut out = io::BufWriter::new(fs::File::create(&temporary)?);
+    write(&mut out)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&temporary, file)?;
+    Ok(())
+}
+
+/// Reads a local file, `None` when there is none.
+pub fn load<C, F>(file: &Path, read: F) -> Result<Option<C>, Error>
+where
+    F: FnOnce(&mut dyn BufRead) -> Result<C, Error>,
+{
+    match fs::File::open(file) {
+        Ok(f) => read(&mut BufReader::new(f)).map(Some),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Checkpoint {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> Result<(), Error> {
+        writeln!(out, "{}", HEADER)?;
+        writeln!(out, "path\t{}", escape(&self.path)?)?;
+        writeln!(out, "roots\t{}", self.roots_started)?;
+        if let Some((depth, ref path)) = self.pending {
+            writeln!(out, "pending\t{}\t{}", depth, escape(path)?)?;
+        }
+        for node in &self.nodes {
+            match *node {
+                Node::File(depth, ref path, matched, _) => {
+                    writeln!(out, "file\t{}\t{}\t{}", depth, matched, escape(path)?)?
+                }
+                Node::Dir(depth, ref path, matched, _, _) => {
+                    writeln!(out, "dir\t{}\t{}\t{}", depth, matched, escape(path)?)?
+                }
+                Node::Exit(depth, ref path) => writeln!(out, "exit\t{}\t{}", depth, escape(path)?)?,
+                Node::Loop(ref path, ref ancestor) => {
+                    writeln!(out, "loop\t{}\t{}", escape(path)?, escape(ancestor)?)?
+                }
+            }
+        }
+        for (dir, rules) in &self.ignores {
+            for line in rules.lines() {
+                writeln!(out, "ignore\t{}\t{}", escape(dir)?, escape_str(line))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(input: &mut dyn BufRead) -> Result<Checkpoint, Error> {
+        let mut lines = input.lines();
+        read_header(&mut lines, HEADER)?;
+        let mut checkpoint = Checkpoint {
+            path: PathBuf::new(),
+            roots_started: 0,
+            pending: None,
+            nodes: vec![],
+            ignores: vec![],
+        };
+        for line in lines {
+            let line = line?;
+            let fields: Vec<_> = line.split('\t').collect();
+            match (fields[0], fields.len()) {
+                ("path", 2) => checkpoint.path = unescape(fields[1])?,
+                ("roots", 2) => checkpoint.roots_started = parse(fields[1], &line)?,
+                ("pending", 3) => {
+                    checkpoint.pending = Some((parse(fields[1], &line)?, unescape(fields[2])?))
+                }
+                ("file", 4) => {
+                    let node = Node::File(
+                        parse(fields[1], &line)?,
+                        unescape(fields[3])?,
+                        parse(fields[2], &line)?,
+                        None,
+                    );
+                    checkpoint.nodes.push(node)
+                }
+                ("dir", 4) => {
+                    let node = Node::Dir(
+                        parse(fields[1], &line)?,
+                        unescape(fields[3])?,
+                        parse(fields[2], &line)?,
+                        None,
+                        None,
+                    );
+                    checkpoint.nodes.push(node)
+                }
+                ("exit", 3) => {
+                    let node = Node::Exit(parse(fields[1], &line)?, unescape(fields[2])?);
+                    checkpoint.nodes.push(node)
+                }
+                ("loop", 3) => {
+                    let node = Node::Loop(unescape(fields[1])?, unescape(fields[2])?);
+                    checkpoint.nodes.push(node)
+                }
+                ("ignore", 3) => {
+                    let dir = unescape(fields[1])?;
+                    if checkpoint.ignores.last().is_none_or(|last| last.0 != dir) {
+                        checkpoint.ignores.push((dir, IgnoreRules::new()));
+                    }
+                    if let Some((_, rules)) = checkpoint.ignores.last_mut() {
+                        rules.add(&unescape_str(fields[2])?)?;
+                    }
+                }
+                _ => return Err(invalid(&line)),
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to a local file, replaced at once so that a crash leaves
+    /// the previous one.
+    pub fn save(&self, file: &Path) -> Result<(), Error> {
+        save(file, |out| self.write(out))
+    }
+
+    /// Reads the checkpoint of a local file, `None` when there is none.
+    pub fn load(file: &Path) -> Result<Option<Checkpoint>, Error> {
+        load(file, Checkpoint::read)
+    }
+}
+
+impl ParallelCheckpoint {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> Result<(), Error> {
+        writeln!(out, "{}", PARALLEL_HEADER)?;
+        writeln!(out, "path\t{}", escape(&self.path)?)?;
+        writeln!(out, "roots\t{}", self.roots_returned)?;
+        if self.sorted {
+            write!(out, "sorted\t{}", self.sorted_errors)?;
+            match self.sorted_after {
+                Some(ref after) => writeln!(out, "\t{}", escape(after)?)?,
+                None => writeln!(out)?,
+            }
+        }
+        for dir in &self.dirs {
+            write!(
+                out,
+                "dir\t{}\t{}\t{}",
+                dir.root,
+                dir.depth,
+                escape(&dir.path)?
+            )?;
+            match dir.after {
+                Some(ref after) => writeln!(out, "\t{}", escape(after)?)?,
+                None => writeln!(out)?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(input: &mut dyn BufRead) -> Result<ParallelCheckpoint, Error> {
+        let mut lines = input.lines();
+        read_header(&mut lines, PARALLEL_HEADER)?;
+        let mut checkpoint = ParallelCheckpoint {
+            path: PathBuf::new(),
+            roots_returned: 0,
+            dirs: vec![],
+            sorted: false,
+            sorted_errors: 0,
+            sorted_after: None,
+        };
+        for line in lines {
+            let line = line?;
+            let fields: Vec<_> = line.split('\t').collect();
+            match (fields[0], fields.len()) {
+                ("path", 2) => checkpoint.path = unescape(fields[1])?,
+                ("roots", 2) => checkpoint.roots_returned = parse(fields[1], &line)?,
+                ("sorted", 2) | ("sorted", 3) => {
+                    checkpoint.sorted = true;
+                    checkpoint.sorted_errors = parse(fields[1], &line)?;
+                    checkpoint.sorted_after =
+                        fields.get(2).map(|after| unescape(after)).transpose()?;
+                }
+                ("dir", 4) | ("dir", 5) => checkpoint.dirs.push(Dir {
+                    root: parse(fields[1], &line)?,
+                    depth: parse(fields[2], &line)?,
+                    path: unescape(fields[3])?,
+                    after: fields.get(4).map(|after| unescape(after)).transpose()?,
+                }),
+                _ => return Err(invalid(&line)),
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to a local file, replaced at once so that a crash leaves
+    /// the previous one.
+    pub fn save(&self, file: &Path) -> Result<(), Error> {
+        save(file, |out| self.write(out))
+    }
+
+    /// Reads the checkpoint of a local file, `None` when there is none.
+    pub fn load(file: &Path) -> Result<Option<ParallelCheckpoint>, Error> {
+        load(file, ParallelCheckpoint::read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let checkpoint = Checkpoint {
+            path: PathBuf::from("/data/{a,b}/**"),
+            roots_started: 1,
+            pending: Some((1, PathBuf::from("/data/a/x"))),
+            nodes: vec![
+                Node::File(2, PathBuf::from("/data/a/tab\there"), false, None),
+                Node::Dir(1, PathBuf::from("/data/a/new\nline\\"), true, None, None),
+                Node::Exit(0, PathBuf::from("/data/a/")),
+                Node::Loop(PathBuf::from("/data/a/link"), PathBuf::from("/data/a/")),
+            ],
+            ignores: vec![(
+                PathBuf::from("/data/a"),
+                IgnoreRules::parse("*.csv\n!keep\tme.csv\n").unwrap(),
+            )],
+        };
+        let mut out = vec![];
+        checkpoint.write(&mut out).unwrap();
+        let read = Checkpoint::read(&mut &out[..]).unwrap();
+        assert_eq!(checkpoint.path, read.path);
+        assert_eq!(checkpoint.roots_started, read.roots_started);
+        assert_eq!(checkpoint.pending, read.pending);
+        assert_eq!(
+            format!("{:?}", checkpoint.nodes),
+            format!("{:?}", read.nodes)
+        );
+        assert_eq!(1, read.ignores.len());
+        assert_eq!(checkpoint.ignores[0].0, read.ignores[0].0);
+        assert_eq!(checkpoint.ignores[0].1.lines(), read.ignores[0].1.lines());
+    }
+
+    #[test]
+    fn test_invalid() {
+        for content in &[
+            "",
+            "walk-checkpoint 2\n",
+            "walk-checkpoint 1\nroots\tx\n",
+            "walk-checkpoint 1\nfile\t1\ttrue\n",
+            "walk-checkpoint 1\nexit\t1\t/a\\x\n",
+            "walk-checkpoint 1\nnode\t1\t/a\n",
+        ] {
+            assert!(
+                Checkpoint::read(&mut content.as_bytes()).is_err(),
+                "{:?}",
+                content
+            );
+        }
+    }
+}
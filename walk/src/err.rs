@@ -1,10 +1,36 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 #[derive(Debug)]
 pub enum Error {
     IoError(std::io::Error),
     PathConversionError(std::ffi::OsString),
     PatternError(glob::PatternError),
+    RegexError(regex::Error),
     NoPathDefined,
     PathFormatError,
+    InvalidFilter(String),
+    /// A link leading back to this directory, walked above it.
+    SymlinkLoop(PathBuf),
+    InvalidCheckpoint(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IoError(ref e) => write!(f, "{}", e),
+            Error::PathConversionError(ref path) => write!(f, "invalid path: {:?}", path),
+            Error::PatternError(ref e) => write!(f, "invalid pattern: {}", e),
+            Error::RegexError(ref e) => write!(f, "{}", e),
+            Error::NoPathDefined => write!(f, "no path to walk"),
+            Error::PathFormatError => write!(f, "path is not valid UTF-8"),
+            Error::InvalidFilter(ref message) => write!(f, "invalid filter: {}", message),
+            Error::SymlinkLoop(ref ancestor) => {
+                write!(f, "link back to {}", ancestor.display())
+            }
+            Error::InvalidCheckpoint(ref message) => write!(f, "invalid checkpoint: {}", message),
+        }
+    }
 }
 
 impl From<std::io::Error> for Error {
@@ -24,3 +50,40 @@ impl From<glob::PatternError> for Error {
         Error::PatternError(err)
     }
 }
+
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Error::RegexError(err)
+    }
+}
+
+/// A failure of the file system during a walk, with the path it happened on.
+#[derive(Debug)]
+pub struct WalkError<E> {
+    path: PathBuf,
+    error: E,
+}
+
+impl<E> WalkError<E> {
+    pub fn new(path: PathBuf, error: E) -> WalkError<E> {
+        WalkError { path, error }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for WalkError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.path.display(), self.error)
+    }
+}
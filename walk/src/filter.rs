@@ -1,17 +1,13 @@
 use err::Error;
+use regex::{Regex, RegexBuilder};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use walk::MetadataTrait;
 
 pub trait PathFilter {
     fn is_match(&self, path: &str) -> bool;
 }
 
-pub struct TestFilter {}
-
-impl PathFilter for TestFilter {
-    fn is_match(&self, path: &str) -> bool {
-        path.ends_with(".h")
-    }
-}
-
 pub struct StartFilter {
     pattern: String,
 }
@@ -30,12 +26,21 @@ impl StartFilter {
 
 pub struct GlobFilter {
     pattern: glob::Pattern,
+    case_sensitive: bool,
 }
 
 impl GlobFilter {
     pub fn new(path: &str) -> Result<GlobFilter, Error> {
         Ok(GlobFilter {
             pattern: glob::Pattern::new(path)?,
+            case_sensitive: true,
+        })
+    }
+
+    pub fn case_insensitive(path: &str) -> Result<GlobFilter, Error> {
+        Ok(GlobFilter {
+            case_sensitive: false,
+            ..GlobFilter::new(path)?
         })
     }
 }
@@ -44,9 +49,340 @@ impl PathFilter for GlobFilter {
     fn is_match(&self, path: &str) -> bool {
         let options = glob::MatchOptions {
             require_literal_separator: true,
-            case_sensitive: true,
+            case_sensitive: self.case_sensitive,
             require_literal_leading_dot: false,
         };
         self.pattern.matches_with(path, &options)
     }
 }
+
+/// Matches the last component of the path only, like `find -name`.
+pub struct NameFilter {
+    pattern: glob::Pattern,
+    case_sensitive: bool,
+}
+
+impl NameFilter {
+    pub fn new(pattern: &str) -> Result<NameFilter, Error> {
+        Ok(NameFilter {
+            pattern: glob::Pattern::new(pattern)?,
+            case_sensitive: true,
+        })
+    }
+
+    pub fn case_insensitive(pattern: &str) -> Result<NameFilter, Error> {
+        Ok(NameFilter {
+            case_sensitive: false,
+            ..NameFilter::new(pattern)?
+        })
+    }
+}
+
+impl PathFilter for NameFilter {
+    fn is_match(&self, path: &str) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            case_sensitive: self.case_sensitive,
+            require_literal_leading_dot: false,
+        };
+        self.pattern.matches_with(file_name(path), &options)
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+/// What a filter matches against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchOn {
+    Path,
+    /// The last component only.
+    Name,
+}
+
+/// Regular expression matching the whole path or name, like `dt=2024-0[1-3]-.*`.
+pub struct RegexFilter {
+    regex: Regex,
+    match_on: MatchOn,
+}
+
+impl RegexFilter {
+    pub fn new(pattern: &str, match_on: MatchOn) -> Result<RegexFilter, Error> {
+        RegexFilter::build(pattern, match_on, true)
+    }
+
+    pub fn case_insensitive(pattern: &str, match_on: MatchOn) -> Result<RegexFilter, Error> {
+        RegexFilter::build(pattern, match_on, false)
+    }
+
+    fn build(pattern: &str, match_on: MatchOn, case_sensitive: bool) -> Result<RegexFilter, Error> {
+        let regex = RegexBuilder::new(&format!("^(?:{})$", pattern))
+            .case_insensitive(!case_sensitive)
+            .build()?;
+        Ok(RegexFilter { regex, match_on })
+    }
+}
+
+impl PathFilter for RegexFilter {
+    fn is_match(&self, path: &str) -> bool {
+        match self.match_on {
+            MatchOn::Path => self.regex.is_match(path),
+            MatchOn::Name => self.regex.is_match(file_name(path)),
+        }
+    }
+}
+
+/// Filters on the attributes of an entry, its path included.
+pub trait MetadataFilter {
+    fn matches(&self, path: &str, metadata: &dyn MetadataTrait) -> bool;
+}
+
+/// Path filters combine with the others.
+impl<P: PathFilter> MetadataFilter for P {
+    fn matches(&self, path: &str, _metadata: &dyn MetadataTrait) -> bool {
+        self.is_match(path)
+    }
+}
+
+/// Sizes like `512`, `64k` or `10G`, units being powers of 1024.
+pub fn parse_size(size: &str) -> Result<u64, Error> {
+    let invalid = || Error::InvalidFilter(format!("invalid size: {}", size));
+    let (number, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => size.split_at(i),
+        None => (size, ""),
+    };
+    let multiplier: u64 = match unit {
+        "" | "b" | "B" => 1,
+        "k" | "K" => 1 << 10,
+        "m" | "M" => 1 << 20,
+        "g" | "G" => 1 << 30,
+        "t" | "T" => 1 << 40,
+        "p" | "P" => 1 << 50,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(invalid)
+}
+
+/// Lengths within inclusive bounds. Units are powers of 1024.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeFilter {
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+impl SizeFilter {
+    pub fn new(min: Option<u64>, max: Option<u64>) -> SizeFilter {
+        SizeFilter { min, max }
+    }
+
+    /// `+1G` is more than 1G, `-10k` less than 10k, `1M..1G` between both included and
+    /// `100` exactly 100 bytes.
+    pub fn parse(size: &str) -> Result<SizeFilter, Error> {
+        if let Some(i) = size.find("..") {
+            let (min, max) = (&size[..i], &size[i + 2..]);
+            let bound = |s: &str| match s {
+                "" => Ok(None),
+                s => parse_size(s).map(Some),
+            };
+            return Ok(SizeFilter::new(bound(min)?, bound(max)?));
+        }
+        if let Some(size) = size.strip_prefix('+') {
+            let size = parse_size(size)?;
+            return Ok(SizeFilter::new(size.checked_add(1), None));
+        }
+        if let Some(size) = size.strip_prefix('-') {
+            return match parse_size(size)? {
+                0 => Err(Error::InvalidFilter(String::from(
+                    "nothing is smaller than 0",
+                ))),
+                size => Ok(SizeFilter::new(None, Some(size - 1))),
+            };
+        }
+        let size = parse_size(size)?;
+        Ok(SizeFilter::new(Some(size), Some(size)))
+    }
+}
+
+impl MetadataFilter for SizeFilter {
+    fn matches(&self, _path: &str, metadata: &dyn MetadataTrait) -> bool {
+        let len = metadata.len();
+        self.min.is_none_or(|min| len >= min) && self.max.is_none_or(|max| len <= max)
+    }
+}
+
+fn parse_duration(duration: &str) -> Result<Duration, Error> {
+    let invalid = || Error::InvalidFilter(format!("invalid duration: {}", duration));
+    let (number, unit) = match duration.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => duration.split_at(i),
+        None => (duration, "d"),
+    };
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(seconds))
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+fn ago(age: Duration) -> Result<SystemTime, Error> {
+    SystemTime::now()
+        .checked_sub(age)
+        .ok_or_else(|| Error::InvalidFilter(format!("age out of range: {:?}", age)))
+}
+
+/// Modification times before or after a point in time, entries without one never match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtimeFilter {
+    before: Option<SystemTime>,
+    after: Option<SystemTime>,
+}
+
+impl MtimeFilter {
+    pub fn before(time: SystemTime) -> MtimeFilter {
+        MtimeFilter {
+            before: Some(time),
+            after: None,
+        }
+    }
+
+    pub fn after(time: SystemTime) -> MtimeFilter {
+        MtimeFilter {
+            before: None,
+            after: Some(time),
+        }
+    }
+
+    pub fn older_than(age: Duration) -> Result<MtimeFilter, Error> {
+        ago(age).map(MtimeFilter::before)
+    }
+
+    pub fn newer_than(age: Duration) -> Result<MtimeFilter, Error> {
+        ago(age).map(MtimeFilter::after)
+    }
+
+    /// `+30d` is older than 30 days, `-2h` newer than 2 hours. Units are `s`, `m`, `h`,
+    /// `d` (the default) and `w`.
+    pub fn parse(age: &str) -> Result<MtimeFilter, Error> {
+        if let Some(age) = age.strip_prefix('+') {
+            MtimeFilter::older_than(parse_duration(age)?)
+        } else if let Some(age) = age.strip_prefix('-') {
+            MtimeFilter::newer_than(parse_duration(age)?)
+        } else {
+            Err(Error::InvalidFilter(format!(
+                "age must start with + or -: {}",
+                age
+            )))
+        }
+    }
+}
+
+impl MetadataFilter for MtimeFilter {
+    fn matches(&self, _path: &str, metadata: &dyn MetadataTrait) -> bool {
+        match metadata.modified() {
+            Some(modified) => {
+                self.before.is_none_or(|before| modified < before)
+                    && self.after.is_none_or(|after| modified > after)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Entries owned by a user or a group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnerFilter {
+    User(String),
+    Group(String),
+}
+
+impl OwnerFilter {
+    pub fn user(user: &str) -> OwnerFilter {
+        OwnerFilter::User(user.to_owned())
+    }
+
+    pub fn group(group: &str) -> OwnerFilter {
+        OwnerFilter::Group(group.to_owned())
+    }
+}
+
+impl MetadataFilter for OwnerFilter {
+    fn matches(&self, _path: &str, metadata: &dyn MetadataTrait) -> bool {
+        match *self {
+            OwnerFilter::User(ref user) => metadata.owner().as_ref() == Some(user),
+            OwnerFilter::Group(ref group) => metadata.group().as_ref() == Some(group),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeFilter(pub FileType);
+
+impl TypeFilter {
+    /// `f`, `d` or `l`, like `find -type`.
+    pub fn parse(file_type: &str) -> Result<TypeFilter, Error> {
+        match file_type {
+            "f" => Ok(TypeFilter(FileType::File)),
+            "d" => Ok(TypeFilter(FileType::Dir)),
+            "l" => Ok(TypeFilter(FileType::Symlink)),
+            _ => Err(Error::InvalidFilter(format!("unknown type: {}", file_type))),
+        }
+    }
+}
+
+impl MetadataFilter for TypeFilter {
+    fn matches(&self, _path: &str, metadata: &dyn MetadataTrait) -> bool {
+        match self.0 {
+            FileType::File => !metadata.is_dir() && !metadata.is_symlink(),
+            FileType::Dir => metadata.is_dir(),
+            FileType::Symlink => metadata.is_symlink(),
+        }
+    }
+}
+
+/// Filters combined are `Send` and `Sync`, for the parallel walks too.
+pub struct And(pub Vec<Box<dyn MetadataFilter + Send + Sync>>);
+
+impl MetadataFilter for And {
+    fn matches(&self, path: &str, metadata: &dyn MetadataTrait) -> bool {
+        self.0.iter().all(|f| f.matches(path, metadata))
+    }
+}
+
+pub struct Or(pub Vec<Box<dyn MetadataFilter + Send + Sync>>);
+
+impl MetadataFilter for Or {
+    fn matches(&self, path: &str, metadata: &dyn MetadataTrait) -> bool {
+        self.0.iter().any(|f| f.matches(path, metadata))
+    }
+}
+
+pub struct Not(pub Box<dyn MetadataFilter + Send + Sync>);
+
+impl MetadataFilter for Not {
+    fn matches(&self, path: &str, metadata: &dyn MetadataTrait) -> bool {
+        !self.0.matches(path, metadata)
+    }
+}
@@ -0,0 +1,176 @@
+//! Gitignore-style exclusion rules: one pattern per line, `#` starting a comment, `!`
+//! including back what an earlier rule excluded, a trailing `/` for directories only
+//! and a leading or inner `/` anchoring the pattern to the directory of the rules.
+//! Other patterns match the name at any depth. The last matching rule wins.
+
+use err::Error;
+use glob;
+use pattern;
+use std::path::Path;
+
+/// Name of the files holding the rules of their directory.
+pub const IGNORE_FILE: &str = ".hdfsignore";
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: glob::Pattern,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Rule {
+    fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            case_sensitive: true,
+            require_literal_leading_dot: false,
+        };
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.pattern.matches_path_with(relative, &options)
+        } else {
+            relative
+                .file_name()
+                .is_some_and(|name| self.pattern.matches_path_with(Path::new(name), &options))
+        }
+    }
+}
+
+/// Rules relative to the directory they apply to.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+    /// The lines the rules come from, to save them with a checkpoint.
+    lines: Vec<String>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> IgnoreRules {
+        IgnoreRules::default()
+    }
+
+    /// Reads the content of an ignore file.
+    pub fn parse(content: &str) -> Result<IgnoreRules, Error> {
+        let mut rules = IgnoreRules::new();
+        for line in content.lines() {
+            rules.add(line)?;
+        }
+        Ok(rules)
+    }
+
+    /// Adds a line of an ignore file, blank lines and comments are skipped.
+    pub fn add(&mut self, line: &str) -> Result<(), Error> {
+        let mut line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let original = line;
+        let negated = line.starts_with('!');
+        // A backslash keeps a leading `!` or `#` literal
+        if negated || line.starts_with("\\!") || line.starts_with("\\#") {
+            line = &line[1..];
+        }
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+        if line.is_empty() {
+            return Ok(());
+        }
+        self.rules.push(Rule {
+            pattern: glob::Pattern::new(&pattern::translate(line)?)?,
+            negated,
+            dir_only,
+            anchored,
+        });
+        self.lines.push(original.to_owned());
+        Ok(())
+    }
+
+    pub(crate) fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether the last rule matching `relative` excludes it, `None` when none match.
+    pub fn matched(&self, relative: &Path, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(relative, is_dir))
+            .map(|rule| !rule.negated)
+    }
+
+    /// An entry is excluded with its directory, whatever the rules say about it.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let excluded_parent = relative
+            .ancestors()
+            .skip(1)
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .any(|parent| self.matched(parent, true) == Some(true));
+        excluded_parent || self.matched(relative, is_dir) == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(lines: &[&str]) -> IgnoreRules {
+        IgnoreRules::parse(&lines.join("\n")).unwrap()
+    }
+
+    #[test]
+    fn test_names_match_at_any_depth() {
+        let rules = rules(&["# Hadoop markers", "_SUCCESS", "*.crc", "", "*~"]);
+        assert!(rules.is_ignored(Path::new("_SUCCESS"), false));
+        assert!(rules.is_ignored(Path::new("a/b/.part-0.crc"), false));
+        assert!(rules.is_ignored(Path::new("notes.txt~"), false));
+        assert!(!rules.is_ignored(Path::new("a/part-0"), false));
+        assert!(!rules.is_ignored(Path::new("# Hadoop markers"), false));
+    }
+
+    #[test]
+    fn test_directories_only() {
+        let rules = rules(&["_temporary/"]);
+        assert!(rules.is_ignored(Path::new("out/_temporary"), true));
+        assert!(!rules.is_ignored(Path::new("out/_temporary"), false));
+        assert!(rules.is_ignored(Path::new("out/_temporary/0/part-0"), false));
+    }
+
+    #[test]
+    fn test_anchored() {
+        let rules = rules(&["/build", "logs/*.log", "**/cache"]);
+        assert!(rules.is_ignored(Path::new("build"), true));
+        assert!(!rules.is_ignored(Path::new("src/build"), true));
+        assert!(rules.is_ignored(Path::new("logs/app.log"), false));
+        assert!(!rules.is_ignored(Path::new("logs/old/app.log"), false));
+        assert!(!rules.is_ignored(Path::new("a/logs/app.log"), false));
+        assert!(rules.is_ignored(Path::new("cache"), true));
+        assert!(rules.is_ignored(Path::new("a/b/cache"), true));
+    }
+
+    #[test]
+    fn test_negations() {
+        let rules = rules(&["*.log", "!keep.log", "tmp/", "!tmp/keep", "\\!bang"]);
+        assert!(rules.is_ignored(Path::new("a.log"), false));
+        assert!(!rules.is_ignored(Path::new("a/keep.log"), false));
+        assert_eq!(Some(false), rules.matched(Path::new("keep.log"), false));
+        assert_eq!(None, rules.matched(Path::new("a.txt"), false));
+        // Nothing comes back from an excluded directory
+        assert!(rules.is_ignored(Path::new("tmp/keep"), false));
+        assert!(rules.is_ignored(Path::new("!bang"), false));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(IgnoreRules::parse("[ab").is_err());
+        assert!(IgnoreRules::parse("a\\").is_err());
+    }
+}
@@ -1,12 +1,13 @@
 #[macro_use]
 extern crate log;
-#[cfg(test)]
-extern crate env_logger;
 extern crate glob;
+extern crate regex;
 
+pub mod checkpoint;
 pub mod err;
 pub mod filter;
+pub mod ignore;
 pub mod linuxfs;
-#[cfg(test)]
-mod tests;
+pub mod parallel;
+pub mod pattern;
 pub mod walk;
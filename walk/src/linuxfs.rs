@@ -1,11 +1,36 @@
 use err;
+use std::cell::OnceCell;
 use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::PathBuf;
+use std::time::SystemTime;
 use walk::{DirEntryTrait, FileSystem, MetadataTrait};
 
 pub struct ReadDirWrapper(fs::ReadDir);
 pub struct MetadataWrapper(fs::Metadata);
-pub struct DirEntryWrapper(fs::DirEntry);
+
+/// The file type comes with the listing, the other attributes need a `lstat`, made
+/// once on first use.
+pub struct DirEntryWrapper {
+    entry: fs::DirEntry,
+    metadata: OnceCell<Option<fs::Metadata>>,
+}
+
+impl DirEntryWrapper {
+    fn new(entry: fs::DirEntry) -> DirEntryWrapper {
+        DirEntryWrapper {
+            entry,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    fn metadata(&self) -> Option<&fs::Metadata> {
+        self.metadata
+            .get_or_init(|| self.entry.metadata().ok())
+            .as_ref()
+    }
+}
 
 impl Iterator for ReadDirWrapper {
     type Item = Result<DirEntryWrapper, err::Error>;
@@ -13,7 +38,7 @@ impl Iterator for ReadDirWrapper {
     fn next(&mut self) -> Option<Self::Item> {
         self.0
             .next()
-            .map(|r| r.map(DirEntryWrapper).map_err(err::Error::from))
+            .map(|r| r.map(DirEntryWrapper::new).map_err(err::Error::from))
     }
 }
 
@@ -21,15 +46,96 @@ impl MetadataTrait for MetadataWrapper {
     fn is_dir(&self) -> bool {
         self.0.is_dir()
     }
+
+    fn len(&self) -> u64 {
+        self.0.len()
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        self.0.modified().ok()
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        self.0.accessed().ok()
+    }
+
+    fn permissions(&self) -> Option<u32> {
+        Some(self.0.permissions().mode() & 0o7777)
+    }
+
+    fn owner(&self) -> Option<String> {
+        Some(self.0.uid().to_string())
+    }
+
+    fn group(&self) -> Option<String> {
+        Some(self.0.gid().to_string())
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.0.file_type().is_symlink()
+    }
+
+    fn file_id(&self) -> Option<(u64, u64)> {
+        Some((self.0.dev(), self.0.ino()))
+    }
 }
 
 impl DirEntryTrait for DirEntryWrapper {
     fn path(&self) -> PathBuf {
-        self.0.path()
+        self.entry.path()
     }
+}
 
+impl MetadataTrait for DirEntryWrapper {
     fn is_dir(&self) -> bool {
-        self.0.path().is_dir()
+        // Links are not followed, a link to a directory is not one
+        self.entry
+            .file_type()
+            .map(|file_type| file_type.is_dir())
+            .unwrap_or(false)
+    }
+
+    fn len(&self) -> u64 {
+        self.metadata().map_or(0, |m| m.len())
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        self.metadata().and_then(|m| m.modified().ok())
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        self.metadata().and_then(|m| m.accessed().ok())
+    }
+
+    fn permissions(&self) -> Option<u32> {
+        self.metadata().map(|m| m.permissions().mode() & 0o7777)
+    }
+
+    fn owner(&self) -> Option<String> {
+        self.metadata().map(|m| m.uid().to_string())
+    }
+
+    fn group(&self) -> Option<String> {
+        self.metadata().map(|m| m.gid().to_string())
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.entry
+            .file_type()
+            .map(|file_type| file_type.is_symlink())
+            .unwrap_or(false)
+    }
+
+    fn symlink_target(&self) -> Option<PathBuf> {
+        if self.is_symlink() {
+            fs::read_link(self.entry.path()).ok()
+        } else {
+            None
+        }
+    }
+
+    fn file_id(&self) -> Option<(u64, u64)> {
+        self.metadata().map(|m| (m.dev(), m.ino()))
     }
 }
 
@@ -57,4 +163,15 @@ impl FileSystem for LinuxFS {
     fn metadata(&self, path: &PathBuf) -> Result<Self::Metadata, Self::Error> {
         Ok(MetadataWrapper(fs::metadata(path)?))
     }
+
+    fn read_to_string(&self, path: &PathBuf) -> Result<String, Self::Error> {
+        Ok(fs::read_to_string(path)?)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        match *error {
+            err::Error::IoError(ref e) => e.kind() == ErrorKind::NotFound,
+            _ => false,
+        }
+    }
 }
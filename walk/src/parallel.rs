@@ -0,0 +1,569 @@
+use checkpoint::ParallelCheckpoint;
+use err;
+use err::WalkError;
+use filter::{MetadataFilter, PathFilter};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use walk::{DirEntryTrait, ErrorPolicy, FileSystem, GlobRoot, MetadataTrait, WalkItem};
+
+const DEFAULT_THREADS: usize = 8;
+const DEFAULT_QUEUE_SIZE: usize = 1024;
+
+/// Builds a walk listing several directories at once, for backends where each listing
+/// is a round trip.
+pub struct ParallelWalkBuilder<'a, T: FileSystem + Sync> {
+    fs: &'a T,
+    path: Option<PathBuf>,
+    filters: Vec<Box<dyn PathFilter + Send + Sync>>,
+    metadata_filters: Vec<Box<dyn MetadataFilter + Send + Sync>>,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    threads: usize,
+    queue_size: usize,
+    sorted: bool,
+    error_policy: ErrorPolicy,
+    checkpoint: Option<ParallelCheckpoint>,
+}
+
+impl<'a, T: FileSystem + Sync> ParallelWalkBuilder<'a, T> {
+    pub fn new(file_system: &'a T) -> ParallelWalkBuilder<'a, T> {
+        ParallelWalkBuilder {
+            fs: file_system,
+            path: None,
+            filters: vec![],
+            metadata_filters: vec![],
+            min_depth: 0,
+            max_depth: None,
+            threads: DEFAULT_THREADS,
+            queue_size: DEFAULT_QUEUE_SIZE,
+            sorted: false,
+            error_policy: ErrorPolicy::Skip,
+            checkpoint: None,
+        }
+    }
+
+    pub fn build(self) -> Result<ParallelWalk<'a, T>, err::Error> {
+        let path = self.path.ok_or(err::Error::NoPathDefined)?;
+        let current_dir = if path.is_relative() {
+            self.fs.current_dir().ok()
+        } else {
+            None
+        };
+        let mut roots = GlobRoot::expand(&path, current_dir.as_deref())?;
+        for root in &mut roots {
+            root.max_depth = match (root.max_depth, self.max_depth) {
+                (Some(glob), Some(max)) => Some(glob.min(max)),
+                (glob, max) => glob.or(max),
+            };
+        }
+        if let Some(ref checkpoint) = self.checkpoint {
+            if checkpoint.path != path {
+                return Err(err::Error::InvalidCheckpoint(format!(
+                    "checkpoint of {}",
+                    checkpoint.path.display()
+                )));
+            }
+            if checkpoint.sorted != self.sorted {
+                return Err(err::Error::InvalidCheckpoint(String::from(
+                    "checkpoint of a walk sorted otherwise",
+                )));
+            }
+            if checkpoint.roots_returned > roots.len()
+                || checkpoint.dirs.iter().any(|dir| dir.root >= roots.len())
+            {
+                return Err(err::Error::InvalidCheckpoint(String::from(
+                    "more roots than the path has",
+                )));
+            }
+        }
+        Ok(ParallelWalk {
+            fs: self.fs,
+            path,
+            roots,
+            filters: self.filters,
+            metadata_filters: self.metadata_filters,
+            min_depth: self.min_depth,
+            threads: self.threads.max(1),
+            queue_size: self.queue_size.max(1),
+            sorted: self.sorted,
+            error_policy: self.error_policy,
+            checkpoint: self.checkpoint,
+        })
+    }
+
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn add_filter(mut self, path_filter: Box<dyn PathFilter + Send + Sync>) -> Self {
+        self.filters.push(path_filter);
+        self
+    }
+
+    /// Entries not matching are not returned, directories are still walked through.
+    pub fn add_metadata_filter(
+        mut self,
+        metadata_filter: Box<dyn MetadataFilter + Send + Sync>,
+    ) -> Self {
+        self.metadata_filters.push(metadata_filter);
+        self
+    }
+
+    /// Entries above `depth` are walked through but not returned, the root being at 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Nothing below `depth` is listed, on top of the limit set by the pattern.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Number of listings running at once.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Number of entries listed ahead of the consumer before the listings wait.
+    pub fn queue_size(mut self, queue_size: usize) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    /// Returns the entries in the order of a depth first walk sorted by name. They are
+    /// all kept until the walk is over, errors coming first.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Errors are returned and skipped by default.
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Goes on from where the checkpoint was taken, returning only what came after.
+    /// The other settings have to be the same as those of the walk checkpointed.
+    pub fn resume(mut self, checkpoint: ParallelCheckpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+}
+
+/// The alternatives of the path pattern are walked at the same time.
+pub struct ParallelWalk<'a, T: FileSystem + Sync> {
+    fs: &'a T,
+    path: PathBuf,
+    roots: Vec<GlobRoot>,
+    filters: Vec<Box<dyn PathFilter + Send + Sync>>,
+    metadata_filters: Vec<Box<dyn MetadataFilter + Send + Sync>>,
+    min_depth: usize,
+    threads: usize,
+    queue_size: usize,
+    sorted: bool,
+    error_policy: ErrorPolicy,
+    checkpoint: Option<ParallelCheckpoint>,
+}
+
+/// A directory to list, the index of its root and its path identifying it.
+#[derive(Debug, Clone)]
+pub(crate) struct Dir {
+    pub(crate) root: usize,
+    pub(crate) depth: usize,
+    pub(crate) path: PathBuf,
+    /// The last entry returned, those up to it are skipped.
+    pub(crate) after: Option<PathBuf>,
+}
+
+/// What the workers send: the entries of a listing, by name, then its end.
+enum Message<E> {
+    /// An entry returned, or queued to be listed, or both.
+    Entry {
+        dir: (usize, PathBuf),
+        path: PathBuf,
+        item: Option<WalkItem>,
+        queued: Option<Dir>,
+    },
+    /// All the entries of the directory were sent, or its listing failed.
+    Listed((usize, PathBuf), Option<WalkError<E>>),
+}
+
+type Sender<E> = SyncSender<Message<E>>;
+
+/// Where a parallel walk is, after the last entry returned.
+pub struct Progress {
+    path: PathBuf,
+    roots_returned: usize,
+    /// The directories whose entries were not all returned.
+    dirs: BTreeMap<(usize, PathBuf), Dir>,
+    sorted: bool,
+    sorted_errors: usize,
+    sorted_after: Option<PathBuf>,
+}
+
+impl Progress {
+    /// State after the last entry returned. Saving it before acting on the entries
+    /// returned since the previous one makes sure none is returned twice after a
+    /// restart.
+    pub fn checkpoint(&self) -> ParallelCheckpoint {
+        ParallelCheckpoint {
+            path: self.path.clone(),
+            roots_returned: self.roots_returned,
+            dirs: self.dirs.values().cloned().collect(),
+            sorted: self.sorted,
+            sorted_errors: self.sorted_errors,
+            sorted_after: self.sorted_after.clone(),
+        }
+    }
+}
+
+/// Directories left to list, shared by the workers.
+struct Queue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+struct QueueState {
+    dirs: VecDeque<Dir>,
+    /// Listings in progress, which may queue more directories.
+    active: usize,
+    closed: bool,
+}
+
+impl Queue {
+    fn push(&self, dir: Dir) {
+        self.state.lock().unwrap().dirs.push_back(dir);
+        self.changed.notify_one();
+    }
+
+    /// Waits for a directory, `None` once all are listed.
+    fn pop(&self) -> Option<Dir> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(dir) = state.dirs.pop_front() {
+                state.active += 1;
+                return Some(dir);
+            }
+            if state.active == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn done(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.active -= 1;
+        if state.active == 0 && state.dirs.is_empty() {
+            self.changed.notify_all();
+        }
+    }
+
+    /// Stops the workers, when nobody reads the results anymore.
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+}
+
+impl<'a, T> ParallelWalk<'a, T>
+where
+    T: FileSystem + Sync,
+    T::Error: Send,
+{
+    /// Walks and calls `f` on each entry from the calling thread, returns the errors
+    /// kept by the `Collect` policy.
+    pub fn run<F>(self, mut f: F) -> Vec<WalkError<T::Error>>
+    where
+        F: FnMut(Result<WalkItem, WalkError<T::Error>>),
+    {
+        self.run_with_progress(|item, _| f(item))
+    }
+
+    /// Like `run`, `f` being given the progress of the walk with each entry, to take
+    /// checkpoints. A sorted walk resumed starts over and skips what was returned.
+    pub fn run_with_progress<F>(mut self, mut f: F) -> Vec<WalkError<T::Error>>
+    where
+        F: FnMut(Result<WalkItem, WalkError<T::Error>>, &Progress),
+    {
+        let checkpoint = self.checkpoint.take();
+        let (resumed, sorted_resumed) = match checkpoint {
+            Some(checkpoint) if self.sorted => (None, Some(checkpoint)),
+            checkpoint => (checkpoint, None),
+        };
+        let mut progress = Progress {
+            path: self.path.clone(),
+            roots_returned: 0,
+            dirs: BTreeMap::new(),
+            sorted: self.sorted,
+            sorted_errors: 0,
+            sorted_after: None,
+        };
+        let (sender, receiver) = sync_channel(self.queue_size);
+        let queue = Queue {
+            state: Mutex::new(QueueState {
+                dirs: VecDeque::new(),
+                active: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        };
+
+        let mut roots = vec![];
+        for (index, glob_root) in self.roots.iter().enumerate() {
+            let metadata = match self.fs.metadata(&glob_root.root) {
+                Ok(metadata) => metadata,
+                Err(ref e) if self.fs.is_not_found(e) => continue,
+                Err(e) => {
+                    roots.push(Err(WalkError::new(glob_root.root.clone(), e)));
+                    continue;
+                }
+            };
+            let is_dir = metadata.is_dir();
+            if self.is_returned(glob_root, 0, &glob_root.root, &metadata) {
+                roots.push(Ok(WalkItem::new(glob_root.root.clone(), is_dir, 0)));
+            }
+            if resumed.is_none() && is_dir && descends(glob_root, 0) {
+                let dir = Dir {
+                    root: index,
+                    depth: 0,
+                    path: glob_root.root.clone(),
+                    after: None,
+                };
+                progress.dirs.insert((index, dir.path.clone()), dir.clone());
+                queue.push(dir);
+            }
+        }
+        if let Some(checkpoint) = resumed {
+            progress.roots_returned = checkpoint.roots_returned;
+            roots.drain(..checkpoint.roots_returned.min(roots.len()));
+            for dir in checkpoint.dirs {
+                progress
+                    .dirs
+                    .insert((dir.root, dir.path.clone()), dir.clone());
+                queue.push(dir);
+            }
+        }
+
+        let walk = &self;
+        let queue = &queue;
+        let mut errors = vec![];
+        let mut sorted = vec![];
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                let sender = sender.clone();
+                scope.spawn(move || walk.work(queue, &sender));
+            }
+            drop(sender);
+
+            for item in roots {
+                progress.roots_returned += 1;
+                let item = match item {
+                    Err(e) => match self.error_policy {
+                        ErrorPolicy::Abort => {
+                            f(Err(e), &progress);
+                            return;
+                        }
+                        ErrorPolicy::Skip => Err(e),
+                        ErrorPolicy::Collect => {
+                            errors.push(e);
+                            continue;
+                        }
+                    },
+                    item => item,
+                };
+                if self.sorted {
+                    sorted.push(item);
+                } else {
+                    f(item, &progress);
+                }
+            }
+
+            // Dropping the receiver stops the workers
+            for message in receiver {
+                let item = match message {
+                    Message::Entry {
+                        dir,
+                        path,
+                        item,
+                        queued,
+                    } => {
+                        if let Some(dir) = progress.dirs.get_mut(&dir) {
+                            dir.after = Some(path);
+                        }
+                        if let Some(queued) = queued {
+                            progress
+                                .dirs
+                                .insert((queued.root, queued.path.clone()), queued);
+                        }
+                        match item {
+                            Some(item) => Ok(item),
+                            None => continue,
+                        }
+                    }
+                    Message::Listed(dir, error) => {
+                        progress.dirs.remove(&dir);
+                        match error {
+                            Some(e) => Err(e),
+                            None => continue,
+                        }
+                    }
+                };
+                let item = match item {
+                    Err(e) => match self.error_policy {
+                        ErrorPolicy::Abort => {
+                            f(Err(e), &progress);
+                            break;
+                        }
+                        ErrorPolicy::Skip => Err(e),
+                        ErrorPolicy::Collect => {
+                            errors.push(e);
+                            continue;
+                        }
+                    },
+                    item => item,
+                };
+                if self.sorted {
+                    sorted.push(item);
+                } else {
+                    f(item, &progress);
+                }
+            }
+        });
+
+        sorted.sort_by_key(|item| item.as_ref().ok().map(WalkItem::path));
+        let (errors_returned, after) = sorted_resumed
+            .map(|checkpoint| (checkpoint.sorted_errors, checkpoint.sorted_after))
+            .unwrap_or((0, None));
+        for item in sorted {
+            let returned = match item {
+                Err(_) => {
+                    progress.sorted_errors += 1;
+                    progress.sorted_errors <= errors_returned
+                }
+                Ok(ref item) => {
+                    let path = item.path();
+                    let returned = after.as_ref().is_some_and(|after| path <= *after);
+                    progress.sorted_after = Some(path);
+                    returned
+                }
+            };
+            if !returned {
+                f(item, &progress);
+            }
+        }
+        errors
+    }
+
+    fn work(&self, queue: &Queue, sender: &Sender<T::Error>) {
+        while let Some(dir) = queue.pop() {
+            let listed = self.list(queue, sender, &dir);
+            queue.done();
+            if !listed {
+                queue.close();
+            }
+        }
+    }
+
+    /// Returns false when the results are not read anymore.
+    fn list(&self, queue: &Queue, sender: &Sender<T::Error>, dir: &Dir) -> bool {
+        let glob_root = &self.roots[dir.root];
+        let key = (dir.root, dir.path.clone());
+        let entries: Result<Vec<_>, _> = self.fs.read_dir(&dir.path).and_then(|r| r.collect());
+        let mut entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                let error = WalkError::new(dir.path.clone(), e);
+                return sender.send(Message::Listed(key, Some(error))).is_ok();
+            }
+        };
+        // By name, for a checkpoint to tell what was returned from the last entry
+        entries.sort_by_cached_key(|entry| entry.path());
+        let depth = dir.depth + 1;
+        for entry in entries {
+            let path = entry.path();
+            if dir.after.as_ref().is_some_and(|after| path <= *after) {
+                continue;
+            }
+            let is_dir = entry.is_dir();
+            if is_dir && !is_valid_dir(glob_root, &path) {
+                continue;
+            }
+            let item = if self.is_returned(glob_root, depth, &path, &entry) {
+                Some(WalkItem::new(path.clone(), is_dir, depth))
+            } else {
+                None
+            };
+            let queued = if is_dir && descends(glob_root, depth) {
+                Some(Dir {
+                    root: dir.root,
+                    depth,
+                    path: path.clone(),
+                    after: None,
+                })
+            } else {
+                None
+            };
+            if item.is_none() && queued.is_none() {
+                continue;
+            }
+            let message = Message::Entry {
+                dir: key.clone(),
+                path,
+                item,
+                queued: queued.clone(),
+            };
+            // Sent first, the entries of the directory queued come after it
+            if sender.send(message).is_err() {
+                return false;
+            }
+            if let Some(queued) = queued {
+                queue.push(queued);
+            }
+        }
+        sender.send(Message::Listed(key, None)).is_ok()
+    }
+
+    fn is_returned<M: MetadataTrait>(
+        &self,
+        glob_root: &GlobRoot,
+        depth: usize,
+        path: &Path,
+        metadata: &M,
+    ) -> bool {
+        depth >= self.min_depth
+            && path.to_str().is_some_and(|path| {
+                glob_root
+                    .glob
+                    .as_ref()
+                    .is_none_or(|glob| glob.is_match(path))
+                    && self.filters.iter().all(|f| f.is_match(path))
+                    && self
+                        .metadata_filters
+                        .iter()
+                        .all(|f| f.matches(path, metadata))
+            })
+    }
+}
+
+fn is_valid_dir(glob_root: &GlobRoot, path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| glob_root.dir_filter.is_match(path))
+}
+
+fn descends(glob_root: &GlobRoot, depth: usize) -> bool {
+    glob_root
+        .max_depth
+        .is_none_or(|max_depth| depth < max_depth)
+}
@@ -0,0 +1,207 @@
+//! Hadoop glob syntax: `?`, `*`, classes like `[a-z]` or `[^a-z]`, alternatives like
+//! `{a,b}` which may nest, and `\` escaping the next character. `**` also walks through
+//! directories.
+
+use err::Error;
+use glob;
+
+fn error(pos: usize, msg: &'static str) -> Error {
+    Error::PatternError(glob::PatternError { pos, msg })
+}
+
+/// Expands the alternatives, one pattern each, in order and without duplicates.
+pub fn expand_braces(pattern: &str) -> Result<Vec<String>, Error> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut patterns = vec![];
+    for pattern in expand(&chars, 0)? {
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+    Ok(patterns)
+}
+
+fn expand(chars: &[char], offset: usize) -> Result<Vec<String>, Error> {
+    let mut open = None;
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '{' if !in_class => {
+                open = Some(i);
+                break;
+            }
+            '}' if !in_class => return Err(error(offset + i, "unmatched }")),
+            _ => {}
+        }
+        i += 1;
+    }
+    let open = match open {
+        Some(open) => open,
+        None => return Ok(vec![chars.iter().collect()]),
+    };
+
+    // Bounds of the alternatives, the braces included
+    let mut bounds = vec![open];
+    let mut nested = 0;
+    let mut in_class = false;
+    let mut i = open + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '{' if !in_class => nested += 1,
+            '}' if !in_class && nested > 0 => nested -= 1,
+            '}' if !in_class => break,
+            ',' if !in_class && nested == 0 => bounds.push(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    if i >= chars.len() {
+        return Err(error(offset + open, "unclosed {"));
+    }
+    bounds.push(i);
+
+    let prefix: String = chars[..open].iter().collect();
+    let suffixes = expand(&chars[i + 1..], offset + i + 1)?;
+    let mut patterns = vec![];
+    for alternative in bounds.windows(2) {
+        let start = alternative[0] + 1;
+        for middle in expand(&chars[start..alternative[1]], offset + start)? {
+            for suffix in &suffixes {
+                patterns.push(format!("{}{}{}", prefix, middle, suffix));
+            }
+        }
+    }
+    Ok(patterns)
+}
+
+/// Translates a pattern without alternatives to the syntax of `glob::Pattern`.
+pub fn translate(pattern: &str) -> Result<String, Error> {
+    let mut translated = String::new();
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, c)) => translated.push_str(&glob::Pattern::escape(&c.to_string())),
+                None => return Err(error(i, "nothing to escape")),
+            },
+            '[' => {
+                translated.push('[');
+                if let Some(&(_, '^')) | Some(&(_, '!')) = chars.peek() {
+                    chars.next();
+                    translated.push('!');
+                }
+                loop {
+                    match chars.next() {
+                        Some((_, ']')) => break,
+                        Some((_, '\\')) => {
+                            if let Some((_, c)) = chars.next() {
+                                translated.push(c)
+                            }
+                        }
+                        Some((_, c)) => translated.push(c),
+                        None => return Err(error(i, "unclosed [")),
+                    }
+                }
+                translated.push(']');
+            }
+            c => translated.push(c),
+        }
+    }
+    Ok(translated)
+}
+
+/// Position of the first wildcard, escaped characters being literal.
+pub fn first_wildcard(pattern: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in pattern.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '*' | '?' | '[' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Removes the escaping of a part without wildcards.
+pub fn unescape(literal: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Escapes what would be special in a pattern.
+pub fn escape(literal: &str) -> String {
+    let mut escaped = String::new();
+    for c in literal.chars() {
+        if let '*' | '?' | '[' | ']' | '{' | '}' | '\\' | ',' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(vec!["/a/b"], expand_braces("/a/b").unwrap());
+        assert_eq!(
+            vec!["/logs/2024-01-*", "/logs/2024-02-*"],
+            expand_braces("/logs/2024-{01,02}-*").unwrap()
+        );
+        assert_eq!(
+            vec!["/a/x1", "/a/x2", "/a/y", "/b/x1", "/b/x2", "/b/y"],
+            expand_braces("/{a,b}/{x{1,2},y}").unwrap()
+        );
+        assert_eq!(vec!["/a", "/a/b"], expand_braces("/a{,/b}").unwrap());
+        assert_eq!(vec!["/a", "/b"], expand_braces("/{a,b,a}").unwrap());
+        // Escaped characters and classes are literal
+        assert_eq!(
+            vec!["/\\{a\\,b\\}", "/[{]"],
+            expand_braces("/{\\{a\\,b\\},[{]}").unwrap()
+        );
+
+        assert!(expand_braces("/{a,b").is_err());
+        assert!(expand_braces("/a}").is_err());
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!("/a/*/b?", translate("/a/*/b?").unwrap());
+        assert_eq!("/[!0-9]/[ab]", translate("/[^0-9]/[ab]").unwrap());
+        assert_eq!("/[!a]", translate("/[!a]").unwrap());
+        assert_eq!("/[*]x[?][[]", translate("/\\*x\\?\\[").unwrap());
+        assert_eq!("/a,b", translate("/a\\,b").unwrap());
+
+        assert!(translate("/[ab").is_err());
+        assert!(translate("/a\\").is_err());
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(Some(3), first_wildcard("/a/[0-9]"));
+        assert_eq!(Some(5), first_wildcard("/a\\*/?"));
+        assert_eq!(None, first_wildcard("/a\\*"));
+        assert_eq!("/a*b", unescape("/a\\*b"));
+        assert_eq!("/a\\*\\{b\\}", escape("/a*{b}"));
+        assert_eq!("/a*{b}", unescape(&escape("/a*{b}")));
+    }
+}
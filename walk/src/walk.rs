@@ -1,8 +1,16 @@
+use checkpoint::Checkpoint;
 use err;
+use err::WalkError;
 use filter::*;
+use ignore::IgnoreRules;
+use pattern;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::path::Path;
 use std::path::PathBuf;
+use std::path::{Component, Path};
+use std::rc::Rc;
+use std::time::SystemTime;
 
 macro_rules! try_opt_res {
     ($e: expr) => {
@@ -13,13 +21,139 @@ macro_rules! try_opt_res {
     };
 }
 
+/// Attributes of a path. Backends return `None` for what they do not know.
+#[allow(clippy::len_without_is_empty)]
 pub trait MetadataTrait {
     fn is_dir(&self) -> bool;
+
+    fn len(&self) -> u64 {
+        0
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Permission bits, like `0o755`.
+    fn permissions(&self) -> Option<u32> {
+        None
+    }
+
+    fn owner(&self) -> Option<String> {
+        None
+    }
+
+    fn group(&self) -> Option<String> {
+        None
+    }
+
+    fn is_symlink(&self) -> bool {
+        false
+    }
+
+    /// Where a link points to, as stored.
+    fn symlink_target(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Device and inode, telling apart directories reached through links.
+    fn file_id(&self) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Copies of each block kept by the backend.
+    fn replication(&self) -> Option<u16> {
+        None
+    }
+}
+
+/// Attributes of an entry taken when it was listed, for walks keeping them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attributes {
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub permissions: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub replication: Option<u16>,
+    pub is_symlink: bool,
+    pub symlink_target: Option<PathBuf>,
+}
+
+impl Attributes {
+    pub fn of<M: MetadataTrait>(metadata: &M) -> Attributes {
+        let is_symlink = metadata.is_symlink();
+        Attributes {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified(),
+            accessed: metadata.accessed(),
+            permissions: metadata.permissions(),
+            owner: metadata.owner(),
+            group: metadata.group(),
+            replication: metadata.replication(),
+            is_symlink,
+            symlink_target: if is_symlink {
+                metadata.symlink_target()
+            } else {
+                None
+            },
+        }
+    }
 }
 
-pub trait DirEntryTrait {
+impl MetadataTrait for Attributes {
+    fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    fn accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    fn permissions(&self) -> Option<u32> {
+        self.permissions
+    }
+
+    fn owner(&self) -> Option<String> {
+        self.owner.clone()
+    }
+
+    fn group(&self) -> Option<String> {
+        self.group.clone()
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.is_symlink
+    }
+
+    fn symlink_target(&self) -> Option<PathBuf> {
+        self.symlink_target.clone()
+    }
+
+    fn replication(&self) -> Option<u16> {
+        self.replication
+    }
+}
+
+/// An entry of a listing. Backends whose listings carry the attributes return them
+/// without another call.
+pub trait DirEntryTrait: MetadataTrait {
     fn path(&self) -> PathBuf;
-    fn is_dir(&self) -> bool;
 }
 
 pub trait FileSystem {
@@ -36,12 +170,63 @@ pub trait FileSystem {
     fn exists(&self, path: &PathBuf) -> bool;
     fn read_dir(&self, path: &PathBuf) -> Result<Self::ReadDir, Self::Error>;
     fn metadata(&self, path: &PathBuf) -> Result<Self::Metadata, Self::Error>;
+    /// Content of a small text file, like the ignore files. Backends that cannot read
+    /// files see every ignore file as empty.
+    fn read_to_string(&self, _path: &PathBuf) -> Result<String, Self::Error> {
+        Ok(String::new())
+    }
+
+    /// Whether `error` means the path does not exist: missing roots walk nothing and
+    /// links followed to missing paths are returned as files. Backends that cannot
+    /// tell report every failure.
+    fn is_not_found(&self, _error: &Self::Error) -> bool {
+        false
+    }
+}
+
+/// Order in which directories are walked through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    /// A directory is followed by its whole subtree.
+    DepthFirst,
+    /// A level is returned before the next one.
+    BreadthFirst,
+}
+
+/// What a walk does when the file system fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorPolicy {
+    /// Returns the error and stops.
+    Abort,
+    /// Returns the error and goes on without what could not be listed.
+    Skip,
+    /// Goes on and keeps the errors for [`Walk::errors`](struct.Walk.html#method.errors).
+    Collect,
+}
+
+/// Order of the children of a directory, listings order when not set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Name,
+    Size,
+    Modified,
 }
 
 pub struct WalkBuilder<'a, T: FileSystem> {
     fs: &'a T,
     path: Option<PathBuf>,
     filters: Vec<Box<dyn PathFilter>>,
+    metadata_filters: Vec<Box<dyn MetadataFilter>>,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    order: Order,
+    sort_by: Option<SortBy>,
+    error_policy: ErrorPolicy,
+    follow_links: bool,
+    attributes: bool,
+    excludes: IgnoreRules,
+    ignore_file: Option<String>,
+    checkpoint: Option<Checkpoint>,
 }
 
 impl<'a, T: FileSystem> WalkBuilder<'a, T> {
@@ -50,12 +235,37 @@ impl<'a, T: FileSystem> WalkBuilder<'a, T> {
             fs: file_system,
             path: None,
             filters: vec![],
+            metadata_filters: vec![],
+            min_depth: 0,
+            max_depth: None,
+            order: Order::DepthFirst,
+            sort_by: None,
+            error_policy: ErrorPolicy::Skip,
+            follow_links: false,
+            attributes: false,
+            excludes: IgnoreRules::new(),
+            ignore_file: None,
+            checkpoint: None,
         }
     }
 
     pub fn build(self) -> Result<Walk<'a, T>, err::Error> {
         let path = self.path.ok_or(err::Error::NoPathDefined)?;
-        Walk::new(self.fs, path, self.filters)
+        let mut walk =
+            Walk::with_metadata_filters(self.fs, path, self.filters, self.metadata_filters)?;
+        walk.min_depth = self.min_depth;
+        walk.depth_limit = self.max_depth;
+        walk.order = self.order;
+        walk.sort_by = self.sort_by;
+        walk.error_policy = self.error_policy;
+        walk.follow_links = self.follow_links;
+        walk.attributes = self.attributes;
+        walk.excludes = self.excludes;
+        walk.ignore_file = self.ignore_file;
+        if let Some(checkpoint) = self.checkpoint {
+            walk.restore(checkpoint)?;
+        }
+        Ok(walk)
     }
 
     pub fn add_filter(mut self, path_filter: Box<dyn PathFilter>) -> Self {
@@ -63,31 +273,221 @@ impl<'a, T: FileSystem> WalkBuilder<'a, T> {
         self
     }
 
+    /// Entries not matching are not returned, directories are still walked through.
+    pub fn add_metadata_filter(mut self, metadata_filter: Box<dyn MetadataFilter>) -> Self {
+        self.metadata_filters.push(metadata_filter);
+        self
+    }
+
     pub fn with_path(mut self, path: PathBuf) -> Self {
         self.path = Some(path);
         self
     }
+
+    /// Entries above `depth` are walked through but not returned, the root being at 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Nothing below `depth` is listed, on top of the limit set by the pattern.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Children are returned sorted, ties broken by name.
+    pub fn sort_by(mut self, sort_by: SortBy) -> Self {
+        self.sort_by = Some(sort_by);
+        self
+    }
+
+    /// Errors are returned and skipped by default.
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Walks through links to directories, returned with the attributes of their target.
+    /// A link leading back to a directory being walked is returned as a `SymlinkLoop`
+    /// error instead.
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Keeps the attributes of the entries listed, for
+    /// [`WalkItem::attributes`](struct.WalkItem.html#method.attributes).
+    pub fn attributes(mut self, attributes: bool) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Excluded entries are neither returned nor walked through. The rules are relative
+    /// to the root of the walk and come before those of the ignore files.
+    pub fn exclude(mut self, rules: IgnoreRules) -> Self {
+        self.excludes = rules;
+        self
+    }
+
+    /// Reads the rules of the files with this name, like `.hdfsignore`, for their
+    /// directory and below. Deeper files take precedence.
+    pub fn ignore_file(mut self, name: &str) -> Self {
+        self.ignore_file = Some(name.to_owned());
+        self
+    }
+
+    /// Goes on from where the checkpoint was taken, returning only what came after.
+    /// The other settings have to be the same as those of the walk checkpointed.
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+}
+
+/// Identifies a directory: by device and inode when the backend has them, else by the
+/// path links resolve to.
+#[derive(Debug, PartialEq)]
+pub(crate) enum DirId {
+    Inode(u64, u64),
+    Path(PathBuf),
+}
+
+/// A directory and those above it, only kept when following links.
+#[derive(Debug)]
+pub(crate) struct Ancestor {
+    id: DirId,
+    path: PathBuf,
+    parent: Ancestors,
+}
+
+pub(crate) type Ancestors = Option<Rc<Ancestor>>;
+
+fn find_ancestor<'b>(mut ancestors: &'b Ancestors, id: &DirId) -> Option<&'b Ancestor> {
+    while let Some(ancestor) = ancestors {
+        if ancestor.id == *id {
+            return Some(ancestor);
+        }
+        ancestors = &ancestor.parent;
+    }
+    None
+}
+
+/// Id of a directory, `target` being set when it is reached through a link.
+fn dir_id<M: MetadataTrait>(
+    metadata: &M,
+    path: &Path,
+    target: Option<PathBuf>,
+    parent: &Ancestors,
+) -> DirId {
+    if let Some((dev, ino)) = metadata.file_id() {
+        return DirId::Inode(dev, ino);
+    }
+    let parent = match parent.as_ref().map(|p| &p.id) {
+        Some(DirId::Path(real_path)) => real_path.to_owned(),
+        _ => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    match (target, path.file_name()) {
+        (Some(target), _) => DirId::Path(normalize(&parent.join(target))),
+        (None, Some(name)) => DirId::Path(parent.join(name)),
+        (None, None) => DirId::Path(path.to_path_buf()),
+    }
+}
+
+/// Resolves `.` and `..` without asking the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Nodes carry their depth, the flag tells whether the metadata filters matched,
+/// checked when the entry is listed, and the attributes when they are kept.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    File(usize, PathBuf, bool, Option<Box<Attributes>>),
+    Dir(usize, PathBuf, bool, Ancestors, Option<Box<Attributes>>),
+    /// A link back to the directory of the second path.
+    Loop(PathBuf, PathBuf),
+    /// Walked once the children of a returned directory are, for the events.
+    Exit(usize, PathBuf),
+}
+
+impl Node {
+    fn path(&self) -> &Path {
+        match *self {
+            Node::File(_, ref path, _, _)
+            | Node::Dir(_, ref path, _, _, _)
+            | Node::Loop(ref path, _)
+            | Node::Exit(_, ref path) => path,
+        }
+    }
 }
 
+/// Items of [`Walk::events`](struct.Walk.html#method.events).
 #[derive(Debug)]
-enum Node {
-    File(PathBuf),
-    Dir(usize, PathBuf),
+pub enum WalkEvent {
+    EnterDir(WalkItem),
+    File(WalkItem),
+    /// Comes after the whole subtree of a directory, skipped or not.
+    ExitDir(WalkItem),
 }
 
+/// Walks each alternative of the path pattern in turn, from its own root.
 pub struct Walk<'a, T: FileSystem> {
-    path_stack: Vec<Node>,
+    path_stack: VecDeque<Node>,
     fs: &'a T,
+    path: PathBuf,
+    roots: VecDeque<GlobRoot>,
+    roots_started: usize,
+    min_depth: usize,
+    /// Set by the current root and the builder
     max_depth: Option<usize>,
+    depth_limit: Option<usize>,
+    order: Order,
+    sort_by: Option<SortBy>,
+    /// The last directory returned, listed on the next call unless skipped.
+    pending: Option<(usize, PathBuf, Ancestors)>,
+    exit_events: bool,
+    follow_links: bool,
+    attributes: bool,
+    /// Root of the current alternative, the excludes are relative to it.
+    root: PathBuf,
+    excludes: IgnoreRules,
+    ignore_file: Option<String>,
+    /// Rules of the ignore files found, by directory.
+    ignores: HashMap<PathBuf, IgnoreRules>,
+    error_policy: ErrorPolicy,
+    errors: Vec<WalkError<T::Error>>,
+    /// Failures of roots and links, handed to the error policy before the next node.
+    failures: VecDeque<WalkError<T::Error>>,
+    aborted: bool,
     dir_filer: StartFilter,
+    glob: Option<GlobFilter>,
     filters: Vec<Box<dyn PathFilter>>,
+    metadata_filters: Vec<Box<dyn MetadataFilter>>,
 }
 
 impl<'a, T: FileSystem> fmt::Debug for Walk<'a, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         f.debug_struct("Walk")
             .field("path_stack", &self.path_stack)
+            .field("min_depth", &self.min_depth)
             .field("max_depth", &self.max_depth)
+            .field("order", &self.order)
             .finish()
     }
 }
@@ -98,44 +498,168 @@ impl<'a, T: FileSystem> Walk<'a, T> {
         path: P,
         filters: Vec<Box<dyn PathFilter>>,
     ) -> Result<Walk<T>, err::Error> {
-        let mut path_stack = vec![];
-        let mut filter_mut = filters;
-        let path: &Path = path.as_ref();
-        let mut root_path = path.to_path_buf();
-        let path_str = path.to_str().ok_or(err::Error::PathFormatError)?;
-
-        if path_str.contains('*') || path_str.contains('?') {
-            let globfilter = GlobFilter::new(path_str)?;
-            filter_mut.push(Box::new(globfilter));
-            root_path = path_root(path_str);
-        }
+        Walk::with_metadata_filters(fs, path, filters, vec![])
+    }
 
-        let max_depth = if path_str.contains("**") {
-            None
+    pub fn with_metadata_filters<P: AsRef<Path>>(
+        fs: &'a T,
+        path: P,
+        filters: Vec<Box<dyn PathFilter>>,
+        metadata_filters: Vec<Box<dyn MetadataFilter>>,
+    ) -> Result<Walk<T>, err::Error> {
+        let path = path.as_ref();
+        // Listings return absolute paths, relative patterns would not match them
+        let current_dir = if path.is_relative() {
+            fs.current_dir().ok()
         } else {
-            Some(path.strip_prefix(&root_path).unwrap().components().count())
+            None
+        };
+        Ok(Walk {
+            dir_filer: StartFilter::new(String::new()),
+            glob: None,
+            path_stack: VecDeque::new(),
+            fs,
+            path: path.to_path_buf(),
+            roots: GlobRoot::expand(path, current_dir.as_deref())?.into(),
+            roots_started: 0,
+            min_depth: 0,
+            max_depth: None,
+            depth_limit: None,
+            order: Order::DepthFirst,
+            sort_by: None,
+            pending: None,
+            exit_events: false,
+            follow_links: false,
+            attributes: false,
+            root: PathBuf::new(),
+            excludes: IgnoreRules::new(),
+            ignore_file: None,
+            ignores: HashMap::new(),
+            error_policy: ErrorPolicy::Skip,
+            errors: vec![],
+            failures: VecDeque::new(),
+            aborted: false,
+            filters,
+            metadata_filters,
+        })
+    }
+
+    /// Starts walking the next root, false when all are walked.
+    fn next_root(&mut self) -> bool {
+        let root = match self.roots.pop_front() {
+            Some(glob_root) => self.start_root(glob_root),
+            None => return false,
         };
 
-        if fs.is_dir(&root_path) {
-            path_stack.push(Node::Dir(0, root_path))
-        } else if fs.exists(&root_path) {
-            path_stack.push(Node::File(root_path))
+        let metadata = match self.fs.metadata(&root) {
+            Ok(metadata) => metadata,
+            Err(ref e) if self.fs.is_not_found(e) => return true,
+            Err(e) => {
+                self.failures.push_back(WalkError::new(root, e));
+                return true;
+            }
+        };
+        let matched = matches_metadata(&self.metadata_filters, &root, &metadata);
+        let attributes = self.keep_attributes(&metadata);
+        if metadata.is_dir() {
+            let ancestors = if self.follow_links {
+                Some(Rc::new(Ancestor {
+                    id: dir_id(&metadata, &root, None, &None),
+                    path: root.clone(),
+                    parent: None,
+                }))
+            } else {
+                None
+            };
+            self.path_stack
+                .push_back(Node::Dir(0, root, matched, ancestors, attributes))
+        } else {
+            self.path_stack
+                .push_back(Node::File(0, root, matched, attributes))
         }
+        true
+    }
 
-        debug!("max depth: {:?}", max_depth);
-        Ok(Walk {
-            dir_filer: start_with_filter(&path_str),
-            path_stack,
-            fs,
+    /// Takes the settings of a root, returns its path.
+    fn start_root(&mut self, glob_root: GlobRoot) -> PathBuf {
+        let GlobRoot {
+            root,
+            glob,
             max_depth,
-            filters: filter_mut,
-        })
+            dir_filter,
+        } = glob_root;
+        self.roots_started += 1;
+        self.glob = glob;
+        self.dir_filer = dir_filter;
+        self.max_depth = match (max_depth, self.depth_limit) {
+            (Some(glob), Some(max)) => Some(glob.min(max)),
+            (glob, max) => glob.or(max),
+        };
+        debug!("root: {:?}, max depth: {:?}", root, self.max_depth);
+        self.root = root.clone();
+        root
+    }
+
+    /// State after the last entry returned. Saving it before acting on the entries
+    /// returned since the previous one makes sure none is returned twice after a
+    /// restart.
+    pub fn checkpoint(&self) -> Checkpoint {
+        let pending = self
+            .pending
+            .as_ref()
+            .map(|&(depth, ref path, _)| (depth, path.clone()));
+        let nodes: Vec<_> = self.path_stack.iter().cloned().collect();
+        // Only the rules of the directories something is left in
+        let mut ignores: Vec<_> = self
+            .ignores
+            .iter()
+            .filter(|&(dir, _)| {
+                pending.iter().any(|(_, path)| path.starts_with(dir))
+                    || nodes.iter().any(|node| node.path().starts_with(dir))
+            })
+            .map(|(dir, rules)| (dir.clone(), rules.clone()))
+            .collect();
+        ignores.sort_by(|a, b| a.0.cmp(&b.0));
+        Checkpoint {
+            path: self.path.clone(),
+            roots_started: self.roots_started,
+            pending,
+            nodes,
+            ignores,
+        }
+    }
+
+    /// Links followed are not known to lead back above the directories resumed, such a
+    /// loop is walked around once more before being found.
+    fn restore(&mut self, checkpoint: Checkpoint) -> Result<(), err::Error> {
+        if checkpoint.path != self.path {
+            return Err(err::Error::InvalidCheckpoint(format!(
+                "checkpoint of {}",
+                checkpoint.path.display()
+            )));
+        }
+        if checkpoint.roots_started > self.roots.len() {
+            return Err(err::Error::InvalidCheckpoint(String::from(
+                "more roots than the path has",
+            )));
+        }
+        let last_started = self.roots.drain(..checkpoint.roots_started).next_back();
+        if let Some(glob_root) = last_started {
+            self.start_root(glob_root);
+        }
+        self.roots_started = checkpoint.roots_started;
+        self.pending = checkpoint.pending.map(|(depth, path)| (depth, path, None));
+        self.path_stack = checkpoint.nodes.into();
+        self.ignores = checkpoint.ignores.into_iter().collect();
+        Ok(())
     }
 
     fn is_valid(&self, path: &PathBuf) -> bool {
         let path_str = path.to_str();
-        self.filters
+        self.glob
             .iter()
+            .map(|glob| glob as &dyn PathFilter)
+            .chain(self.filters.iter().map(|f| f.as_ref()))
             .all(|f| path_str.map_or(false, |s| f.is_match(s)))
     }
 
@@ -146,87 +670,386 @@ impl<'a, T: FileSystem> Walk<'a, T> {
             .unwrap_or(false)
     }
 
-    fn resolve_next(&mut self) -> Option<Result<Node, T::Error>> {
-        while let Some(node) = self.path_stack.pop() {
+    fn pop(&mut self) -> Option<Node> {
+        match self.order {
+            Order::DepthFirst => self.path_stack.pop_back(),
+            Order::BreadthFirst => self.path_stack.pop_front(),
+        }
+    }
+
+    /// Does not walk through the directory just returned. Nothing happens when it was a
+    /// file.
+    pub fn skip_current_dir(&mut self) {
+        self.pending = None;
+    }
+
+    /// Turns the walk into a stream of events, walked depth first so that the exit of a
+    /// directory follows its subtree.
+    pub fn events(mut self) -> WalkEvents<'a, T> {
+        self.order = Order::DepthFirst;
+        self.exit_events = true;
+        WalkEvents(self)
+    }
+
+    /// Errors kept so far by the `Collect` policy.
+    pub fn errors(&self) -> &[WalkError<T::Error>] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<WalkError<T::Error>> {
+        self.errors
+    }
+
+    fn resolve_next(&mut self) -> Option<Result<Node, WalkError<T::Error>>> {
+        while !self.aborted {
+            let next = match self.failures.pop_front() {
+                Some(e) => Err(e),
+                None => match self.resolve_node() {
+                    Some(next) => next,
+                    None if self.failures.is_empty() => return None,
+                    None => continue,
+                },
+            };
+            match next {
+                Err(e) => match self.error_policy {
+                    ErrorPolicy::Abort => {
+                        self.aborted = true;
+                        return Some(Err(e));
+                    }
+                    ErrorPolicy::Skip => return Some(Err(e)),
+                    ErrorPolicy::Collect => self.errors.push(e),
+                },
+                node => return Some(node),
+            }
+        }
+        None
+    }
+
+    fn resolve_node(&mut self) -> Option<Result<Node, WalkError<T::Error>>> {
+        if let Some((depth, path, ancestors)) = self.pending.take() {
+            try_opt_res!(self.fill_path_stack(&path, depth, ancestors));
+        }
+        while let Some(node) = self.pop().or_else(|| self.next_root_node()) {
             debug!("resolve_next: {:?}", node);
             match node {
-                Node::File(path) => {
-                    if self.is_valid(&path) {
-                        return Some(Ok(Node::File(path)));
+                Node::File(depth, path, matched, attributes) => {
+                    if depth >= self.min_depth && matched && self.is_valid(&path) {
+                        let attributes = try_opt_res!(self.fetch_attributes(&path, attributes));
+                        return Some(Ok(Node::File(depth, path, matched, attributes)));
                     }
                 }
-                Node::Dir(depth, path) => {
+                Node::Dir(depth, path, matched, ancestors, attributes) => {
                     if depth != 0 && !self.is_valid_dir(&path) {
                         continue;
                     }
 
-                    if self.max_depth.map(|md| depth < md).unwrap_or(true) {
-                        try_opt_res!(self.fill_path_stack(&path, depth));
+                    let descend = self.max_depth.map(|md| depth < md).unwrap_or(true);
+                    if depth >= self.min_depth && matched && self.is_valid(&path) {
+                        let attributes = try_opt_res!(self.fetch_attributes(&path, attributes));
+                        if self.exit_events {
+                            self.path_stack.push_back(Node::Exit(depth, path.clone()));
+                        }
+                        if descend {
+                            self.pending = Some((depth, path.clone(), ancestors));
+                        }
+                        return Some(Ok(Node::Dir(depth, path, matched, None, attributes)));
                     }
 
-                    if self.is_valid(&path) {
-                        return Some(Ok(Node::Dir(depth, path)));;
+                    if descend {
+                        try_opt_res!(self.fill_path_stack(&path, depth, ancestors));
                     }
                 }
+                Node::Loop(path, ancestor) => {
+                    let error = err::Error::SymlinkLoop(ancestor);
+                    return Some(Err(WalkError::new(path, error.into())));
+                }
+                Node::Exit(depth, path) => {
+                    return Some(Ok(Node::Exit(depth, path)));
+                }
             }
         }
         None
     }
 
-    fn fill_path_stack(&mut self, path: &PathBuf, depth: usize) -> Result<(), T::Error> {
-        for entry in self.fs.read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if entry.is_dir() {
-                self.path_stack.push(Node::Dir(depth + 1, path))
-            } else {
-                self.path_stack.push(Node::File(path))
+    /// Stops at the first root failing, for its error to come in order.
+    fn next_root_node(&mut self) -> Option<Node> {
+        while self.failures.is_empty() && self.next_root() {
+            if let Some(node) = self.pop() {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    fn fill_path_stack(
+        &mut self,
+        path: &PathBuf,
+        depth: usize,
+        ancestors: Ancestors,
+    ) -> Result<(), WalkError<T::Error>> {
+        let entries: Result<Vec<_>, _> = self.fs.read_dir(path).and_then(|r| r.collect());
+        let mut entries = entries.map_err(|e| WalkError::new(path.clone(), e))?;
+        self.read_ignore_file(path, &entries)?;
+        if !self.excludes.is_empty() || !self.ignores.is_empty() {
+            entries.retain(|entry| !self.is_ignored(&entry.path(), entry.is_dir()));
+        }
+        if let Some(sort_by) = self.sort_by {
+            entries.sort_by(|a, b| compare(sort_by, a, b));
+        }
+        let mut nodes = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match self.node(depth + 1, entry, &ancestors) {
+                Ok(node) => nodes.push(node),
+                Err(e) => self.failures.push_back(e),
             }
         }
+        if self.order == Order::DepthFirst {
+            // The last pushed is walked first
+            nodes.reverse();
+        }
+        self.path_stack.extend(nodes);
         Ok(())
     }
 
-    fn is_recursive(&self) -> bool {
-        self.max_depth.is_none()
+    fn read_ignore_file(
+        &mut self,
+        dir: &Path,
+        entries: &[T::DirEntry],
+    ) -> Result<(), WalkError<T::Error>> {
+        let ignore_file = match self.ignore_file {
+            Some(ref name) => dir.join(name),
+            None => return Ok(()),
+        };
+        if entries.iter().any(|entry| entry.path() == ignore_file) {
+            let content = self
+                .fs
+                .read_to_string(&ignore_file)
+                .map_err(|e| WalkError::new(ignore_file.clone(), e))?;
+            let rules = IgnoreRules::parse(&content)
+                .map_err(|e| WalkError::new(ignore_file.clone(), e.into()))?;
+            self.ignores.insert(dir.components().collect(), rules);
+        }
+        Ok(())
+    }
+
+    /// The rules of the deepest directory matching the entry win, then those of the
+    /// builder.
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let dirs = path.ancestors().skip(1);
+        for dir in dirs.take_while(|dir| dir.starts_with(&self.root)) {
+            if let Some(rules) = self.ignores.get(dir) {
+                let relative = path.strip_prefix(dir).unwrap_or(path);
+                if let Some(ignored) = rules.matched(relative, is_dir) {
+                    return ignored;
+                }
+            }
+        }
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.excludes.matched(relative, is_dir) == Some(true)
     }
-}
 
-fn path_root(path: &str) -> PathBuf {
-    let mut s = PathBuf::new();
-    let mut slice = String::new();
-    for token in path.chars() {
-        match token {
-            '*' | '?' | '[' => break,
-            '/' => {
-                slice.push(token);
-                s.push(slice);
-                slice = String::new();
+    fn node(
+        &self,
+        depth: usize,
+        entry: T::DirEntry,
+        ancestors: &Ancestors,
+    ) -> Result<Node, WalkError<T::Error>> {
+        let path = entry.path();
+        if !self.follow_links {
+            let matched = matches_metadata(&self.metadata_filters, &path, &entry);
+            let attributes = self.keep_attributes(&entry);
+            return Ok(if entry.is_dir() {
+                Node::Dir(depth, path, matched, None, attributes)
+            } else {
+                Node::File(depth, path, matched, attributes)
+            });
+        }
+        if entry.is_symlink() {
+            match self.fs.metadata(&path) {
+                Ok(metadata) => {
+                    let target = entry.symlink_target();
+                    return Ok(self.followed_node(depth, path, &metadata, target, ancestors));
+                }
+                // Dangling links are returned as files
+                Err(ref e) if self.fs.is_not_found(e) => {}
+                Err(e) => return Err(WalkError::new(path, e)),
             }
-            _ => slice.push(token),
         }
+        Ok(self.followed_node(depth, path, &entry, None, ancestors))
+    }
+
+    fn followed_node<M: MetadataTrait>(
+        &self,
+        depth: usize,
+        path: PathBuf,
+        metadata: &M,
+        target: Option<PathBuf>,
+        ancestors: &Ancestors,
+    ) -> Node {
+        let matched = matches_metadata(&self.metadata_filters, &path, metadata);
+        let attributes = self.keep_attributes(metadata).map(|mut attributes| {
+            if target.is_some() {
+                attributes.is_symlink = true;
+                attributes.symlink_target = target.clone();
+            }
+            attributes
+        });
+        if !metadata.is_dir() {
+            return Node::File(depth, path, matched, attributes);
+        }
+        let id = dir_id(metadata, &path, target, ancestors);
+        if let Some(ancestor) = find_ancestor(ancestors, &id) {
+            return Node::Loop(path, ancestor.path.clone());
+        }
+        let ancestor = Ancestor {
+            id,
+            path: path.clone(),
+            parent: ancestors.clone(),
+        };
+        Node::Dir(depth, path, matched, Some(Rc::new(ancestor)), attributes)
+    }
+
+    fn keep_attributes<M: MetadataTrait>(&self, metadata: &M) -> Option<Box<Attributes>> {
+        if self.attributes {
+            Some(Box::new(Attributes::of(metadata)))
+        } else {
+            None
+        }
+    }
+
+    /// Attributes of an entry resumed from a checkpoint are fetched again.
+    fn fetch_attributes(
+        &self,
+        path: &PathBuf,
+        attributes: Option<Box<Attributes>>,
+    ) -> Result<Option<Box<Attributes>>, WalkError<T::Error>> {
+        if !self.attributes || attributes.is_some() {
+            return Ok(attributes);
+        }
+        let metadata = self
+            .fs
+            .metadata(path)
+            .map_err(|e| WalkError::new(path.clone(), e))?;
+        Ok(self.keep_attributes(&metadata))
     }
-    s
+
+    fn is_recursive(&self) -> bool {
+        self.max_depth.is_none()
+    }
+}
+
+fn matches_metadata<M: MetadataTrait>(
+    filters: &[Box<dyn MetadataFilter>],
+    path: &Path,
+    metadata: &M,
+) -> bool {
+    filters.is_empty()
+        || path
+            .to_str()
+            .is_some_and(|path| filters.iter().all(|f| f.matches(path, metadata)))
+}
+
+fn compare<E: DirEntryTrait>(sort_by: SortBy, a: &E, b: &E) -> Ordering {
+    let ordering = match sort_by {
+        SortBy::Name => Ordering::Equal,
+        SortBy::Size => a.len().cmp(&b.len()),
+        SortBy::Modified => a.modified().cmp(&b.modified()),
+    };
+    ordering.then_with(|| a.path().file_name().cmp(&b.path().file_name()))
 }
 
-fn start_with_filter<'a>(path: &str) -> StartFilter {
-    let mut slice = String::new();
-    for token in path.chars() {
-        match token {
-            '*' | '?' | '[' => break,
-            _ => slice.push(token),
+/// Where a walk starts and how far it goes, for one alternative of its path.
+pub(crate) struct GlobRoot {
+    pub root: PathBuf,
+    pub glob: Option<GlobFilter>,
+    pub max_depth: Option<usize>,
+    /// Directories outside of it are not walked through.
+    pub dir_filter: StartFilter,
+}
+
+impl GlobRoot {
+    /// Relative patterns with wildcards are resolved against `current_dir`.
+    pub fn expand(path: &Path, current_dir: Option<&Path>) -> Result<Vec<GlobRoot>, err::Error> {
+        let path_str = path.to_str().ok_or(err::Error::PathFormatError)?;
+        pattern::expand_braces(path_str)?
+            .iter()
+            .map(|pattern| GlobRoot::new(pattern, current_dir))
+            .collect()
+    }
+
+    fn new(pattern: &str, current_dir: Option<&Path>) -> Result<GlobRoot, err::Error> {
+        if pattern::first_wildcard(pattern).is_none() {
+            pattern::translate(pattern)?;
+            let root = PathBuf::from(pattern::unescape(pattern));
+            return Ok(GlobRoot {
+                dir_filter: StartFilter::new(root.to_string_lossy().into_owned()),
+                root,
+                glob: None,
+                max_depth: Some(0),
+            });
         }
+
+        let pattern = match current_dir {
+            Some(current_dir) if !pattern.starts_with('/') => {
+                let current_dir = current_dir.to_str().ok_or(err::Error::PathFormatError)?;
+                let joined = format!(
+                    "{}/{}",
+                    pattern::escape(current_dir.trim_end_matches('/')),
+                    pattern
+                );
+                let components: Vec<_> = joined.split('/').filter(|c| *c != ".").collect();
+                components.join("/")
+            }
+            _ => pattern.to_owned(),
+        };
+        let wildcard = pattern::first_wildcard(&pattern).unwrap_or(pattern.len());
+        let root_len = pattern[..wildcard].rfind('/').map_or(0, |i| i + 1);
+
+        let mut root = pattern::unescape(&pattern[..root_len]);
+        let max_depth = if pattern.contains("**") {
+            None
+        } else {
+            // `*` would match the empty name after the separator
+            if root.len() > 1 {
+                root.pop();
+            }
+            Some(
+                pattern[root_len..]
+                    .split('/')
+                    .filter(|c| !c.is_empty())
+                    .count(),
+            )
+        };
+        Ok(GlobRoot {
+            root: PathBuf::from(root),
+            glob: Some(GlobFilter::new(&pattern::translate(&pattern)?)?),
+            max_depth,
+            dir_filter: StartFilter::new(pattern::unescape(&pattern[..wildcard])),
+        })
     }
-    StartFilter::new(slice)
 }
 
+#[derive(Debug)]
 pub struct WalkItem {
     path: PathBuf,
     is_dir: bool,
+    depth: usize,
+    attributes: Option<Box<Attributes>>,
 }
 
 impl WalkItem {
-    fn new(path: PathBuf, is_dir: bool) -> WalkItem {
-        WalkItem { path, is_dir }
+    pub(crate) fn new(path: PathBuf, is_dir: bool, depth: usize) -> WalkItem {
+        WalkItem {
+            path,
+            is_dir,
+            depth,
+            attributes: None,
+        }
+    }
+
+    fn with_attributes(mut self, attributes: Option<Box<Attributes>>) -> WalkItem {
+        self.attributes = attributes;
+        self
     }
 
     pub fn path(&self) -> PathBuf {
@@ -236,19 +1059,75 @@ impl WalkItem {
     pub fn is_dir(&self) -> bool {
         self.is_dir
     }
+
+    /// Depth below the root of the walk, which is at 0.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Set when the walk keeps them, `None` for the exit of a directory.
+    pub fn attributes(&self) -> Option<&Attributes> {
+        self.attributes.as_deref()
+    }
 }
 
 impl<'a, T: FileSystem> Iterator for Walk<'a, T> {
-    type Item = Result<WalkItem, T::Error>;
+    type Item = Result<WalkItem, WalkError<T::Error>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(entry) = self.resolve_next() {
             match entry {
-                Ok(Node::Dir(_, path)) => return Some(Ok(WalkItem::new(path, true))),
-                Ok(Node::File(path)) => return Some(Ok(WalkItem::new(path, false))),
+                Ok(Node::Dir(depth, path, _, _, attributes)) => {
+                    return Some(Ok(
+                        WalkItem::new(path, true, depth).with_attributes(attributes)
+                    ))
+                }
+                Ok(Node::File(depth, path, _, attributes)) => {
+                    return Some(Ok(
+                        WalkItem::new(path, false, depth).with_attributes(attributes)
+                    ))
+                }
+                Ok(Node::Exit(_, _)) => unreachable!("exit events are off"),
+                Ok(Node::Loop(_, _)) => unreachable!("loops are errors"),
                 Err(err) => return Some(Err(err)),
             }
         }
         None
     }
 }
+
+pub struct WalkEvents<'a, T: FileSystem>(Walk<'a, T>);
+
+impl<'a, T: FileSystem> WalkEvents<'a, T> {
+    /// Skips the subtree of the directory just entered, its exit still comes next.
+    pub fn skip_current_dir(&mut self) {
+        self.0.skip_current_dir()
+    }
+
+    pub fn errors(&self) -> &[WalkError<T::Error>] {
+        self.0.errors()
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.0.checkpoint()
+    }
+}
+
+impl<'a, T: FileSystem> Iterator for WalkEvents<'a, T> {
+    type Item = Result<WalkEvent, WalkError<T::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.resolve_next().map(|entry| {
+            entry.map(|node| match node {
+                Node::Dir(depth, path, _, _, attributes) => WalkEvent::EnterDir(
+                    WalkItem::new(path, true, depth).with_attributes(attributes),
+                ),
+                Node::File(depth, path, _, attributes) => {
+                    WalkEvent::File(WalkItem::new(path, false, depth).with_attributes(attributes))
+                }
+                Node::Exit(depth, path) => WalkEvent::ExitDir(WalkItem::new(path, true, depth)),
+                Node::Loop(_, _) => unreachable!("loops are errors"),
+            })
+        })
+    }
+}
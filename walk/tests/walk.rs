@@ -0,0 +1,1529 @@
+extern crate env_logger;
+extern crate fs_test;
+extern crate hdfs;
+extern crate walk;
+
+use fs_test::{Error, FakeHdfs, Fault, FaultyFs, Operation, TempDir};
+use hdfs::err::Error as HdfsError;
+use hdfs::fs::FileSystem as HdfsFs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walk::checkpoint::{Checkpoint, ParallelCheckpoint};
+use walk::err::WalkError;
+use walk::filter::*;
+use walk::ignore::{IgnoreRules, IGNORE_FILE};
+use walk::parallel::*;
+use walk::walk::*;
+
+fn path_list(list: &[&str]) -> Vec<PathBuf> {
+    list.iter().map(PathBuf::from).collect()
+}
+
+fn enable_log() {
+    let env = env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "debug");
+    let _ = env_logger::Builder::from_env(env).try_init();
+}
+
+fn walk(fs: &FakeHdfs, path: &str) -> Vec<PathBuf> {
+    let list: Result<Vec<_>, WalkError<Error>> = WalkBuilder::new(fs)
+        .with_path(PathBuf::from(path))
+        .build()
+        .unwrap()
+        .map(|e| e.map(|d| d.path()))
+        .collect();
+
+    let mut list = list.unwrap();
+    list.sort();
+    list
+}
+
+fn var_tree() -> FakeHdfs {
+    FakeHdfs::new()
+        .file("/var/file1", b"")
+        .file("/var/file2", b"")
+        .file("/file", b"")
+}
+
+fn deep_tree() -> FakeHdfs {
+    FakeHdfs::new()
+        .file("/var/file1", b"")
+        .file("/var/file2", b"")
+        .file("/var2/file3", b"")
+        .file("/var2/file4", b"")
+        .file("/var3/var4/file5", b"")
+        .file("/var3/var4/file8", b"")
+        .file("/var3/var5/file2", b"")
+        .file("/var3/var5/file9", b"")
+        .file("/var3/var6/file7", b"")
+        .file("/var3/var6/file10", b"")
+        .file("/var3/file11", b"")
+        .file("/file0", b"")
+}
+
+#[test]
+fn walk_1() {
+    let fs = FakeHdfs::new();
+    assert!(fs.is_dir(&PathBuf::from("/")));
+}
+
+#[test]
+fn walk_2() {
+    let fs = FakeHdfs::new().dir("/var").file("/file", b"");
+    assert!(fs.is_dir(&PathBuf::from("/var")));
+    assert!(!fs.is_dir(&PathBuf::from("/file")));
+}
+
+#[test]
+fn walk_3() {
+    let fs = FakeHdfs::new().dir("/var").file("/file", b"");
+
+    let entries: Result<Vec<_>, _> = fs.read_dir(&PathBuf::from("/")).unwrap().collect();
+    let mut paths: Vec<_> = entries.unwrap().into_iter().map(|de| de.path()).collect();
+    paths.sort();
+    assert_eq!(paths, path_list(&["/file", "/var"]));
+}
+
+#[test]
+fn walk_4() {
+    let fs = var_tree();
+
+    let entries: Result<Vec<_>, _> = fs.read_dir(&PathBuf::from("/var")).unwrap().collect();
+    let mut paths: Vec<_> = entries.unwrap().into_iter().map(|de| de.path()).collect();
+    paths.sort();
+    assert_eq!(paths, path_list(&["/var/file1", "/var/file2"]));
+}
+
+#[test]
+fn test_list_root() {
+    enable_log();
+    let fs = FakeHdfs::new().dir("/var").file("/file", b"");
+
+    assert_eq!(walk(&fs, "/"), path_list(&["/"]));
+}
+
+#[test]
+fn test_list_directory_explicit() {
+    enable_log();
+    let fs = var_tree();
+
+    assert_eq!(walk(&fs, "/var/"), path_list(&["/var"]));
+}
+
+#[test]
+fn test_list_directory_implicit() {
+    enable_log();
+    let fs = var_tree();
+
+    assert_eq!(walk(&fs, "/var"), path_list(&["/var"]));
+}
+
+#[test]
+fn test_list_directory_empty() {
+    enable_log();
+    let fs = var_tree();
+
+    assert_eq!(0, walk(&fs, "/va").len());
+}
+
+#[test]
+fn test_list_directory_with_glob() {
+    enable_log();
+    let fs = var_tree();
+
+    assert_eq!(walk(&fs, "/va*"), path_list(&["/var"]));
+}
+
+#[test]
+fn test_list_directory_with_complex_glob() {
+    enable_log();
+    let fs = var_tree();
+
+    assert_eq!(
+        walk(&fs, "/va*/fi*"),
+        path_list(&["/var/file1", "/var/file2"])
+    );
+}
+
+#[test]
+fn test_list_directory_with_question_mark() {
+    enable_log();
+    let fs = FakeHdfs::new()
+        .file("/var/file1", b"")
+        .file("/var/file2a", b"")
+        .file("/var/file3a", b"")
+        .file("/var/file2b", b"")
+        .file("/file", b"");
+
+    assert_eq!(
+        walk(&fs, "/var/file?a"),
+        path_list(&["/var/file2a", "/var/file3a"])
+    );
+}
+
+#[test]
+fn test_list_directory_with_multiple_glob() {
+    enable_log();
+    let fs = deep_tree()
+        .file("/bar3/file3", b"")
+        .file("/bar3/file4", b"")
+        .dir("/var3/var6/var7");
+
+    assert_eq!(
+        walk(&fs, "/*/*/*"),
+        path_list(&[
+            "/var3/var4/file5",
+            "/var3/var4/file8",
+            "/var3/var5/file2",
+            "/var3/var5/file9",
+            "/var3/var6/file10",
+            "/var3/var6/file7",
+            "/var3/var6/var7"
+        ])
+    );
+}
+
+#[test]
+fn test_list_directory_with_globstar() {
+    enable_log();
+    let fs = deep_tree();
+
+    assert_eq!(
+        walk(&fs, "/var*/**/file2"),
+        path_list(&["/var/file2", "/var3/var5/file2"])
+    );
+}
+
+#[test]
+fn test_list_directory_with_current_path() {
+    enable_log();
+    let fs = deep_tree()
+        .file("/user/hdfs/file1", b"")
+        .file("/user/hdfs/file2", b"")
+        .file("/user/hdfs/other", b"");
+
+    assert_eq!(
+        walk(&fs, "./file*"),
+        path_list(&["/user/hdfs/file1", "/user/hdfs/file2"])
+    );
+}
+
+#[test]
+fn test_list_directory_with_character_classes() {
+    let fs = FakeHdfs::new()
+        .file("/data/1/x", b"")
+        .file("/data/2/x", b"")
+        .file("/data/a/x", b"")
+        .file("/data/b/y", b"");
+
+    assert_eq!(
+        walk(&fs, "/data/[0-9]/x"),
+        path_list(&["/data/1/x", "/data/2/x"])
+    );
+    assert_eq!(walk(&fs, "/data/[^0-9]/x"), path_list(&["/data/a/x"]));
+    assert_eq!(walk(&fs, "/data/[!0-9a]/*"), path_list(&["/data/b/y"]));
+}
+
+#[test]
+fn test_list_directory_with_braces() {
+    let fs = FakeHdfs::new()
+        .file("/logs/2024-01-01", b"")
+        .file("/logs/2024-01-02", b"")
+        .file("/logs/2024-02-01", b"")
+        .file("/logs/2024-03-01", b"")
+        .file("/data/a/part-0", b"")
+        .file("/data/b/part-0", b"")
+        .file("/data/b/part-1", b"")
+        .file("/other/part-0", b"");
+
+    assert_eq!(
+        walk(&fs, "/logs/2024-{01,02}-*"),
+        path_list(&["/logs/2024-01-01", "/logs/2024-01-02", "/logs/2024-02-01"])
+    );
+    assert_eq!(
+        walk(&fs, "/logs/2024-{01-0{1,2},03-01}"),
+        path_list(&["/logs/2024-01-01", "/logs/2024-01-02", "/logs/2024-03-01"])
+    );
+    // Each alternative is a root of its own
+    assert_eq!(
+        walk(&fs, "/{data/b,other}/part-*"),
+        path_list(&["/data/b/part-0", "/data/b/part-1", "/other/part-0"])
+    );
+    assert_eq!(
+        walk(&fs, "/{logs,data/a}"),
+        path_list(&["/data/a", "/logs"])
+    );
+}
+
+#[test]
+fn test_list_directory_with_escaping() {
+    let fs = FakeHdfs::new()
+        .file("/data/a*b", b"")
+        .file("/data/axb", b"")
+        .file("/data/{x}", b"")
+        .file("/data/[y]/z", b"");
+
+    assert_eq!(
+        walk(&fs, "/data/a*b"),
+        path_list(&["/data/a*b", "/data/axb"])
+    );
+    assert_eq!(walk(&fs, "/data/a\\*b"), path_list(&["/data/a*b"]));
+    assert_eq!(walk(&fs, "/data/a\\*?"), path_list(&["/data/a*b"]));
+    assert_eq!(walk(&fs, "/data/\\{x\\}"), path_list(&["/data/{x}"]));
+    assert_eq!(walk(&fs, "/data/\\[y\\]/*"), path_list(&["/data/[y]/z"]));
+}
+
+#[test]
+fn test_invalid_patterns() {
+    let fs = var_tree();
+    for path in &["/var/{a,b", "/var/[ab", "/var/a}", "/var\\"] {
+        assert!(
+            WalkBuilder::new(&fs)
+                .with_path(PathBuf::from(path))
+                .build()
+                .is_err(),
+            "{}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_walk_surfaces_standby_errors() {
+    // the root lookup goes through, listing it hits a standby namenode
+    let fs = FaultyFs::new(deep_tree(), 0).script(vec![Fault::None, Fault::Standby]);
+
+    let results: Vec<_> = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/var3/*"))
+        .build()
+        .unwrap()
+        .collect();
+
+    assert_eq!(1, results.len());
+    match results[0] {
+        Err(ref e) => match *e.error() {
+            Error::Hdfs(HdfsError::RemoteException(ref class, _)) => {
+                assert_eq!("StandbyException", class);
+                assert_eq!(Path::new("/var3"), e.path());
+            }
+            ref other => panic!("unexpected error {:?}", other),
+        },
+        Ok(ref entry) => panic!("unexpected entry {:?}", entry.path()),
+    }
+}
+
+#[test]
+fn test_walk_with_intermittent_standby() {
+    let tree = deep_tree();
+    let expected = walk(&tree, "/var3/**");
+    let fs = FaultyFs::new(tree, 3)
+        .script(vec![Fault::None])
+        .standby_rate(0.3);
+
+    let mut found = vec![];
+    let mut errors = 0;
+    for result in WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/var3/**"))
+        .build()
+        .unwrap()
+    {
+        match result {
+            Ok(entry) => found.push(entry.path()),
+            Err(_) => errors += 1,
+        }
+    }
+
+    assert!(errors > 0);
+    assert!(found.iter().all(|path| expected.contains(path)));
+    assert_eq!(fs.stats().standby, errors);
+}
+
+#[test]
+fn test_entry_attributes_come_with_the_listing() {
+    let fs = FakeHdfs::new().file_with("/data/part-0", 42, "alice", 0o640, 3, 1024);
+
+    let entries: Result<Vec<_>, _> = fs.read_dir(&PathBuf::from("/data")).unwrap().collect();
+    let entries = entries.unwrap();
+    assert_eq!(1, entries.len());
+    let entry = &entries[0];
+    assert_eq!(42, entry.len());
+    assert_eq!(Some(0o640), entry.permissions());
+    assert_eq!(Some(String::from("alice")), entry.owner());
+    assert_eq!(Some(String::from("supergroup")), entry.group());
+    assert!(entry.modified().is_some());
+    assert!(!entry.is_symlink());
+
+    let metadata = fs.metadata(&PathBuf::from("/data")).unwrap();
+    assert!(metadata.is_dir());
+    assert_eq!(Some(String::from("hdfs")), metadata.owner());
+}
+
+#[test]
+fn test_linux_attributes() {
+    use std::fs;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use walk::linuxfs::LinuxFS;
+
+    let dir = TempDir::default();
+    let file = dir.path().join("file");
+    fs::write(&file, b"content").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+    symlink(&file, dir.path().join("link")).unwrap();
+
+    let lfs = LinuxFS::default();
+    let mut entries: Vec<_> = lfs
+        .read_dir(&dir.path().to_path_buf())
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .collect();
+    entries.sort_by_key(|entry| entry.path());
+
+    assert_eq!(7, entries[0].len());
+    assert_eq!(Some(0o640), entries[0].permissions());
+    assert!(entries[0].modified().is_some());
+    assert!(!entries[0].is_symlink());
+    assert!(entries[1].is_symlink());
+    assert!(!entries[1].is_dir());
+
+    let metadata = lfs.metadata(&file).unwrap();
+    assert_eq!(7, metadata.len());
+    assert_eq!(entries[0].owner(), metadata.owner());
+}
+
+#[test]
+fn test_walk_keeps_attributes() {
+    let fs = FakeHdfs::new()
+        .file_with("/data/a/part-0", 42, "alice", 0o640, 2, 1024)
+        .file("/data/b", b"bb");
+    let builder = || {
+        WalkBuilder::new(&fs)
+            .with_path(PathBuf::from("/data/**"))
+            .sort_by(SortBy::Name)
+            .attributes(true)
+    };
+    let attributes = |walk: Walk<FakeHdfs>| -> Vec<(PathBuf, bool, u64, Option<u16>)> {
+        walk.map(|item| {
+            let item = item.unwrap();
+            let attributes = item.attributes().unwrap();
+            (
+                item.path(),
+                attributes.is_dir,
+                attributes.len,
+                attributes.replication,
+            )
+        })
+        .collect()
+    };
+    let all = attributes(builder().build().unwrap());
+    assert_eq!(
+        vec![
+            (PathBuf::from("/data"), true, 0, Some(0)),
+            (PathBuf::from("/data/a"), true, 0, Some(0)),
+            (PathBuf::from("/data/a/part-0"), false, 42, Some(2)),
+            (PathBuf::from("/data/b"), false, 2, Some(3)),
+        ],
+        all
+    );
+
+    // The attributes are not saved with a checkpoint, but fetched again
+    let mut walk = builder().build().unwrap();
+    walk.by_ref().take(2).for_each(drop);
+    let checkpoint = checkpoint_round_trip(walk.checkpoint());
+    let resumed = attributes(builder().resume(checkpoint).build().unwrap());
+    assert_eq!(&all[2..], &resumed[..]);
+
+    let walk = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/data"))
+        .build()
+        .unwrap();
+    assert!(walk
+        .map(|item| item.unwrap())
+        .all(|item| item.attributes().is_none()));
+}
+
+#[test]
+fn test_follow_links() {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use walk::err::Error as WalkErr;
+    use walk::linuxfs::LinuxFS;
+
+    let dir = TempDir::default();
+    let root = dir.path();
+    fs::create_dir_all(root.join("staging/data")).unwrap();
+    fs::create_dir_all(root.join("shared")).unwrap();
+    fs::write(root.join("shared/file"), b"content").unwrap();
+    symlink(root.join("shared"), root.join("staging/shared")).unwrap();
+    // Back to the root of the walk, and to the directory holding the link
+    symlink("..", root.join("staging/data/up")).unwrap();
+    symlink(root.join("staging/data"), root.join("staging/data/self")).unwrap();
+    symlink(root.join("missing"), root.join("staging/dangling")).unwrap();
+
+    let lfs = LinuxFS::default();
+    let staging = root.join("staging");
+    let walk = |follow_links| {
+        let mut walk = WalkBuilder::new(&lfs)
+            .with_path(staging.join("**"))
+            .follow_links(follow_links)
+            .error_policy(ErrorPolicy::Collect)
+            .build()
+            .unwrap();
+        let mut list: Vec<_> = walk.by_ref().map(|item| item.unwrap()).collect();
+        list.sort_by_key(WalkItem::path);
+        let mut errors: Vec<_> = walk
+            .into_errors()
+            .into_iter()
+            .map(|e| match e.error() {
+                WalkErr::SymlinkLoop(ancestor) => (e.path().to_path_buf(), ancestor.clone()),
+                e => panic!("unexpected error {:?}", e),
+            })
+            .collect();
+        errors.sort();
+        (list, errors)
+    };
+
+    let (list, errors) = walk(false);
+    let paths: Vec<_> = list.iter().map(WalkItem::path).collect();
+    assert_eq!(
+        vec![
+            staging.join(""),
+            staging.join("dangling"),
+            staging.join("data"),
+            staging.join("data/self"),
+            staging.join("data/up"),
+            staging.join("shared"),
+        ],
+        paths
+    );
+    assert!(!list[5].is_dir());
+    assert!(errors.is_empty());
+
+    let (list, errors) = walk(true);
+    let paths: Vec<_> = list.iter().map(WalkItem::path).collect();
+    assert_eq!(
+        vec![
+            staging.join(""),
+            staging.join("dangling"),
+            staging.join("data"),
+            staging.join("shared"),
+            staging.join("shared/file"),
+        ],
+        paths
+    );
+    assert!(list[3].is_dir());
+    assert_eq!(
+        vec![
+            (staging.join("data/self"), staging.join("data")),
+            (staging.join("data/up"), staging.join("")),
+        ],
+        errors
+    );
+}
+
+fn output_tree() -> FakeHdfs {
+    FakeHdfs::new()
+        .file("/out/_SUCCESS", b"")
+        .file("/out/part-0", b"")
+        .file("/out/.part-0.crc", b"")
+        .file("/out/_temporary/0/part-1", b"")
+        .file("/out/logs/app.log", b"")
+        .file("/out/logs/keep.log", b"")
+        .file("/out/logs/old/app.log", b"")
+        .file("/build/report", b"")
+}
+
+fn walk_excluding(fs: &FakeHdfs, path: &str, excludes: &str) -> Vec<PathBuf> {
+    let mut list: Vec<_> = WalkBuilder::new(fs)
+        .with_path(PathBuf::from(path))
+        .exclude(IgnoreRules::parse(excludes).unwrap())
+        .ignore_file(IGNORE_FILE)
+        .build()
+        .unwrap()
+        .map(|item| item.unwrap().path())
+        .collect();
+    list.sort();
+    list
+}
+
+#[test]
+fn test_exclude() {
+    let fs = output_tree();
+    assert_eq!(
+        path_list(&["/out/", "/out/logs", "/out/logs/old", "/out/part-0"]),
+        walk_excluding(&fs, "/out/**", "_SUCCESS\n*.crc\n_temporary/\n*.log")
+    );
+    // Anchored to the root of the walk, and included back
+    assert_eq!(
+        path_list(&[
+            "/out/",
+            "/out/.part-0.crc",
+            "/out/_SUCCESS",
+            "/out/_temporary",
+            "/out/_temporary/0",
+            "/out/_temporary/0/part-1",
+            "/out/logs",
+            "/out/logs/keep.log",
+            "/out/logs/old",
+            "/out/logs/old/app.log",
+            "/out/part-0",
+        ]),
+        walk_excluding(&fs, "/out/**", "/logs/*.log\n!keep.log\n/_SUCCESS/\n/out")
+    );
+    // Patterns with several roots are relative to each of them
+    assert_eq!(
+        path_list(&["/build/", "/out/"]),
+        walk_excluding(&fs, "/{out,build}/**", "/*")
+    );
+}
+
+#[test]
+fn test_ignore_files() {
+    let fs = output_tree()
+        .file("/out/.hdfsignore", b"_*\n*.crc\n!_temporary/\n")
+        .file("/out/logs/.hdfsignore", b"# Only the recent ones\nold/\n");
+    assert_eq!(
+        path_list(&[
+            "/out/",
+            "/out/.hdfsignore",
+            "/out/_temporary",
+            "/out/_temporary/0",
+            "/out/_temporary/0/part-1",
+            "/out/logs",
+            "/out/logs/.hdfsignore",
+            "/out/logs/app.log",
+            "/out/logs/keep.log",
+            "/out/part-0",
+        ]),
+        walk_excluding(&fs, "/out/**", "")
+    );
+    // Ignore files take precedence
+    assert_eq!(
+        path_list(&[
+            "/out/",
+            "/out/.hdfsignore",
+            "/out/_temporary",
+            "/out/_temporary/0",
+            "/out/_temporary/0/part-1",
+            "/out/part-0",
+        ]),
+        walk_excluding(&fs, "/out/**", "logs/\n_temporary/")
+    );
+
+    let fs = output_tree().file("/out/.hdfsignore", b"[ab\n");
+    let errors: Vec<_> = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/out/**"))
+        .ignore_file(IGNORE_FILE)
+        .build()
+        .unwrap()
+        .filter_map(Result::err)
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    assert_eq!(path_list(&["/out/.hdfsignore"]), errors);
+}
+
+fn checkpoint_round_trip(checkpoint: Checkpoint) -> Checkpoint {
+    let mut saved = vec![];
+    checkpoint.write(&mut saved).unwrap();
+    Checkpoint::read(&mut &saved[..]).unwrap()
+}
+
+#[test]
+fn test_resume_from_checkpoints() {
+    let fs = deep_tree();
+    let builder = |order| {
+        WalkBuilder::new(&fs)
+            .with_path(PathBuf::from("/{var,var3,file0}/**"))
+            .order(order)
+            .sort_by(SortBy::Name)
+    };
+    for &order in &[Order::DepthFirst, Order::BreadthFirst] {
+        let all: Vec<_> = builder(order)
+            .build()
+            .unwrap()
+            .map(|item| item.unwrap().path())
+            .collect();
+        assert_eq!(15, all.len());
+
+        // Stopped after each entry, and once done
+        for stop in 0..=all.len() {
+            let mut walk = builder(order).build().unwrap();
+            let mut paths: Vec<_> = walk
+                .by_ref()
+                .take(stop)
+                .map(|item| item.unwrap().path())
+                .collect();
+            let checkpoint = checkpoint_round_trip(walk.checkpoint());
+            let resumed = builder(order).resume(checkpoint).build().unwrap();
+            paths.extend(resumed.map(|item| item.unwrap().path()));
+            assert_eq!(all, paths, "{:?} stopped after {}", order, stop);
+        }
+    }
+
+    let events = |walk: Walk<FakeHdfs>| -> Vec<String> {
+        walk.events()
+            .map(|event| match event.unwrap() {
+                WalkEvent::EnterDir(item) => format!("enter {}", item.path().display()),
+                WalkEvent::File(item) => format!("file {}", item.path().display()),
+                WalkEvent::ExitDir(item) => format!("exit {}", item.path().display()),
+            })
+            .collect()
+    };
+    let all = events(builder(Order::DepthFirst).build().unwrap());
+    let mut walk = builder(Order::DepthFirst).build().unwrap().events();
+    walk.by_ref().take(7).for_each(drop);
+    let checkpoint = checkpoint_round_trip(walk.checkpoint());
+    let resumed = events(
+        builder(Order::DepthFirst)
+            .resume(checkpoint)
+            .build()
+            .unwrap(),
+    );
+    assert_eq!(&all[7..], &resumed[..]);
+
+    let checkpoint = builder(Order::DepthFirst).build().unwrap().checkpoint();
+    let other = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/var/**"))
+        .resume(checkpoint)
+        .build();
+    assert!(other.is_err());
+}
+
+#[test]
+fn test_resume_with_ignore_files() {
+    let fs = FakeHdfs::new()
+        .file("/data/a/x.csv", b"")
+        .file("/data/b/.hdfsignore", b"*.csv\n")
+        .file("/data/b/sub/x.csv", b"")
+        .file("/data/b/sub/y.txt", b"")
+        .file("/data/c/x.csv", b"");
+    let builder = || {
+        WalkBuilder::new(&fs)
+            .with_path(PathBuf::from("/data/**"))
+            .sort_by(SortBy::Name)
+            .ignore_file(IGNORE_FILE)
+    };
+    let all: Vec<_> = builder()
+        .build()
+        .unwrap()
+        .map(|item| item.unwrap().path())
+        .collect();
+    assert!(!all.contains(&PathBuf::from("/data/b/sub/x.csv")));
+
+    for stop in 0..=all.len() {
+        let mut walk = builder().build().unwrap();
+        let mut paths: Vec<_> = walk
+            .by_ref()
+            .take(stop)
+            .map(|item| item.unwrap().path())
+            .collect();
+        let checkpoint = checkpoint_round_trip(walk.checkpoint());
+        let resumed = builder().resume(checkpoint).build().unwrap();
+        paths.extend(resumed.map(|item| item.unwrap().path()));
+        assert_eq!(all, paths, "stopped after {}", stop);
+    }
+}
+
+fn days_ago(days: u64) -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    (now - Duration::from_secs(days * 24 * 60 * 60)).as_millis() as u64
+}
+
+fn walk_with(fs: &FakeHdfs, path: &str, filter: Box<dyn MetadataFilter>) -> Vec<PathBuf> {
+    let mut list: Vec<_> = WalkBuilder::new(fs)
+        .with_path(PathBuf::from(path))
+        .add_metadata_filter(filter)
+        .build()
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    list.sort();
+    list
+}
+
+#[test]
+fn test_metadata_filters() {
+    let fs = FakeHdfs::new()
+        .file_with("/data/old.parquet", 100, "etl", 0o644, 3, 1024)
+        .file_with("/data/new.parquet", 2000, "etl", 0o644, 3, 1024)
+        .file_with("/data/old.csv", 100, "etl", 0o644, 3, 1024)
+        .file_with("/data/other.parquet", 100, "alice", 0o644, 3, 1024);
+    for path in &["/data/old.parquet", "/data/old.csv", "/data/other.parquet"] {
+        HdfsFs::set_times(&fs, Path::new(path), Some(days_ago(40)), None).unwrap();
+    }
+
+    let filter = And(vec![
+        Box::new(GlobFilter::new("**/*.parquet").unwrap()),
+        Box::new(MtimeFilter::parse("+30d").unwrap()),
+        Box::new(OwnerFilter::user("etl")),
+    ]);
+    assert_eq!(
+        walk_with(&fs, "/data/**", Box::new(filter)),
+        path_list(&["/data/old.parquet"])
+    );
+
+    // Directories are walked through without being returned
+    assert_eq!(
+        walk_with(&fs, "/**", Box::new(SizeFilter::parse("+1k").unwrap())),
+        path_list(&["/data/new.parquet"])
+    );
+    assert_eq!(
+        walk_with(&fs, "/**", Box::new(TypeFilter(FileType::Dir))),
+        path_list(&["/", "/data"])
+    );
+
+    let older = MtimeFilter::older_than(Duration::from_secs(3600)).unwrap();
+    let filter = And(vec![
+        Box::new(TypeFilter(FileType::File)),
+        Box::new(Or(vec![
+            Box::new(OwnerFilter::user("alice")),
+            Box::new(Not(Box::new(older))),
+        ])),
+    ]);
+    assert_eq!(
+        walk_with(&fs, "/data/*", Box::new(filter)),
+        path_list(&["/data/new.parquet", "/data/other.parquet"])
+    );
+}
+
+#[test]
+fn test_regex_and_case_insensitive_filters() {
+    let fs = FakeHdfs::new()
+        .file("/table/dt=2024-01-15/part-0.parquet", b"")
+        .file("/table/dt=2024-03-01/part-0.PARQUET", b"")
+        .file("/table/dt=2024-04-01/part-0.parquet", b"")
+        .file("/table/DT=2024-02-01/_SUCCESS", b"");
+
+    let partitions = |filter: RegexFilter| walk_with(&fs, "/table/*", Box::new(filter));
+    assert_eq!(
+        path_list(&["/table/dt=2024-01-15", "/table/dt=2024-03-01"]),
+        partitions(RegexFilter::new("dt=2024-0[1-3]-.*", MatchOn::Name).unwrap())
+    );
+    assert_eq!(
+        path_list(&[
+            "/table/DT=2024-02-01",
+            "/table/dt=2024-01-15",
+            "/table/dt=2024-03-01",
+        ]),
+        partitions(RegexFilter::case_insensitive("dt=2024-0[1-3]-.*", MatchOn::Name).unwrap())
+    );
+    // The whole path or name has to match
+    assert!(partitions(RegexFilter::new("dt=2024-0[1-3]", MatchOn::Name).unwrap()).is_empty());
+    assert_eq!(
+        path_list(&["/table/dt=2024-04-01"]),
+        partitions(RegexFilter::new("/table/dt=.*-04-.*", MatchOn::Path).unwrap())
+    );
+    assert!(RegexFilter::new("dt=(", MatchOn::Name).is_err());
+
+    assert_eq!(
+        path_list(&[
+            "/table/dt=2024-01-15/part-0.parquet",
+            "/table/dt=2024-03-01/part-0.PARQUET",
+            "/table/dt=2024-04-01/part-0.parquet",
+        ]),
+        walk_with(
+            &fs,
+            "/table/**",
+            Box::new(GlobFilter::case_insensitive("/table/*/*.parquet").unwrap())
+        )
+    );
+    assert_eq!(
+        path_list(&["/table/dt=2024-03-01/part-0.PARQUET"]),
+        walk_with(
+            &fs,
+            "/table/**",
+            Box::new(GlobFilter::new("/table/*/*.PARQUET").unwrap())
+        )
+    );
+}
+
+#[test]
+fn test_parse_filters() {
+    assert_eq!(
+        SizeFilter::new(Some((1 << 30) + 1), None),
+        SizeFilter::parse("+1G").unwrap()
+    );
+    assert_eq!(
+        SizeFilter::new(None, Some(10 * 1024 - 1)),
+        SizeFilter::parse("-10k").unwrap()
+    );
+    assert_eq!(
+        SizeFilter::new(Some(1 << 20), Some(1 << 30)),
+        SizeFilter::parse("1M..1G").unwrap()
+    );
+    assert_eq!(
+        SizeFilter::new(Some(100), Some(100)),
+        SizeFilter::parse("100").unwrap()
+    );
+    assert!(SizeFilter::parse("1X").is_err());
+    assert!(SizeFilter::parse("-0").is_err());
+    assert!(MtimeFilter::parse("30d").is_err());
+    assert!(MtimeFilter::parse("+3y").is_err());
+    // Further back than the clock goes
+    assert!(MtimeFilter::parse("+20000000000000w").is_err());
+    assert!(MtimeFilter::newer_than(Duration::MAX).is_err());
+    assert_eq!(
+        TypeFilter(FileType::Symlink),
+        TypeFilter::parse("l").unwrap()
+    );
+    assert!(TypeFilter::parse("x").is_err());
+}
+
+fn walk_in_order<'a>(builder: WalkBuilder<'a, FakeHdfs>, path: &str) -> Vec<(usize, PathBuf)> {
+    builder
+        .with_path(PathBuf::from(path))
+        .build()
+        .unwrap()
+        .map(|e| e.map(|item| (item.depth(), item.path())).unwrap())
+        .collect()
+}
+
+fn depths(list: &[(usize, &str)]) -> Vec<(usize, PathBuf)> {
+    list.iter()
+        .map(|&(depth, path)| (depth, PathBuf::from(path)))
+        .collect()
+}
+
+#[test]
+fn test_depth_limits() {
+    let fs = deep_tree();
+
+    let list = walk_in_order(
+        WalkBuilder::new(&fs)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by(SortBy::Name),
+        "/var3",
+    );
+    assert_eq!(list, depths(&[]), "a plain path is not walked through");
+
+    let list = walk_in_order(
+        WalkBuilder::new(&fs)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by(SortBy::Name),
+        "/var3/**",
+    );
+    assert_eq!(
+        list,
+        depths(&[
+            (1, "/var3/file11"),
+            (1, "/var3/var4"),
+            (1, "/var3/var5"),
+            (1, "/var3/var6"),
+        ])
+    );
+
+    let list = walk_in_order(
+        WalkBuilder::new(&fs).min_depth(2).sort_by(SortBy::Name),
+        "/var3/**",
+    );
+    assert_eq!(list.len(), 6);
+    assert!(list.iter().all(|&(depth, _)| depth == 2));
+
+    // The glob limit applies too
+    let list = walk_in_order(WalkBuilder::new(&fs).max_depth(5), "/var3/*");
+    assert_eq!(list.len(), 4);
+}
+
+#[test]
+fn test_traversal_order() {
+    let fs = deep_tree();
+
+    let list = walk_in_order(WalkBuilder::new(&fs).sort_by(SortBy::Name), "/var3/**");
+    assert_eq!(
+        list,
+        depths(&[
+            (0, "/var3/"),
+            (1, "/var3/file11"),
+            (1, "/var3/var4"),
+            (2, "/var3/var4/file5"),
+            (2, "/var3/var4/file8"),
+            (1, "/var3/var5"),
+            (2, "/var3/var5/file2"),
+            (2, "/var3/var5/file9"),
+            (1, "/var3/var6"),
+            (2, "/var3/var6/file10"),
+            (2, "/var3/var6/file7"),
+        ])
+    );
+
+    let list = walk_in_order(
+        WalkBuilder::new(&fs)
+            .order(Order::BreadthFirst)
+            .sort_by(SortBy::Name),
+        "/var3/**",
+    );
+    assert_eq!(
+        list,
+        depths(&[
+            (0, "/var3/"),
+            (1, "/var3/file11"),
+            (1, "/var3/var4"),
+            (1, "/var3/var5"),
+            (1, "/var3/var6"),
+            (2, "/var3/var4/file5"),
+            (2, "/var3/var4/file8"),
+            (2, "/var3/var5/file2"),
+            (2, "/var3/var5/file9"),
+            (2, "/var3/var6/file10"),
+            (2, "/var3/var6/file7"),
+        ])
+    );
+}
+
+#[test]
+fn test_sort_by_attributes() {
+    let fs = FakeHdfs::new()
+        .file("/data/a", b"12345")
+        .file("/data/b", b"1")
+        .file("/data/c", b"123")
+        .file("/data/d", b"1");
+    for &(path, days) in &[
+        ("/data/a", 1),
+        ("/data/b", 3),
+        ("/data/c", 2),
+        ("/data/d", 4),
+    ] {
+        HdfsFs::set_times(&fs, Path::new(path), Some(days_ago(days)), None).unwrap();
+    }
+
+    let names = |sort_by| -> Vec<PathBuf> {
+        walk_in_order(
+            WalkBuilder::new(&fs).min_depth(1).sort_by(sort_by),
+            "/data/*",
+        )
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect()
+    };
+    assert_eq!(
+        names(SortBy::Size),
+        path_list(&["/data/b", "/data/d", "/data/c", "/data/a"])
+    );
+    assert_eq!(
+        names(SortBy::Modified),
+        path_list(&["/data/d", "/data/b", "/data/c", "/data/a"])
+    );
+}
+
+#[test]
+fn test_skip_current_dir() {
+    let fs = deep_tree()
+        .file("/var3/_temporary/attempt/file", b"")
+        .file("/var3/var4/.snapshot/s1/file5", b"");
+
+    let mut walk = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/var3/**"))
+        .sort_by(SortBy::Name)
+        .build()
+        .unwrap();
+    let mut list = vec![];
+    while let Some(item) = walk.next() {
+        let path = item.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+        if name == "_temporary" || name == ".snapshot" || name == "var5" {
+            walk.skip_current_dir();
+        }
+        list.push(path);
+    }
+
+    assert_eq!(
+        list,
+        path_list(&[
+            "/var3/",
+            "/var3/_temporary",
+            "/var3/file11",
+            "/var3/var4",
+            "/var3/var4/.snapshot",
+            "/var3/var4/file5",
+            "/var3/var4/file8",
+            "/var3/var5",
+            "/var3/var6",
+            "/var3/var6/file10",
+            "/var3/var6/file7",
+        ])
+    );
+}
+
+#[test]
+fn test_walk_events() {
+    let fs = FakeHdfs::new()
+        .file("/data/a/part-0", b"")
+        .file("/data/a/part-1", b"")
+        .file("/data/b/part-0", b"")
+        .file("/data/b/_temporary/part-1", b"")
+        .file("/data/file", b"");
+
+    let mut events = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/data/**"))
+        .sort_by(SortBy::Name)
+        .build()
+        .unwrap()
+        .events();
+    let mut list = vec![];
+    // Files per directory, subdirectories included
+    let mut counts = vec![];
+    let mut totals = vec![];
+    while let Some(event) = events.next() {
+        match event.unwrap() {
+            WalkEvent::EnterDir(item) => {
+                if item.path().ends_with("_temporary") {
+                    events.skip_current_dir();
+                }
+                list.push(format!("enter {}", item.path().display()));
+                counts.push(0);
+            }
+            WalkEvent::File(item) => {
+                list.push(format!("file {}", item.path().display()));
+                *counts.last_mut().unwrap() += 1;
+            }
+            WalkEvent::ExitDir(item) => {
+                list.push(format!("exit {}", item.path().display()));
+                let count = counts.pop().unwrap();
+                if let Some(parent) = counts.last_mut() {
+                    *parent += count;
+                }
+                totals.push((item.path(), count));
+            }
+        }
+    }
+
+    assert_eq!(
+        list,
+        vec![
+            "enter /data/",
+            "enter /data/a",
+            "file /data/a/part-0",
+            "file /data/a/part-1",
+            "exit /data/a",
+            "enter /data/b",
+            "enter /data/b/_temporary",
+            "exit /data/b/_temporary",
+            "file /data/b/part-0",
+            "exit /data/b",
+            "file /data/file",
+            "exit /data/",
+        ]
+    );
+    assert_eq!(
+        totals,
+        vec![
+            (PathBuf::from("/data/a"), 2),
+            (PathBuf::from("/data/b/_temporary"), 0),
+            (PathBuf::from("/data/b"), 1),
+            (PathBuf::from("/data/"), 4),
+        ]
+    );
+}
+
+fn parallel_walk<T>(
+    builder: ParallelWalkBuilder<T>,
+    path: &str,
+) -> Vec<Result<PathBuf, WalkError<T::Error>>>
+where
+    T: FileSystem + Sync,
+    T::Error: Send,
+{
+    let mut list = vec![];
+    builder
+        .with_path(PathBuf::from(path))
+        .build()
+        .unwrap()
+        .run(|item| list.push(item.map(|item| item.path())));
+    list
+}
+
+#[test]
+fn test_parallel_walk_matches_walk() {
+    let fs = deep_tree().dir("/var3/var6/var7");
+
+    for path in &[
+        "/",
+        "/var3/**",
+        "/**",
+        "/*/*/*",
+        "/var3/*/file?",
+        "/var3/var4",
+    ] {
+        let mut expected = vec![];
+        let walk = WalkBuilder::new(&fs)
+            .with_path(PathBuf::from(path))
+            .sort_by(SortBy::Name)
+            .build()
+            .unwrap();
+        for item in walk {
+            expected.push(item.unwrap().path());
+        }
+
+        let list: Vec<_> =
+            parallel_walk(ParallelWalkBuilder::new(&fs).threads(4).sorted(true), path)
+                .into_iter()
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(list, expected, "{}", path);
+    }
+}
+
+#[test]
+fn test_parallel_walk_options() {
+    let fs = deep_tree();
+
+    let mut list: Vec<_> = parallel_walk(
+        ParallelWalkBuilder::new(&fs)
+            .threads(3)
+            .queue_size(1)
+            .min_depth(2)
+            .max_depth(2),
+        "/**",
+    )
+    .into_iter()
+    .map(Result::unwrap)
+    .collect();
+    list.sort();
+    assert_eq!(
+        list,
+        path_list(&[
+            "/var/file1",
+            "/var/file2",
+            "/var2/file3",
+            "/var2/file4",
+            "/var3/file11",
+            "/var3/var4",
+            "/var3/var5",
+            "/var3/var6",
+        ])
+    );
+
+    let list: Vec<_> = parallel_walk(
+        ParallelWalkBuilder::new(&fs)
+            .add_filter(Box::new(GlobFilter::new("**/file1*").unwrap()))
+            .add_metadata_filter(Box::new(TypeFilter(FileType::File)))
+            .sorted(true),
+        "/**",
+    )
+    .into_iter()
+    .map(Result::unwrap)
+    .collect();
+    assert_eq!(
+        list,
+        path_list(&["/var/file1", "/var3/file11", "/var3/var6/file10"])
+    );
+
+    let filter = And(vec![
+        Box::new(TypeFilter(FileType::File)),
+        Box::new(Or(vec![
+            Box::new(GlobFilter::new("/var/**").unwrap()),
+            Box::new(Not(Box::new(NameFilter::new("file[0-9]").unwrap()))),
+        ])),
+    ]);
+    let list: Vec<_> = parallel_walk(
+        ParallelWalkBuilder::new(&fs)
+            .add_metadata_filter(Box::new(filter))
+            .sorted(true),
+        "/**",
+    )
+    .into_iter()
+    .map(Result::unwrap)
+    .collect();
+    assert_eq!(
+        list,
+        path_list(&[
+            "/var/file1",
+            "/var/file2",
+            "/var3/file11",
+            "/var3/var6/file10"
+        ])
+    );
+}
+
+#[test]
+fn test_parallel_walk_lists_concurrently() {
+    let mut tree = FakeHdfs::new();
+    for i in 0..20 {
+        tree = tree.file(&format!("/data/{}/file", i), b"");
+    }
+    let fs = FaultyFs::new(tree, 0).latency(Duration::from_millis(50));
+
+    let start = std::time::Instant::now();
+    let list = parallel_walk(ParallelWalkBuilder::new(&fs).threads(10), "/data/**");
+    assert_eq!(41, list.len());
+    assert!(list.iter().all(Result::is_ok));
+    // 21 listings one after the other take more than a second
+    assert!(
+        start.elapsed() < Duration::from_millis(600),
+        "{:?}",
+        start.elapsed()
+    );
+}
+
+#[test]
+fn test_parallel_walk_surfaces_errors() {
+    let fs = FaultyFs::new(deep_tree(), 0).script(vec![Fault::None, Fault::Standby]);
+
+    let list = parallel_walk(ParallelWalkBuilder::new(&fs).sorted(true), "/var3/*");
+    assert_eq!(1, list.len());
+    match list[0] {
+        Err(ref e) => match *e.error() {
+            Error::Hdfs(HdfsError::RemoteException(ref class, _)) => {
+                assert_eq!("StandbyException", class);
+                assert_eq!(Path::new("/var3"), e.path());
+            }
+            ref other => panic!("unexpected error {:?}", other),
+        },
+        ref other => panic!("unexpected {:?}", other.as_ref().map(|_| ())),
+    }
+}
+
+fn permission_denied() -> HdfsError {
+    HdfsError::RemoteException(
+        String::from("AccessControlException"),
+        String::from("Permission denied"),
+    )
+}
+
+fn warehouse() -> FakeHdfs {
+    let fs = deep_tree();
+    fs.inject_error(Operation::List, "/var2", permission_denied);
+    fs.inject_error(Operation::List, "/var3/var5", permission_denied);
+    fs
+}
+
+fn error_paths(errors: &[WalkError<Error>]) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = errors.iter().map(|e| e.path().to_path_buf()).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_error_policies() {
+    let fs = warehouse();
+
+    // Skip returns the errors along with the rest
+    let (mut found, mut errors) = (vec![], vec![]);
+    let walk = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/**"))
+        .build()
+        .unwrap();
+    for item in walk {
+        match item {
+            Ok(item) => found.push(item.path()),
+            Err(e) => errors.push(e),
+        }
+    }
+    assert_eq!(error_paths(&errors), path_list(&["/var2", "/var3/var5"]));
+    assert_eq!(15, found.len());
+    assert!(found.contains(&PathBuf::from("/var2")));
+    assert!(!found.contains(&PathBuf::from("/var2/file3")));
+
+    // Collect keeps them for the end
+    let mut walk = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/**"))
+        .error_policy(ErrorPolicy::Collect)
+        .build()
+        .unwrap();
+    let found: Result<Vec<_>, _> = (&mut walk).collect();
+    assert_eq!(15, found.unwrap().len());
+    assert_eq!(
+        error_paths(walk.errors()),
+        path_list(&["/var2", "/var3/var5"])
+    );
+    assert_eq!(
+        "/var2: Hdfs(RemoteException(\"AccessControlException\", \"Permission denied\"))",
+        walk.into_errors()
+            .into_iter()
+            .find(|e| e.path() == Path::new("/var2"))
+            .unwrap()
+            .to_string()
+    );
+
+    // Abort stops at the first one
+    let list: Vec<_> = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/**"))
+        .sort_by(SortBy::Name)
+        .error_policy(ErrorPolicy::Abort)
+        .build()
+        .unwrap()
+        .collect();
+    assert_eq!(7, list.len());
+    assert_eq!(
+        Path::new("/var2"),
+        list.last().unwrap().as_ref().unwrap_err().path()
+    );
+}
+
+#[test]
+fn test_parallel_error_policies() {
+    let fs = warehouse();
+
+    let mut found = vec![];
+    let errors = ParallelWalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/**"))
+        .error_policy(ErrorPolicy::Collect)
+        .build()
+        .unwrap()
+        .run(|item| found.push(item.unwrap().path()));
+    assert_eq!(15, found.len());
+    assert_eq!(error_paths(&errors), path_list(&["/var2", "/var3/var5"]));
+
+    let list = parallel_walk(
+        ParallelWalkBuilder::new(&fs)
+            .threads(1)
+            .error_policy(ErrorPolicy::Abort),
+        "/**",
+    );
+    assert!(list.last().unwrap().is_err());
+    assert_eq!(1, list.iter().filter(|item| item.is_err()).count());
+}
+
+fn unreadable_root() -> FakeHdfs {
+    let fs = deep_tree();
+    fs.inject_error(Operation::Stat, "/var2", permission_denied);
+    fs
+}
+
+#[test]
+fn test_failing_roots() {
+    let fs = unreadable_root();
+    let builder = |policy| {
+        WalkBuilder::new(&fs)
+            .with_path(PathBuf::from("/{var2,var}/**"))
+            .sort_by(SortBy::Name)
+            .error_policy(policy)
+            .build()
+            .unwrap()
+    };
+
+    let list: Vec<_> = builder(ErrorPolicy::Skip).collect();
+    assert_eq!(4, list.len());
+    assert_eq!(Path::new("/var2"), list[0].as_ref().unwrap_err().path());
+    assert_eq!(Path::new("/var/"), list[1].as_ref().unwrap().path());
+
+    let mut walk = builder(ErrorPolicy::Collect);
+    let found: Result<Vec<_>, _> = (&mut walk).collect();
+    assert_eq!(3, found.unwrap().len());
+    assert_eq!(error_paths(walk.errors()), path_list(&["/var2"]));
+
+    let list: Vec<_> = builder(ErrorPolicy::Abort).collect();
+    assert_eq!(1, list.len());
+    assert_eq!(Path::new("/var2"), list[0].as_ref().unwrap_err().path());
+
+    // Missing roots are not failures
+    let list: Vec<_> = WalkBuilder::new(&fs)
+        .with_path(PathBuf::from("/missing"))
+        .error_policy(ErrorPolicy::Abort)
+        .build()
+        .unwrap()
+        .collect();
+    assert!(list.is_empty());
+}
+
+#[test]
+fn test_parallel_failing_roots() {
+    let fs = unreadable_root();
+    let builder = |policy| {
+        ParallelWalkBuilder::new(&fs)
+            .sorted(true)
+            .error_policy(policy)
+    };
+
+    let list = parallel_walk(builder(ErrorPolicy::Skip), "/{var2,var}/**");
+    assert_eq!(4, list.len());
+    assert_eq!(
+        vec![Path::new("/var2")],
+        list.iter()
+            .filter_map(|item| item.as_ref().err().map(|e| e.path()))
+            .collect::<Vec<_>>()
+    );
+
+    let mut found = vec![];
+    let errors = builder(ErrorPolicy::Collect)
+        .with_path(PathBuf::from("/{var2,var}/**"))
+        .build()
+        .unwrap()
+        .run(|item| found.push(item.unwrap().path()));
+    assert_eq!(3, found.len());
+    assert_eq!(error_paths(&errors), path_list(&["/var2"]));
+
+    let list = parallel_walk(builder(ErrorPolicy::Abort), "/{var2,var}/**");
+    assert_eq!(1, list.len());
+    assert_eq!(Path::new("/var2"), list[0].as_ref().unwrap_err().path());
+
+    assert!(parallel_walk(builder(ErrorPolicy::Abort), "/missing").is_empty());
+}
+
+fn parallel_checkpoint_round_trip(checkpoint: ParallelCheckpoint) -> ParallelCheckpoint {
+    let mut saved = vec![];
+    checkpoint.write(&mut saved).unwrap();
+    ParallelCheckpoint::read(&mut &saved[..]).unwrap()
+}
+
+#[test]
+fn test_resume_parallel_walk() {
+    let fs = deep_tree()
+        .dir("/var3/var6/var7")
+        .file("/var3/bad/file", b"");
+    fs.inject_error(Operation::List, "/var3/bad", || {
+        HdfsError::Unsupported(String::from("listing"))
+    });
+    let builder = |sorted| {
+        ParallelWalkBuilder::new(&fs)
+            .with_path(PathBuf::from("/{var,var3,file0}/**"))
+            .threads(4)
+            .queue_size(2)
+            .sorted(sorted)
+    };
+    let describe = |item: Result<WalkItem, WalkError<Error>>| match item {
+        Ok(item) => item.path().display().to_string(),
+        Err(e) => format!("error {}", e.path().display()),
+    };
+    for &sorted in &[false, true] {
+        let mut all = vec![];
+        builder(sorted)
+            .build()
+            .unwrap()
+            .run(|item| all.push(describe(item)));
+        assert_eq!(18, all.len());
+
+        // Stopped after each entry, and once done
+        for stop in 0..=all.len() {
+            let mut returned = vec![];
+            let mut checkpoint = None;
+            builder(sorted)
+                .build()
+                .unwrap()
+                .run_with_progress(|item, progress| {
+                    if returned.len() < stop {
+                        returned.push(describe(item));
+                        checkpoint = Some(progress.checkpoint());
+                    }
+                });
+            let resumed = match checkpoint {
+                Some(checkpoint) => {
+                    builder(sorted).resume(parallel_checkpoint_round_trip(checkpoint))
+                }
+                None => builder(sorted),
+            };
+            resumed
+                .build()
+                .unwrap()
+                .run(|item| returned.push(describe(item)));
+            let mut expected = all.clone();
+            if !sorted {
+                returned.sort();
+                expected.sort();
+            }
+            assert_eq!(
+                expected, returned,
+                "sorted: {}, stopped after {}",
+                sorted, stop
+            );
+        }
+    }
+
+    let mut checkpoint = None;
+    builder(false)
+        .build()
+        .unwrap()
+        .run_with_progress(|_, progress| checkpoint = Some(progress.checkpoint()));
+    let checkpoint = checkpoint.unwrap();
+    assert!(builder(true).resume(checkpoint).build().is_err());
+}